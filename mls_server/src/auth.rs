@@ -0,0 +1,635 @@
+// ─────────────────────────────────────────────────────────────
+// AUTH – join firmati con la chiave del KeyPackage
+// ─────────────────────────────────────────────────────────────
+//
+// Il server resta cieco sui segreti del gruppo: legge solo la parte
+// pubblica del KeyPackage (suite, chiave di firma, credential) e
+// verifica firme con quella chiave.
+
+use std::fmt;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ed25519_dalek::Verifier as _; // stesso trait `signature` anche per p256
+
+// Etichette SignWithLabel (RFC 9420 §5.1.2)
+const KEY_PACKAGE_LABEL: &[u8] = b"MLS 1.0 KeyPackageTBS";
+const JOIN_LABEL: &[u8] = b"MLS 1.0 MLS-DS join";
+const UPLOAD_LABEL: &[u8] = b"MLS 1.0 MLS-DS keypackages";
+const LEAVE_LABEL: &[u8] = b"MLS 1.0 MLS-DS leave";
+const CLAIM_LABEL: &[u8] = b"MLS 1.0 MLS-DS claim";
+const COMMIT_LABEL: &[u8] = b"MLS 1.0 MLS-DS commit";
+const PROPOSAL_LABEL: &[u8] = b"MLS 1.0 MLS-DS proposal";
+const ACK_LABEL: &[u8] = b"MLS 1.0 MLS-DS ack";
+
+const CREDENTIAL_BASIC: u16 = 0x0001;
+const CREDENTIAL_X509: u16 = 0x0002;
+// id-at-commonName (2.5.4.3)
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const EXTENSION_LAST_RESORT: u16 = 0x000a;
+
+#[derive(Debug)]
+pub enum AuthError {
+    Malformed(&'static str),
+    UnsupportedCiphersuite(u16),
+    IdentityMismatch,
+    BadSignature,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Malformed(what) => write!(f, "KeyPackage non valido: {what}"),
+            AuthError::UnsupportedCiphersuite(cs) => write!(f, "ciphersuite {cs:#06x} non supportata"),
+            AuthError::IdentityMismatch => write!(f, "identità diversa da quella della credential"),
+            AuthError::BadSignature => write!(f, "firma non valida"),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+// KEYPACKAGE (solo i campi pubblici che servono)
+// ─────────────────────────────────────────────────────────────
+
+pub struct KeyPackageInfo {
+    pub ciphersuite: u16,
+    pub signature_key: Vec<u8>,
+    // Basic: l'identità della credential; X.509: il CN del subject del
+    // certificato leaf, che deve avere la stessa chiave di firma
+    pub identity: Vec<u8>,
+    // Estensione last_resort: il KeyPackage può essere usato più volte
+    pub last_resort: bool,
+    // KeyPackage TLS nudo, senza l'involucro JSON della webapp
+    pub bytes: Vec<u8>,
+}
+
+// Lettore TLS minimale con le lunghezze varint di MLS (RFC 9420 §2.1.2)
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], AuthError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.buf.len())
+            .ok_or(AuthError::Malformed("troncato"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, AuthError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AuthError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn varint(&mut self) -> Result<usize, AuthError> {
+        let first = self.u8()?;
+        let mut len = (first & 0x3f) as usize;
+        let extra = match first >> 6 {
+            0 => 0,
+            1 => 1,
+            2 => 3,
+            _ => return Err(AuthError::Malformed("lunghezza varint non valida")),
+        };
+        for &b in self.take(extra)? {
+            len = (len << 8) | b as usize;
+        }
+        Ok(len)
+    }
+
+    fn vec(&mut self) -> Result<&'a [u8], AuthError> {
+        let len = self.varint()?;
+        self.take(len)
+    }
+}
+
+fn parse_key_package(bytes: &[u8]) -> Result<KeyPackageInfo, AuthError> {
+    let mut r = Reader { buf: bytes, pos: 0 };
+
+    if r.u16()? != 0x0001 {
+        return Err(AuthError::Malformed("versione MLS"));
+    }
+    let ciphersuite = r.u16()?;
+    r.vec()?; // init_key
+
+    // LeafNode
+    r.vec()?; // encryption_key
+    let signature_key = r.vec()?.to_vec();
+    let identity = match r.u16()? {
+        CREDENTIAL_BASIC => r.vec()?.to_vec(),
+        CREDENTIAL_X509 => {
+            let mut chain = Reader { buf: r.vec()?, pos: 0 };
+            let (common_name, public_key) = x509_leaf(chain.vec()?)?;
+            if public_key != signature_key {
+                return Err(AuthError::Malformed("certificato leaf con un'altra chiave"));
+            }
+            common_name.to_vec()
+        }
+        _ => return Err(AuthError::Malformed("credential non supportata")),
+    };
+    for _ in 0..5 {
+        r.vec()?; // capabilities
+    }
+    match r.u8()? {
+        1 => {
+            r.take(16)?; // lifetime
+        }
+        2 => {}
+        3 => {
+            r.vec()?; // parent_hash
+        }
+        _ => return Err(AuthError::Malformed("leaf_node_source")),
+    }
+    r.vec()?; // estensioni della foglia
+    r.vec()?; // firma della foglia
+
+    let extensions = r.vec()?;
+    let tbs_end = r.pos;
+    let signature = r.vec()?;
+    if r.pos != r.buf.len() {
+        return Err(AuthError::Malformed("byte in eccesso"));
+    }
+
+    verify_with_label(ciphersuite, &signature_key, KEY_PACKAGE_LABEL, &r.buf[..tbs_end], signature)?;
+
+    let mut ext = Reader { buf: extensions, pos: 0 };
+    let mut last_resort = false;
+    while ext.pos < ext.buf.len() {
+        last_resort |= ext.u16()? == EXTENSION_LAST_RESORT;
+        ext.vec()?;
+    }
+
+    Ok(KeyPackageInfo {
+        ciphersuite,
+        signature_key,
+        identity,
+        last_resort,
+        bytes: bytes.to_vec(),
+    })
+}
+
+// Lettura DER minimale: (tag, contenuto, resto) del primo TLV di `input`
+fn der_tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), AuthError> {
+    let malformed = || AuthError::Malformed("DER");
+    let (&tag, rest) = input.split_first().ok_or_else(malformed)?;
+    let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return Err(malformed());
+        }
+        let len = rest[..n].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return Err(malformed());
+    }
+    Ok((tag, &rest[..len], &rest[len..]))
+}
+
+// CN del subject e chiave pubblica raw di un certificato X.509 DER
+// (RFC 5280 §4.1). La catena la valida l'Authentication Service: qui
+// basta legare l'identità alla chiave che firma le richieste.
+fn x509_leaf(cert: &[u8]) -> Result<(&[u8], &[u8]), AuthError> {
+    let malformed = || AuthError::Malformed("certificato X.509");
+    let (0x30, cert, _) = der_tlv(cert)? else { return Err(malformed()) };
+    let (0x30, mut tbs, _) = der_tlv(cert)? else { return Err(malformed()) };
+
+    // version [0] opzionale, poi serial, signature, issuer, validity
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_tlv(tbs)?.2;
+    }
+    for _ in 0..4 {
+        tbs = der_tlv(tbs)?.2;
+    }
+    let (0x30, mut subject, tbs) = der_tlv(tbs)? else { return Err(malformed()) };
+
+    let (0x30, spki, _) = der_tlv(tbs)? else { return Err(malformed()) };
+    let (0x30, _, spki) = der_tlv(spki)? else { return Err(malformed()) }; // algorithm
+    let (0x03, bits, _) = der_tlv(spki)? else { return Err(malformed()) };
+    // Primo byte: bit inutilizzati, sempre 0 per le chiavi supportate
+    let Some((0, public_key)) = bits.split_first() else { return Err(malformed()) };
+
+    // Name: SEQUENCE OF RelativeDistinguishedName (SET OF AttributeTypeAndValue)
+    while !subject.is_empty() {
+        let (0x31, mut rdn, rest) = der_tlv(subject)? else { return Err(malformed()) };
+        subject = rest;
+        while !rdn.is_empty() {
+            let (0x30, attribute, rest) = der_tlv(rdn)? else { return Err(malformed()) };
+            rdn = rest;
+            let (0x06, oid, value) = der_tlv(attribute)? else { return Err(malformed()) };
+            if oid == OID_COMMON_NAME {
+                return Ok((der_tlv(value)?.1, public_key));
+            }
+        }
+    }
+    Err(AuthError::Malformed("certificato senza CN"))
+}
+
+// Il campo `key_package` del join è Base64 di {"mls": <KeyPackage Base64>, ...}
+// come lo invia la webapp, oppure direttamente Base64 del KeyPackage.
+pub fn decode_key_package(field: &str) -> Result<KeyPackageInfo, AuthError> {
+    let outer = BASE64
+        .decode(field)
+        .map_err(|_| AuthError::Malformed("Base64"))?;
+
+    let raw = match serde_json::from_slice::<serde_json::Value>(&outer) {
+        Ok(json) => {
+            let mls = json
+                .get("mls")
+                .and_then(|v| v.as_str())
+                .ok_or(AuthError::Malformed("campo mls mancante"))?;
+            BASE64.decode(mls).map_err(|_| AuthError::Malformed("Base64"))?
+        }
+        Err(_) => outer,
+    };
+
+    parse_key_package(&raw)
+}
+
+// ─────────────────────────────────────────────────────────────
+// FIRME
+// ─────────────────────────────────────────────────────────────
+
+fn push_vec(out: &mut Vec<u8>, bytes: &[u8]) {
+    let len = bytes.len();
+    if len < 0x40 {
+        out.push(len as u8);
+    } else if len < 0x4000 {
+        out.extend_from_slice(&(0x4000 | len as u16).to_be_bytes());
+    } else {
+        out.extend_from_slice(&(0x8000_0000 | len as u32).to_be_bytes());
+    }
+    out.extend_from_slice(bytes);
+}
+
+// SignContent { opaque label<V>; opaque content<V>; }
+fn verify_with_label(
+    ciphersuite: u16,
+    public_key: &[u8],
+    label: &[u8],
+    content: &[u8],
+    signature: &[u8],
+) -> Result<(), AuthError> {
+    let mut message = Vec::with_capacity(label.len() + content.len() + 8);
+    push_vec(&mut message, label);
+    push_vec(&mut message, content);
+
+    match ciphersuite {
+        // Ed25519
+        0x0001 | 0x0003 => {
+            let key: [u8; 32] = public_key.try_into().map_err(|_| AuthError::BadSignature)?;
+            let key = ed25519_dalek::VerifyingKey::from_bytes(&key).map_err(|_| AuthError::BadSignature)?;
+            let sig = ed25519_dalek::Signature::from_slice(signature).map_err(|_| AuthError::BadSignature)?;
+            key.verify(&message, &sig).map_err(|_| AuthError::BadSignature)
+        }
+        // ECDSA P-256 / SHA-256, firma DER
+        0x0002 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key).map_err(|_| AuthError::BadSignature)?;
+            let sig = p256::ecdsa::DerSignature::from_bytes(signature).map_err(|_| AuthError::BadSignature)?;
+            key.verify(&message, &sig).map_err(|_| AuthError::BadSignature)
+        }
+        other => Err(AuthError::UnsupportedCiphersuite(other)),
+    }
+}
+
+// JoinTBS { opaque room_id<V>; opaque identity<V>; uint64 timestamp; }
+// Deve coincidere con WasmMlsClient::sign_join (e sign_leave e sign_ack,
+// stessa struttura; per l'ack al posto del timestamp c'è l'ultimo seq letto).
+fn join_tbs(room_id: &str, identity: &str, timestamp: u64) -> Vec<u8> {
+    let mut tbs = Vec::new();
+    push_vec(&mut tbs, room_id.as_bytes());
+    push_vec(&mut tbs, identity.as_bytes());
+    tbs.extend_from_slice(&timestamp.to_be_bytes());
+    tbs
+}
+
+// UploadTBS { opaque identity<V>; uint64 timestamp; }
+// Deve coincidere con WasmMlsClient::sign_key_package_upload.
+fn upload_tbs(identity: &str, timestamp: u64) -> Vec<u8> {
+    let mut tbs = Vec::new();
+    push_vec(&mut tbs, identity.as_bytes());
+    tbs.extend_from_slice(&timestamp.to_be_bytes());
+    tbs
+}
+
+// ClaimTBS { opaque room_id<V>; opaque claimer<V>; opaque identity<V>; uint64 timestamp; }
+// Deve coincidere con WasmMlsClient::sign_claim.
+fn claim_tbs(room_id: &str, claimer: &str, identity: &str, timestamp: u64) -> Vec<u8> {
+    let mut tbs = Vec::new();
+    push_vec(&mut tbs, room_id.as_bytes());
+    push_vec(&mut tbs, claimer.as_bytes());
+    push_vec(&mut tbs, identity.as_bytes());
+    tbs.extend_from_slice(&timestamp.to_be_bytes());
+    tbs
+}
+
+// HandshakeTBS { opaque room_id<V>; opaque sender<V>; uint64 epoch; opaque message<V>; }
+// Deve coincidere con WasmMlsClient::sign_commit e sign_proposal.
+fn handshake_tbs(room_id: &str, sender: &str, epoch: u64, message: &[u8]) -> Vec<u8> {
+    let mut tbs = Vec::new();
+    push_vec(&mut tbs, room_id.as_bytes());
+    push_vec(&mut tbs, sender.as_bytes());
+    tbs.extend_from_slice(&epoch.to_be_bytes());
+    push_vec(&mut tbs, message);
+    tbs
+}
+
+fn verify_request(
+    kp: &KeyPackageInfo,
+    identity: &str,
+    label: &[u8],
+    tbs: &[u8],
+    signature: &str,
+) -> Result<(), AuthError> {
+    if kp.identity != identity.as_bytes() {
+        return Err(AuthError::IdentityMismatch);
+    }
+
+    let signature = BASE64.decode(signature).map_err(|_| AuthError::BadSignature)?;
+    verify_with_label(kp.ciphersuite, &kp.signature_key, label, tbs, &signature)
+}
+
+/// Verifica la firma del join (Base64) con la chiave del KeyPackage.
+pub fn verify_join(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    identity: &str,
+    timestamp: u64,
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, identity, JOIN_LABEL, &join_tbs(room_id, identity, timestamp), signature)
+}
+
+/// Verifica la firma di una pubblicazione di KeyPackage nella directory.
+pub fn verify_upload(
+    kp: &KeyPackageInfo,
+    identity: &str,
+    timestamp: u64,
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, identity, UPLOAD_LABEL, &upload_tbs(identity, timestamp), signature)
+}
+
+/// Verifica la firma del leave (Base64) con la chiave del KeyPackage
+/// con cui il membro è entrato.
+pub fn verify_leave(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    identity: &str,
+    timestamp: u64,
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, identity, LEAVE_LABEL, &join_tbs(room_id, identity, timestamp), signature)
+}
+
+/// Verifica la firma di chi prende un KeyPackage di `identity` dalla
+/// directory, con la chiave del KeyPackage con cui `claimer` è nella stanza.
+pub fn verify_claim(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    claimer: &str,
+    identity: &str,
+    timestamp: u64,
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, claimer, CLAIM_LABEL, &claim_tbs(room_id, claimer, identity, timestamp), signature)
+}
+
+/// Verifica la firma di un Commit con la chiave del KeyPackage con cui
+/// `sender` è entrato: solo un membro può occupare l'epoch.
+pub fn verify_commit(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    sender: &str,
+    epoch: u64,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, sender, COMMIT_LABEL, &handshake_tbs(room_id, sender, epoch, message), signature)
+}
+
+/// Come verify_commit, per le Proposal.
+pub fn verify_proposal(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    sender: &str,
+    epoch: u64,
+    message: &[u8],
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, sender, PROPOSAL_LABEL, &handshake_tbs(room_id, sender, epoch, message), signature)
+}
+
+/// Verifica la conferma di lettura della coda fino a `after`. Non serve un
+/// timestamp: ripetere un ack già applicato non cambia nulla.
+pub fn verify_ack(
+    kp: &KeyPackageInfo,
+    room_id: &str,
+    identity: &str,
+    after: u64,
+    signature: &str,
+) -> Result<(), AuthError> {
+    verify_request(kp, identity, ACK_LABEL, &join_tbs(room_id, identity, after), signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mls::{Client, ED25519, Fixture, P256, x509_certificate, x509_credential};
+    use openmls::prelude::PURE_PLAINTEXT_WIRE_FORMAT_POLICY;
+    use openmls::prelude::tls_codec::{Serialize as _, VLBytes};
+
+    // TBS costruita con tls_codec come fa WasmMlsClient, non con push_vec
+    fn tls_tbs(fields: &[&str], timestamp: u64) -> Vec<u8> {
+        let mut tbs = Vec::new();
+        for field in fields {
+            tbs.extend(VLBytes::new(field.as_bytes().to_vec()).tls_serialize_detached().unwrap());
+        }
+        tbs.extend_from_slice(&timestamp.to_be_bytes());
+        tbs
+    }
+
+    fn sign(client: &Client, label: &[u8], tbs: &[u8]) -> String {
+        BASE64.encode(client.sign_with_label(label, tbs))
+    }
+
+    #[test]
+    fn key_package_round_trip() {
+        for (ciphersuite, id) in [(ED25519, 0x0001), (P256, 0x0002)] {
+            for last_resort in [false, true] {
+                let client = Client::new("alice", ciphersuite);
+                let raw = client.key_package_bytes(last_resort);
+
+                let wrapped = BASE64.encode(serde_json::json!({ "mls": BASE64.encode(&raw) }).to_string());
+                for field in [BASE64.encode(&raw), wrapped] {
+                    let kp = decode_key_package(&field).unwrap();
+                    assert_eq!(kp.ciphersuite, id);
+                    assert_eq!(kp.identity, b"alice");
+                    assert_eq!(kp.signature_key, client.signer.public());
+                    assert_eq!(kp.last_resort, last_resort);
+                    assert_eq!(kp.bytes, raw);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn key_package_truncated_or_padded() {
+        let raw = Client::new("alice", ED25519).key_package_bytes(true);
+
+        for cut in 0..raw.len() {
+            assert!(
+                matches!(parse_key_package(&raw[..cut]), Err(AuthError::Malformed(_))),
+                "prefisso di {cut} byte accettato"
+            );
+        }
+
+        let mut padded = raw.clone();
+        padded.push(0);
+        assert!(matches!(parse_key_package(&padded), Err(AuthError::Malformed("byte in eccesso"))));
+    }
+
+    #[test]
+    fn key_package_tampered() {
+        let raw = Client::new("alice", ED25519).key_package_bytes(false);
+
+        // Ultimo byte della firma, poi un byte della chiave di init
+        for pos in [raw.len() - 1, 8] {
+            let mut bytes = raw.clone();
+            bytes[pos] ^= 0x01;
+            assert!(matches!(parse_key_package(&bytes), Err(AuthError::BadSignature)));
+        }
+    }
+
+    #[test]
+    fn key_package_malformed_json() {
+        let field = BASE64.encode(r#"{"altro": "x"}"#);
+        assert!(matches!(decode_key_package(&field), Err(AuthError::Malformed(_))));
+        assert!(matches!(decode_key_package("non è base64"), Err(AuthError::Malformed("Base64"))));
+    }
+
+    #[test]
+    fn request_signatures() {
+        // Una stanza con ID lungo fa usare le lunghezze varint a 2 byte
+        let room_id = "r".repeat(100);
+        let ts = 1_700_000_000;
+
+        for ciphersuite in [ED25519, P256] {
+            let alice = Client::new("alice", ciphersuite);
+            let kp = parse_key_package(&alice.key_package_bytes(false)).unwrap();
+
+            let join = sign(&alice, JOIN_LABEL, &tls_tbs(&[&room_id, "alice"], ts));
+            verify_join(&kp, &room_id, "alice", ts, &join).unwrap();
+            assert!(matches!(verify_join(&kp, &room_id, "alice", ts + 1, &join), Err(AuthError::BadSignature)));
+            assert!(matches!(verify_join(&kp, "altra", "alice", ts, &join), Err(AuthError::BadSignature)));
+            // Stessa TBS, etichetta diversa: un join non vale come leave
+            assert!(matches!(verify_leave(&kp, &room_id, "alice", ts, &join), Err(AuthError::BadSignature)));
+
+            let leave = sign(&alice, LEAVE_LABEL, &tls_tbs(&[&room_id, "alice"], ts));
+            verify_leave(&kp, &room_id, "alice", ts, &leave).unwrap();
+
+            let upload = sign(&alice, UPLOAD_LABEL, &tls_tbs(&["alice"], ts));
+            verify_upload(&kp, "alice", ts, &upload).unwrap();
+
+            let ack = sign(&alice, ACK_LABEL, &tls_tbs(&[&room_id, "alice"], 42));
+            verify_ack(&kp, &room_id, "alice", 42, &ack).unwrap();
+            assert!(matches!(verify_ack(&kp, &room_id, "alice", 43, &ack), Err(AuthError::BadSignature)));
+
+            let claim = sign(&alice, CLAIM_LABEL, &tls_tbs(&[&room_id, "alice", "bob"], ts));
+            verify_claim(&kp, &room_id, "alice", "bob", ts, &claim).unwrap();
+            assert!(matches!(
+                verify_claim(&kp, &room_id, "alice", "carol", ts, &claim),
+                Err(AuthError::BadSignature)
+            ));
+        }
+    }
+
+    // HandshakeTBS con tls_codec, come WasmMlsClient::sign_commit
+    fn tls_handshake_tbs(room_id: &str, sender: &str, epoch: u64, message: &[u8]) -> Vec<u8> {
+        let mut tbs = tls_tbs(&[room_id, sender], epoch);
+        tbs.extend(VLBytes::new(message.to_vec()).tls_serialize_detached().unwrap());
+        tbs
+    }
+
+    #[test]
+    fn handshake_signatures() {
+        // Un Commit vero supera i 64 byte: lunghezza varint a 2 byte
+        let message = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY).add_commit;
+
+        for ciphersuite in [ED25519, P256] {
+            let alice = Client::new("alice", ciphersuite);
+            let kp = parse_key_package(&alice.key_package_bytes(false)).unwrap();
+            let tbs = tls_handshake_tbs("1", "alice", 3, &message);
+
+            let commit = sign(&alice, COMMIT_LABEL, &tbs);
+            verify_commit(&kp, "1", "alice", 3, &message, &commit).unwrap();
+            assert!(matches!(verify_commit(&kp, "1", "alice", 4, &message, &commit), Err(AuthError::BadSignature)));
+            assert!(matches!(verify_commit(&kp, "1", "alice", 3, &message[1..], &commit), Err(AuthError::BadSignature)));
+            // Un Commit firmato non vale come Proposal
+            assert!(matches!(verify_proposal(&kp, "1", "alice", 3, &message, &commit), Err(AuthError::BadSignature)));
+
+            let proposal = sign(&alice, PROPOSAL_LABEL, &tbs);
+            verify_proposal(&kp, "1", "alice", 3, &message, &proposal).unwrap();
+            assert!(matches!(verify_commit(&kp, "1", "bob", 3, &message, &proposal), Err(AuthError::IdentityMismatch)));
+        }
+    }
+
+    #[test]
+    fn x509_identity_from_certificate() {
+        for ciphersuite in [ED25519, P256] {
+            let alice = Client::new_x509("alice", ciphersuite);
+            let kp = parse_key_package(&alice.key_package_bytes(false)).unwrap();
+            assert_eq!(kp.identity, b"alice");
+
+            let join = sign(&alice, JOIN_LABEL, &tls_tbs(&["1", "alice"], 1));
+            verify_join(&kp, "1", "alice", 1, &join).unwrap();
+
+            // La firma è valida, ma il certificato dice "alice"
+            let join = sign(&alice, JOIN_LABEL, &tls_tbs(&["1", "mallory"], 1));
+            assert!(matches!(verify_join(&kp, "1", "mallory", 1, &join), Err(AuthError::IdentityMismatch)));
+        }
+    }
+
+    #[test]
+    fn x509_certificate_of_another_key() {
+        let other = Client::new("other", ED25519);
+        let mallory = Client::with_credential(ED25519, |_| {
+            x509_credential(&x509_certificate("alice", other.signer.public()))
+        });
+        assert!(matches!(
+            parse_key_package(&mallory.key_package_bytes(false)),
+            Err(AuthError::Malformed("certificato leaf con un'altra chiave"))
+        ));
+    }
+
+    #[test]
+    fn x509_leaf_truncated() {
+        let cert = x509_certificate("alice", &[7; 32]);
+        assert_eq!(x509_leaf(&cert).unwrap(), (&b"alice"[..], &[7; 32][..]));
+        for cut in 0..cert.len() {
+            assert!(x509_leaf(&cert[..cut]).is_err(), "prefisso di {cut} byte accettato");
+        }
+    }
+
+    #[test]
+    fn request_from_other_identity() {
+        let alice = Client::new("alice", ED25519);
+        let mallory = Client::new("mallory", ED25519);
+        let kp = parse_key_package(&alice.key_package_bytes(false)).unwrap();
+
+        let join = sign(&alice, JOIN_LABEL, &tls_tbs(&["1", "mallory"], 1));
+        assert!(matches!(verify_join(&kp, "1", "mallory", 1, &join), Err(AuthError::IdentityMismatch)));
+
+        let forged = sign(&mallory, JOIN_LABEL, &tls_tbs(&["1", "alice"], 1));
+        assert!(matches!(verify_join(&kp, "1", "alice", 1, &forged), Err(AuthError::BadSignature)));
+        assert!(matches!(verify_join(&kp, "1", "alice", 1, "!!"), Err(AuthError::BadSignature)));
+    }
+}
@@ -0,0 +1,101 @@
+// ─────────────────────────────────────────────────────────────
+// DIRECTORY – KeyPackage pubblicati in anticipo per identità
+// ─────────────────────────────────────────────────────────────
+//
+// Come il Delivery Service dell'architettura MLS: ogni client carica
+// alcuni KeyPackage monouso più uno "last resort", così chi invita può
+// aggiungerlo al gruppo anche se è offline. Si salva con lo stesso
+// backend delle stanze (RoomStore::flush_directory).
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// KeyPackage monouso conservati per identità (i più vecchi escono per primi)
+pub const MAX_ONE_TIME: usize = 100;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct IdentityPackages {
+    // Chiave di firma (Base64) fissata dalla prima pubblicazione
+    signature_key: String,
+    last_upload_ts: u64,
+    one_time: VecDeque<String>,
+    last_resort: Option<String>,
+}
+
+pub enum Claimed {
+    OneTime(String),
+    // Nessun monouso rimasto: il last resort non viene consumato
+    LastResort(String),
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    WrongSignatureKey,
+    Replay,
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::WrongSignatureKey => write!(f, "chiave di firma diversa da quella registrata"),
+            PublishError::Replay => write!(f, "richiesta ripetuta"),
+        }
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct KeyPackageDirectory {
+    entries: HashMap<String, IdentityPackages>,
+}
+
+impl KeyPackageDirectory {
+    /// Aggiunge KeyPackage già verificati; `last_resort` sostituisce il precedente.
+    pub fn publish(
+        &mut self,
+        identity: &str,
+        signature_key: &str,
+        timestamp: u64,
+        one_time: Vec<String>,
+        last_resort: Option<String>,
+    ) -> Result<(), PublishError> {
+        let entry = self.entries.entry(identity.to_string()).or_default();
+
+        if entry.signature_key.is_empty() {
+            entry.signature_key = signature_key.to_string();
+        } else if entry.signature_key != signature_key {
+            return Err(PublishError::WrongSignatureKey);
+        }
+        if timestamp <= entry.last_upload_ts {
+            return Err(PublishError::Replay);
+        }
+        entry.last_upload_ts = timestamp;
+
+        entry.one_time.extend(one_time);
+        while entry.one_time.len() > MAX_ONE_TIME {
+            entry.one_time.pop_front();
+        }
+        if last_resort.is_some() {
+            entry.last_resort = last_resort;
+        }
+        Ok(())
+    }
+
+    /// Consuma un KeyPackage monouso, altrimenti restituisce il last resort.
+    pub fn claim(&mut self, identity: &str) -> Option<Claimed> {
+        let entry = self.entries.get_mut(identity)?;
+        match entry.one_time.pop_front() {
+            Some(kp) => Some(Claimed::OneTime(kp)),
+            None => entry.last_resort.clone().map(Claimed::LastResort),
+        }
+    }
+
+    /// (monouso disponibili, last resort presente)
+    pub fn status(&self, identity: &str) -> (usize, bool) {
+        self.entries
+            .get(identity)
+            .map(|e| (e.one_time.len(), e.last_resort.is_some()))
+            .unwrap_or((0, false))
+    }
+}
@@ -4,14 +4,42 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use warp::Filter;
+use std::time::{SystemTime, UNIX_EPOCH};
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use warp::{Filter, Reply};
+use warp::http::StatusCode;
+use warp::http::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+use warp::hyper::body::Bytes;
+use warp::ws::{Message, WebSocket, Ws};
 use serde::{Serialize, Deserialize};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+
+mod auth;
+mod directory;
+mod storage;
+mod wire;
+#[cfg(test)]
+mod test_mls;
+use auth::AuthError;
+use directory::{Claimed, KeyPackageDirectory, PublishError};
+use storage::{FileStore, MemoryStore, RoomStore};
+use wire::{ContentType, WireError, WireFormat};
 
 // ─────────────────────────────────────────────────────────────
 // STRUCTS (Le "Lettere" che il postino gestisce)
 // ─────────────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum MemberStatus {
+    Active,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct MemberEntry {
     index: u32,
     identity: String,
@@ -19,19 +47,45 @@ struct MemberEntry {
     key_package: String, 
     // Il Welcome message cifrato destinato a questo utente (in Base64)
     welcome_message: Option<String>, 
+    status: MemberStatus,
+    // Per un membro rimosso: chi deve ancora pubblicare il Commit di rimozione
+    removal_committer: Option<String>,
+    // Chiave di firma del KeyPackage (in Base64): solo lei può rifare il join
+    #[serde(default)]
+    signature_key: String,
+    // Timestamp dell'ultimo join accettato (contro i replay)
+    #[serde(default)]
+    last_join_ts: u64,
+    // Timestamp dell'ultimo claim dalla directory fatto da questo membro:
+    // al più un claim al secondo, e mai ripetuto
+    #[serde(default)]
+    last_claim_ts: u64,
+}
+
+impl MemberEntry {
+    fn is_active(&self) -> bool {
+        self.status == MemberStatus::Active
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct JoinRequest {
     identity: String,
-    room_id: u32,
+    room_id: String,
     key_package: String, // Ora il client DEVE inviare il suo pacchetto
+    // Secondi Unix e firma (Base64) su room_id, identity e timestamp
+    // fatta con la chiave di firma del KeyPackage
+    timestamp: u64,
+    signature: String,
 }
 
+// Scarto massimo tra l'orologio del client e quello del server
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
 #[derive(Debug, Serialize)]
 struct JoinResponse {
     epoch: u64,
-    room_id: u32,
+    room_id: String,
     sender_index: u32,
     is_creator: bool, // Diciamo al client se è il primo (deve creare lui il gruppo!)
     roster: Vec<MemberEntry>,
@@ -39,7 +93,7 @@ struct JoinResponse {
 
 #[derive(Debug, Deserialize)]
 struct WelcomeRequest {
-    room_id: u32,
+    room_id: String,
     target_identity: String,
     welcome_message: String,
 }
@@ -49,95 +103,748 @@ struct GenericResponse {
     success: bool,
 }
 
+#[derive(Debug, Deserialize)]
+struct CreateRequest {
+    // Numero massimo di membri attivi (nessun limite se assente)
+    #[serde(default)]
+    max_members: Option<u32>,
+    // Inattività dopo cui la stanza viene eliminata (al massimo --room-ttl)
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateResponse {
+    room_id: String,
+    // Segreto del creatore: serve solo per chiudere la stanza
+    owner_token: String,
+    max_members: Option<u32>,
+    ttl_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloseRequest {
+    room_id: String,
+}
+
+// Pubblicazione nella directory: tutti i KeyPackage con la stessa chiave
+// di firma, che firma anche (identity, timestamp) come nel join
+#[derive(Debug, Deserialize)]
+struct PublishRequest {
+    identity: String,
+    #[serde(default)]
+    key_packages: Vec<String>,
+    #[serde(default)]
+    last_resort: Option<String>,
+    timestamp: u64,
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityQuery {
+    identity: String,
+}
+
+// Claim dalla directory (corpo JSON o query dell'endpoint binario): solo un
+// membro attivo di `room_id` può prendere i KeyPackage di chi vuole invitare
+#[derive(Debug, Deserialize)]
+struct ClaimRequest {
+    identity: String, // di chi si prende il KeyPackage
+    room_id: String,
+    claimer: String,
+    // Secondi Unix e firma (Base64) su room_id, claimer, identity e timestamp
+    // con la chiave di firma del KeyPackage con cui `claimer` è entrato
+    timestamp: u64,
+    signature: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DirectoryStatus {
+    identity: String,
+    available: usize,
+    last_resort: bool,
+}
+
+// Endpoint binari (application/mls-message): i metadati viaggiano nella query
+#[derive(Debug, Deserialize)]
+struct BinHandshakeQuery {
+    room_id: String,
+    sender_identity: String,
+    // Destinatari del Welcome accodato al Commit, separati da virgola
+    #[serde(default)]
+    welcome_to: Option<String>,
+    // Firma (Base64) del solo messaggio MLS, come nei corpi JSON
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinWelcomeQuery {
+    room_id: String,
+    target_identity: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberQuery {
+    room_id: String,
+    identity: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinMessageQuery {
+    room_id: String,
+    identity: String,
+    seq: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ClaimResponse {
+    identity: String,
+    key_package: String, // KeyPackage in Base64
+    last_resort: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaveRequest {
+    room_id: String,
+    identity: String,
+    // Come nel join: firma su room_id, identity e timestamp con la chiave
+    // del KeyPackage con cui il membro è entrato
+    timestamp: u64,
+    signature: String,
+}
+
+// Rimozione da parte dell'amministratore: basta l'X-Admin-Token
+#[derive(Debug, Deserialize)]
+struct RemoveRequest {
+    room_id: String,
+    identity: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LeaveResponse {
+    success: bool,
+    epoch: u64,
+    committer: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct RosterQuery {
-    room_id: u32,
+    room_id: String,
 }
 
 #[derive(Debug, Serialize)]
 struct RosterResponse {
     epoch: u64,
-    room_id: u32,
+    room_id: String,
     roster: Vec<MemberEntry>,
 }
 
-#[derive(Clone)]
+// Commit e Proposal restano opachi: il server legge solo l'epoch MLS
+// dichiarata dal mittente per ordinare i Commit (uno solo per epoch).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum HandshakeKind {
+    Commit,
+    Proposal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedMessage {
+    seq: u64,
+    kind: HandshakeKind,
+    epoch: u64,
+    sender: String,
+    message: String, // MlsMessageOut in Base64
+}
+
+#[derive(Debug, Deserialize)]
+struct WelcomeEntry {
+    target_identity: String,
+    welcome_message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitRequest {
+    room_id: String,
+    sender_identity: String,
+    epoch: u64, // epoch MLS in cui il Commit è stato creato
+    message: String,
+    #[serde(default)]
+    welcomes: Vec<WelcomeEntry>, // Welcome per i membri aggiunti dal Commit
+    // Firma (Base64) su room_id, sender_identity, epoch e messaggio con la
+    // chiave del KeyPackage con cui il mittente è entrato
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProposalRequest {
+    room_id: String,
+    sender_identity: String,
+    epoch: u64,
+    message: String,
+    signature: String, // come nel Commit
+}
+
+#[derive(Debug, Serialize)]
+struct HandshakeResponse {
+    accepted: bool,
+    epoch: u64,
+    seq: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesQuery {
+    room_id: String,
+    identity: String,
+    // Ultimo seq già elaborato: si ricevono solo i messaggi successivi
+    #[serde(default)]
+    after: u64,
+}
+
+// Conferma di lettura: i messaggi fino a `after` escono dalla coda
+#[derive(Debug, Deserialize)]
+struct AckRequest {
+    room_id: String,
+    identity: String,
+    after: u64,
+    // Firma (Base64) su room_id, identity e after con la chiave del
+    // KeyPackage con cui il membro è entrato
+    signature: String,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesResponse {
+    epoch: u64,
+    room_id: String,
+    messages: Vec<QueuedMessage>,
+}
+
+// Eventi spinti ai client sul WebSocket della stanza (JSON con campo "type")
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RoomEvent {
+    Roster { epoch: u64, roster: Vec<MemberEntry> },
+    WelcomeAvailable { identity: String },
+    Epoch { epoch: u64 },
+    MemberLeft { index: u32, identity: String, committer: Option<String> },
+    Message { seq: u64, kind: HandshakeKind, epoch: u64, sender: String },
+    Closed { reason: CloseReason },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CloseReason {
+    Owner,
+    Expired,
+}
+
+const ROOM_EVENTS_CAPACITY: usize = 64;
+const ROOM_ID_BYTES: usize = 16;
+const OWNER_TOKEN_BYTES: usize = 32;
+// Default (e massimo) per il TTL delle stanze, sovrascrivibile con --room-ttl
+const DEFAULT_ROOM_TTL_SECS: u64 = 3600;
+const GC_INTERVAL_SECS: u64 = 60;
+// Prima del primo Commit: di quanto l'epoch MLS dichiarata può superare quella della stanza
+const MAX_EPOCH_AHEAD: u64 = 1;
+// Messaggi non confermati per membro: oltre, la stanza rifiuta Commit e Proposal
+const MAX_QUEUE_LEN: usize = 256;
+
+#[derive(Clone, Serialize, Deserialize)]
 struct GroupState {
     epoch: u64,
     roster: Vec<MemberEntry>,
+    // Epoch MLS dell'ultimo Commit accettato
+    last_commit_epoch: Option<u64>,
+    next_seq: u64,
+    // identity → messaggi in attesa di essere letti
+    queues: HashMap<String, Vec<QueuedMessage>>,
+    #[serde(default)]
+    owner_token: String,
+    #[serde(default)]
+    max_members: Option<u32>,
+    #[serde(default)]
+    ttl_secs: u64,
+    // Secondi Unix dell'ultima richiesta sulla stanza
+    #[serde(default)]
+    last_activity: u64,
+    // Attività vista solo da letture (roster, messages) e non ancora su disco:
+    // la salva il giro di GC, così le GET non scrivono
+    #[serde(skip)]
+    activity_unsaved: bool,
+}
+
+impl GroupState {
+    fn new(owner_token: String, max_members: Option<u32>, ttl_secs: u64) -> Self {
+        GroupState {
+            epoch: 1,
+            roster: vec![],
+            last_commit_epoch: None,
+            next_seq: 1,
+            queues: HashMap::new(),
+            owner_token,
+            max_members,
+            ttl_secs,
+            last_activity: now_secs(),
+            activity_unsaved: false,
+        }
+    }
+
+    fn touch(&mut self) {
+        self.last_activity = now_secs();
+    }
+
+    fn touch_read(&mut self) {
+        self.touch();
+        self.activity_unsaved = true;
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.last_activity) > self.ttl_secs
+    }
+
+    fn is_full(&self) -> bool {
+        let active = self.roster.iter().filter(|m| m.is_active()).count();
+        self.max_members.is_some_and(|max| active >= max as usize)
+    }
+
+    fn roster_event(&self) -> RoomEvent {
+        RoomEvent::Roster {
+            epoch: self.epoch,
+            roster: self.roster.clone(),
+        }
+    }
+
+    fn is_member(&self, identity: &str) -> bool {
+        self.roster.iter().any(|m| m.identity == identity && m.is_active())
+    }
+
+    // Segna il membro come rimosso e sceglie chi deve committare la rimozione:
+    // il membro attivo con l'indice più basso. Restituisce (index, committer).
+    // Le rimozioni che toccavano al membro uscente passano allo stesso committer.
+    fn remove_member(&mut self, identity: &str) -> Option<(u32, Option<String>)> {
+        let committer = self
+            .roster
+            .iter()
+            .filter(|m| m.is_active() && m.identity != identity)
+            .min_by_key(|m| m.index)
+            .map(|m| m.identity.clone());
+
+        let member = self
+            .roster
+            .iter_mut()
+            .find(|m| m.identity == identity && m.is_active())?;
+        member.status = MemberStatus::Removed;
+        member.welcome_message = None;
+        member.removal_committer = committer.clone();
+        let index = member.index;
+
+        for pending in self.roster.iter_mut().filter(|m| m.removal_committer.as_deref() == Some(identity)) {
+            pending.removal_committer = committer.clone();
+        }
+
+        self.queues.remove(identity);
+        self.epoch += 1;
+        Some((index, committer))
+    }
+
+    // Indice per un nuovo membro: il primo slot rimosso la cui rimozione è
+    // già stata committata (come fa MLS con le foglie vuote), altrimenti in coda.
+    fn free_index(&self) -> u32 {
+        self.roster
+            .iter()
+            .filter(|m| !m.is_active() && m.removal_committer.is_none())
+            .map(|m| m.index)
+            .min()
+            .unwrap_or(self.roster.len() as u32)
+    }
+
+    // Dopo il primo Commit vale solo l'epoch MLS corrente, quella successiva
+    // all'ultimo Commit accettato. Prima non la conosciamo: basta che non
+    // superi di molto il contatore della stanza, che avanza a ogni Commit,
+    // Welcome o rimozione.
+    fn accepts(&self, epoch: u64) -> bool {
+        match self.last_commit_epoch {
+            Some(last) => last.checked_add(1) == Some(epoch),
+            None => epoch <= self.epoch.saturating_add(MAX_EPOCH_AHEAD),
+        }
+    }
+
+    // Firma di un Commit o di una Proposal con la chiave del KeyPackage con
+    // cui il mittente è entrato. Il chiamante ha già verificato che sia un membro.
+    fn verify_handshake(
+        &self,
+        kind: HandshakeKind,
+        room_id: &str,
+        sender: &str,
+        epoch: u64,
+        message: &[u8],
+        signature: &str,
+    ) -> Result<(), AuthError> {
+        let member = self
+            .roster
+            .iter()
+            .find(|m| m.identity == sender && m.is_active())
+            .ok_or(AuthError::IdentityMismatch)?;
+        let kp = auth::decode_key_package(&member.key_package)?;
+        match kind {
+            HandshakeKind::Commit => auth::verify_commit(&kp, room_id, sender, epoch, message, signature),
+            HandshakeKind::Proposal => auth::verify_proposal(&kp, room_id, sender, epoch, message, signature),
+        }
+    }
+
+    // Primo destinatario di fan_out con la coda già piena: finché non
+    // conferma la lettura la stanza non accoda altro
+    fn full_queue(&self, sender: &str, skip: &[&str]) -> Option<&str> {
+        self.roster
+            .iter()
+            .filter(|m| m.is_active() && m.identity != sender && !skip.contains(&m.identity.as_str()))
+            .find(|m| self.queues.get(&m.identity).is_some_and(|q| q.len() >= MAX_QUEUE_LEN))
+            .map(|m| m.identity.as_str())
+    }
+
+    // Accoda il messaggio a tutti i membri tranne `skip`; restituisce il seq.
+    fn fan_out(
+        &mut self,
+        kind: HandshakeKind,
+        epoch: u64,
+        sender: &str,
+        message: String,
+        skip: &[&str],
+    ) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let queued = QueuedMessage {
+            seq,
+            kind,
+            epoch,
+            sender: sender.to_string(),
+            message,
+        };
+
+        for member in &self.roster {
+            if !member.is_active() || member.identity == sender || skip.contains(&member.identity.as_str()) {
+                continue;
+            }
+            self.queues
+                .entry(member.identity.clone())
+                .or_default()
+                .push(queued.clone());
+        }
+
+        seq
+    }
 }
 
 #[derive(Clone)]
 struct Groups {
-    // room_id → stato della stanza
-    inner: Arc<Mutex<HashMap<u32, GroupState>>>,
+    // room_id → stato della stanza, nel backend scelto all'avvio
+    inner: Arc<Mutex<Box<dyn RoomStore>>>,
+    // room_id → canale eventi per i WebSocket (creato al primo uso)
+    events: Arc<Mutex<HashMap<String, broadcast::Sender<RoomEvent>>>>,
+    // TTL di default e massimo per le nuove stanze
+    room_ttl: u64,
+    // identity → KeyPackage pubblicati in anticipo
+    key_packages: Arc<Mutex<KeyPackageDirectory>>,
 }
 
 impl Groups {
-    fn new() -> Self {
+    fn new(mut store: Box<dyn RoomStore>, room_ttl: u64) -> Self {
+        let directory = store.take_directory();
         Groups {
-            inner: Arc::new(Mutex::new(HashMap::new())),
+            inner: Arc::new(Mutex::new(store)),
+            events: Arc::new(Mutex::new(HashMap::new())),
+            room_ttl,
+            key_packages: Arc::new(Mutex::new(directory)),
+        }
+    }
+
+    fn subscribe(&self, room_id: &str) -> broadcast::Receiver<RoomEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .entry(room_id.to_string())
+            .or_insert_with(|| broadcast::channel(ROOM_EVENTS_CAPACITY).0)
+            .subscribe()
+    }
+
+    // Nessun errore se non ci sono WebSocket aperti sulla stanza
+    fn publish(&self, room_id: &str, event: RoomEvent) {
+        if let Some(tx) = self.events.lock().unwrap().get(room_id) {
+            let _ = tx.send(event);
         }
     }
+
+    fn roster_event(&self, room_id: &str) -> RoomEvent {
+        match self.inner.lock().unwrap().get(room_id) {
+            Some(gs) => gs.roster_event(),
+            None => RoomEvent::Roster { epoch: 0, roster: vec![] },
+        }
+    }
+
+    // Il lock e l'I/O del backend in un thread bloccante, fuori dal runtime
+    async fn blocking<R: Send + 'static>(&self, f: impl FnOnce(&Groups) -> R + Send + 'static) -> R {
+        let groups = self.clone();
+        tokio::task::spawn_blocking(move || f(&groups))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    // Modifica una stanza: se `f` accetta la richiesta la stanza viene salvata
+    // e solo dopo si pubblicano gli eventi; se il salvataggio fallisce la
+    // stanza torna com'era e il client riceve un 500.
+    async fn update<R: Send + 'static>(
+        &self,
+        room_id: &str,
+        f: impl FnOnce(&mut dyn RoomStore) -> Update<R> + Send + 'static,
+    ) -> Result<R, warp::Rejection> {
+        let room_id = room_id.to_string();
+        self.blocking(move |groups| {
+            let mut store = groups.inner.lock().unwrap();
+            let before = store.get(&room_id).cloned();
+
+            let (reply, events) = match f(store.as_mut()) {
+                Ok(accepted) => accepted,
+                Err(rejected) => return Ok(rejected),
+            };
+            if let Err(e) = store.flush(&room_id) {
+                eprintln!("[MLS-DS] Errore salvataggio room={}: {}", room_id, e);
+                match before {
+                    Some(gs) => {
+                        store.insert(&room_id, gs);
+                    }
+                    None => {
+                        let _ = store.remove(&room_id);
+                    }
+                }
+                return Err(warp::reject::custom(StorageError));
+            }
+
+            // Ancora sotto il lock: gli eventi escono nell'ordine delle modifiche
+            for event in events {
+                groups.publish(&room_id, event);
+            }
+            Ok(reply)
+        })
+        .await
+    }
+
+    // Come update, per la directory dei KeyPackage: Ok(risposta) va salvata,
+    // Err(risposta) non ha modificato nulla. Lock nell'ordine directory → stanze.
+    async fn update_directory<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut KeyPackageDirectory) -> Result<R, R> + Send + 'static,
+    ) -> Result<R, warp::Rejection> {
+        self.blocking(move |groups| {
+            let mut directory = groups.key_packages.lock().unwrap();
+            let before = directory.clone();
+
+            let reply = match f(&mut directory) {
+                Ok(accepted) => accepted,
+                Err(rejected) => return Ok(rejected),
+            };
+            if let Err(e) = groups.inner.lock().unwrap().flush_directory(&directory) {
+                eprintln!("[MLS-DS] Errore salvataggio directory KeyPackage: {}", e);
+                *directory = before;
+                return Err(warp::reject::custom(StorageError));
+            }
+            Ok(reply)
+        })
+        .await
+    }
+
+    // Elimina la stanza (roster, Welcome e code) e chiude i suoi WebSocket:
+    // togliendo il Sender i client ricevono Closed e poi la fine del canale.
+    fn close_room(&self, store: &mut dyn RoomStore, room_id: &str, reason: CloseReason) -> std::io::Result<()> {
+        store.remove(room_id)?;
+        self.publish(room_id, RoomEvent::Closed { reason });
+        self.events.lock().unwrap().remove(room_id);
+        Ok(())
+    }
+
+    // Elimina le stanze inattive da più del loro TTL e salva l'attività
+    // vista solo dalle letture, perché dopo un riavvio non scadano prima
+    fn collect_expired(&self) {
+        let mut store = self.inner.lock().unwrap();
+        let now = now_secs();
+
+        for room_id in store.room_ids() {
+            let Some(gs) = store.get_mut(&room_id) else {
+                continue;
+            };
+            if !gs.is_expired(now) {
+                if std::mem::take(&mut gs.activity_unsaved) && let Err(e) = store.flush(&room_id) {
+                    eprintln!("[MLS-DS] Errore salvataggio room={}: {}", room_id, e);
+                    if let Some(gs) = store.get_mut(&room_id) {
+                        gs.activity_unsaved = true;
+                    }
+                }
+                continue;
+            }
+            match self.close_room(store.as_mut(), &room_id, CloseReason::Expired) {
+                Ok(()) => println!("[MLS-DS] Stanza {} scaduta per inattività", room_id),
+                Err(e) => eprintln!("[MLS-DS] Errore eliminazione room={}: {}", room_id, e),
+            }
+        }
+
+        // Canali aperti su stanze inesistenti e senza più ascoltatori
+        self.events
+            .lock()
+            .unwrap()
+            .retain(|room_id, tx| store.get(room_id).is_some() || tx.receiver_count() > 0);
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Identificativo casuale non indovinabile, sicuro in URL e nomi di file
+fn random_id(len: usize) -> String {
+    let bytes: Vec<u8> = (0..len).map(|_| rand::random::<u8>()).collect();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Confronto dei token a tempo costante (rispetto al contenuto)
+fn token_eq(expected: &str, got: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), got.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Scrittura fallita sul backend: diventa un 500
+#[derive(Debug)]
+struct StorageError;
+
+impl warp::reject::Reject for StorageError {}
+
+// Esito di una modifica: Ok((risposta, eventi)) se la stanza va salvata,
+// Err(risposta) se la richiesta è rifiutata e non c'è nulla da salvare
+type Update<R> = Result<(R, Vec<RoomEvent>), R>;
+
+async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    if err.find::<StorageError>().is_some() {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&GenericResponse { success: false }),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ));
+    }
+    Err(err)
 }
 
 // ─────────────────────────────────────────────────────────────
 // HANDLERS
 // ─────────────────────────────────────────────────────────────
 
-// 1. Un utente entra e deposita il suo KeyPackage
+// 1. Un utente entra e deposita il suo KeyPackage, firmando la richiesta
+//    con la chiave contenuta nel KeyPackage stesso
 async fn handle_join(
     req: JoinRequest,
     groups: Groups,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut map = groups.inner.lock().unwrap();
+    let rejected = |status| {
+        warp::reply::with_status(
+            warp::reply::json(&GenericResponse { success: false }),
+            status,
+        )
+    };
 
-    let gs = map.entry(req.room_id).or_insert_with(|| {
-        println!("[MLS-DS] Stanza {} creata", req.room_id);
-        GroupState {
-            epoch: 1,
-            roster: vec![],
+    let kp = match auth::decode_key_package(&req.key_package) {
+        Ok(kp) => kp,
+        Err(e) => {
+            println!("[MLS-DS] Join rifiutato room={} identity={}: {}", req.room_id, req.identity, e);
+            return Ok(rejected(StatusCode::BAD_REQUEST));
         }
-    });
+    };
 
-    let is_creator = gs.roster.is_empty();
+    if now_secs().abs_diff(req.timestamp) > MAX_CLOCK_SKEW_SECS {
+        println!("[MLS-DS] Join rifiutato room={} identity={}: timestamp fuori finestra", req.room_id, req.identity);
+        return Ok(rejected(StatusCode::UNAUTHORIZED));
+    }
 
-    // Se l'utente esiste già, aggiorniamo il suo KeyPackage (magari ha ricaricato la pagina)
-    if let Some(existing) = gs.roster.iter_mut().find(|m| m.identity == req.identity) {
-        existing.key_package = req.key_package.clone();
-        existing.welcome_message = None; // Resettiamo eventuali vecchi inviti
-        
-        println!("[MLS-DS] Re-join room={} identity={} index={}", req.room_id, req.identity, existing.index);
-        
-        return Ok(warp::reply::json(&JoinResponse {
-            epoch: gs.epoch,
-            room_id: req.room_id,
-            sender_index: existing.index,
-            is_creator,
-            roster: gs.roster.clone(),
-        }));
+    if let Err(e) = auth::verify_join(&kp, &req.room_id, &req.identity, req.timestamp, &req.signature) {
+        println!("[MLS-DS] Join rifiutato room={} identity={}: {}", req.room_id, req.identity, e);
+        let status = match e {
+            AuthError::Malformed(_) | AuthError::UnsupportedCiphersuite(_) => StatusCode::BAD_REQUEST,
+            AuthError::IdentityMismatch | AuthError::BadSignature => StatusCode::UNAUTHORIZED,
+        };
+        return Ok(rejected(status));
     }
+    let signature_key = BASE64.encode(&kp.signature_key);
 
-    // Nuovo utente
-    let new_idx = gs.roster.len() as u32;
-    gs.roster.push(MemberEntry {
-        index: new_idx,
-        identity: req.identity.clone(),
-        key_package: req.key_package,
-        welcome_message: None, // Aspetta che il creatore gli mandi l'invito
-    });
+    let room_id = req.room_id.clone();
+    groups.update(&room_id, move |store| {
+        // Le stanze si creano solo con /mls/create
+        let Some(gs) = store.get_mut(&req.room_id) else {
+            return Err(rejected(StatusCode::NOT_FOUND));
+        };
+        gs.touch();
 
-    // NOTA: Non avanziamo l'epoch qui. L'epoch avanza quando il creatore carica il Welcome.
+        let is_creator = !gs.roster.iter().any(|m| m.is_active());
+        let rejoin_active = gs.roster.iter().any(|m| m.identity == req.identity && m.is_active());
+        if !rejoin_active && gs.is_full() {
+            println!("[MLS-DS] Join rifiutato room={} identity={}: stanza piena", req.room_id, req.identity);
+            return Err(rejected(StatusCode::CONFLICT));
+        }
 
-    println!("[MLS-DS] Nuovo join room={} identity={} index={}", req.room_id, req.identity, new_idx);
+        // Se l'utente esiste già, aggiorniamo il suo KeyPackage (magari ha ricaricato la pagina)
+        let sender_index = if let Some(existing) = gs.roster.iter_mut().find(|m| m.identity == req.identity) {
+            // Un membro attivo si sovrascrive solo con la stessa chiave di firma
+            if existing.is_active() && !existing.signature_key.is_empty() && existing.signature_key != signature_key {
+                println!("[MLS-DS] Re-join rifiutato room={} identity={}: chiave di firma diversa", req.room_id, req.identity);
+                return Err(rejected(StatusCode::FORBIDDEN));
+            }
+            if req.timestamp <= existing.last_join_ts {
+                println!("[MLS-DS] Re-join rifiutato room={} identity={}: richiesta ripetuta", req.room_id, req.identity);
+                return Err(rejected(StatusCode::UNAUTHORIZED));
+            }
 
-    Ok(warp::reply::json(&JoinResponse {
-        epoch: gs.epoch,
-        room_id: req.room_id,
-        sender_index: new_idx,
-        is_creator,
-        roster: gs.roster.clone(),
-    }))
+            existing.key_package = req.key_package;
+            existing.welcome_message = None; // Resettiamo eventuali vecchi inviti
+            existing.status = MemberStatus::Active;
+            existing.removal_committer = None;
+            existing.signature_key = signature_key;
+            existing.last_join_ts = req.timestamp;
+
+            println!("[MLS-DS] Re-join room={} identity={} index={}", req.room_id, req.identity, existing.index);
+            existing.index
+        } else {
+            // Nuovo utente
+            let new_idx = gs.free_index();
+            let entry = MemberEntry {
+                index: new_idx,
+                identity: req.identity.clone(),
+                key_package: req.key_package,
+                welcome_message: None, // Aspetta che il creatore gli mandi l'invito
+                status: MemberStatus::Active,
+                removal_committer: None,
+                signature_key,
+                last_join_ts: req.timestamp,
+                last_claim_ts: 0,
+            };
+            match gs.roster.iter_mut().find(|m| m.index == new_idx) {
+                Some(slot) => *slot = entry,
+                None => gs.roster.push(entry),
+            }
+
+            // NOTA: Non avanziamo l'epoch qui. L'epoch avanza quando il creatore carica il Welcome.
+
+            println!("[MLS-DS] Nuovo join room={} identity={} index={}", req.room_id, req.identity, new_idx);
+            new_idx
+        };
+
+        let response = JoinResponse {
+            epoch: gs.epoch,
+            room_id: req.room_id.clone(),
+            sender_index,
+            is_creator,
+            roster: gs.roster.clone(),
+        };
+        Ok((
+            warp::reply::with_status(warp::reply::json(&response), StatusCode::OK),
+            vec![gs.roster_event()],
+        ))
+    })
+    .await
 }
 
 // 2. Il creatore carica un Welcome message per un nuovo utente
@@ -145,19 +852,31 @@ async fn handle_welcome(
     req: WelcomeRequest,
     groups: Groups,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut map = groups.inner.lock().unwrap();
+    let room_id = req.room_id.clone();
+    groups.update(&room_id, move |store| {
+        let Some(gs) = store.get_mut(&req.room_id) else {
+            return Err(warp::reply::json(&GenericResponse { success: false }));
+        };
+        let Some(target) = gs.roster.iter_mut().find(|m| m.identity == req.target_identity) else {
+            return Err(warp::reply::json(&GenericResponse { success: false }));
+        };
 
-    if let Some(gs) = map.get_mut(&req.room_id) {
-        if let Some(target) = gs.roster.iter_mut().find(|m| m.identity == req.target_identity) {
-            target.welcome_message = Some(req.welcome_message);
-            gs.epoch += 1; // Un utente è stato aggiunto ufficialmente, l'epoch avanza!
-            
-            println!("[MLS-DS] Welcome caricato per {} in room {} (Nuova Epoch: {})", req.target_identity, req.room_id, gs.epoch);
-            return Ok(warp::reply::json(&GenericResponse { success: true }));
-        }
-    }
+        target.welcome_message = Some(req.welcome_message);
+        gs.epoch += 1; // Un utente è stato aggiunto ufficialmente, l'epoch avanza!
+        gs.touch();
+        // Il Welcome sostituisce tutto ciò che era in coda prima dell'ingresso
+        gs.queues.remove(&req.target_identity);
 
-    Ok(warp::reply::json(&GenericResponse { success: false }))
+        println!("[MLS-DS] Welcome caricato per {} in room {} (Nuova Epoch: {})", req.target_identity, req.room_id, gs.epoch);
+        Ok((
+            warp::reply::json(&GenericResponse { success: true }),
+            vec![
+                RoomEvent::WelcomeAvailable { identity: req.target_identity },
+                RoomEvent::Epoch { epoch: gs.epoch },
+            ],
+        ))
+    })
+    .await
 }
 
 // 3. I client chiedono la lista dei partecipanti (e controllano la posta)
@@ -165,30 +884,937 @@ async fn handle_roster(
     query: RosterQuery,
     groups: Groups,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let map = groups.inner.lock().unwrap();
+    let response = groups
+        .blocking(move |groups| {
+            let mut store = groups.inner.lock().unwrap();
 
-    if let Some(gs) = map.get(&query.room_id) {
-        Ok(warp::reply::json(&RosterResponse {
-            epoch: gs.epoch,
-            room_id: query.room_id,
-            roster: gs.roster.clone(),
-        }))
-    } else {
-        Ok(warp::reply::json(&RosterResponse {
-            epoch: 0,
-            room_id: query.room_id,
-            roster: vec![],
-        }))
+            // Il polling tiene viva la stanza; su disco ci pensa collect_expired
+            match store.get_mut(&query.room_id) {
+                Some(gs) => {
+                    gs.touch_read();
+                    RosterResponse {
+                        epoch: gs.epoch,
+                        room_id: query.room_id,
+                        roster: gs.roster.clone(),
+                    }
+                }
+                None => RosterResponse {
+                    epoch: 0,
+                    room_id: query.room_id,
+                    roster: vec![],
+                },
+            }
+        })
+        .await;
+    Ok(warp::reply::json(&response))
+}
+
+// Risposta a un Commit o una Proposal non accettati
+fn handshake_rejected(epoch: u64, status: StatusCode) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(
+        warp::reply::json(&HandshakeResponse { accepted: false, epoch, seq: None }),
+        status,
+    )
+}
+
+// 4. Un membro pubblica un Commit firmato: accettato solo il primo per ogni epoch
+async fn handle_commit(
+    req: CommitRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Ok(message) = BASE64.decode(&req.message) else {
+        return Ok(handshake_rejected(0, StatusCode::BAD_REQUEST));
+    };
+
+    let room_id = req.room_id.clone();
+    groups.update(&room_id, move |store| {
+        let Some(gs) = store.get_mut(&req.room_id).filter(|gs| gs.is_member(&req.sender_identity)) else {
+            return Err(handshake_rejected(0, StatusCode::NOT_FOUND));
+        };
+
+        if let Err(e) = gs.verify_handshake(HandshakeKind::Commit, &req.room_id, &req.sender_identity, req.epoch, &message, &req.signature) {
+            println!("[MLS-DS] Commit rifiutato room={} sender={}: {}", req.room_id, req.sender_identity, e);
+            return Err(handshake_rejected(0, StatusCode::UNAUTHORIZED));
+        }
+        gs.touch();
+
+        if !gs.accepts(req.epoch) {
+            println!("[MLS-DS] Commit rifiutato room={} sender={} epoch={} (non corrente)", req.room_id, req.sender_identity, req.epoch);
+            return Err(handshake_rejected(gs.epoch, StatusCode::CONFLICT));
+        }
+
+        // I nuovi membri entrano con il Welcome, non devono ricevere il Commit
+        let targets: Vec<&str> = req.welcomes.iter().map(|w| w.target_identity.as_str()).collect();
+        if let Some(full) = gs.full_queue(&req.sender_identity, &targets) {
+            println!("[MLS-DS] Commit rifiutato room={} sender={}: coda di {} piena", req.room_id, req.sender_identity, full);
+            return Err(handshake_rejected(gs.epoch, StatusCode::TOO_MANY_REQUESTS));
+        }
+        let seq = gs.fan_out(HandshakeKind::Commit, req.epoch, &req.sender_identity, req.message.clone(), &targets);
+
+        let mut events = vec![RoomEvent::Message {
+            seq,
+            kind: HandshakeKind::Commit,
+            epoch: req.epoch,
+            sender: req.sender_identity.clone(),
+        }];
+
+        for welcome in &req.welcomes {
+            if let Some(target) = gs.roster.iter_mut().find(|m| m.identity == welcome.target_identity) {
+                target.welcome_message = Some(welcome.welcome_message.clone());
+                gs.queues.remove(&welcome.target_identity);
+                events.push(RoomEvent::WelcomeAvailable { identity: welcome.target_identity.clone() });
+            }
+        }
+
+        // Il Commit del membro incaricato include le rimozioni pendenti
+        for member in gs.roster.iter_mut().filter(|m| m.removal_committer.as_deref() == Some(&req.sender_identity)) {
+            member.removal_committer = None;
+        }
+
+        gs.last_commit_epoch = Some(req.epoch);
+        gs.epoch += 1;
+        events.push(RoomEvent::Epoch { epoch: gs.epoch });
+
+        println!("[MLS-DS] Commit accettato room={} sender={} epoch MLS={} (Nuova Epoch: {})", req.room_id, req.sender_identity, req.epoch, gs.epoch);
+
+        Ok((
+            warp::reply::with_status(
+                warp::reply::json(&HandshakeResponse { accepted: true, epoch: gs.epoch, seq: Some(seq) }),
+                StatusCode::OK,
+            ),
+            events,
+        ))
+    })
+    .await
+}
+
+// 5. Un membro pubblica una Proposal firmata per l'epoch corrente
+async fn handle_proposal(
+    req: ProposalRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Ok(message) = BASE64.decode(&req.message) else {
+        return Ok(handshake_rejected(0, StatusCode::BAD_REQUEST));
+    };
+
+    let room_id = req.room_id.clone();
+    groups.update(&room_id, move |store| {
+        let Some(gs) = store.get_mut(&req.room_id).filter(|gs| gs.is_member(&req.sender_identity)) else {
+            return Err(handshake_rejected(0, StatusCode::NOT_FOUND));
+        };
+
+        if let Err(e) = gs.verify_handshake(HandshakeKind::Proposal, &req.room_id, &req.sender_identity, req.epoch, &message, &req.signature) {
+            println!("[MLS-DS] Proposal rifiutata room={} sender={}: {}", req.room_id, req.sender_identity, e);
+            return Err(handshake_rejected(0, StatusCode::UNAUTHORIZED));
+        }
+        gs.touch();
+
+        // Una Proposal fuori dall'epoch corrente non può essere inclusa
+        if !gs.accepts(req.epoch) {
+            return Err(handshake_rejected(gs.epoch, StatusCode::CONFLICT));
+        }
+        if let Some(full) = gs.full_queue(&req.sender_identity, &[]) {
+            println!("[MLS-DS] Proposal rifiutata room={} sender={}: coda di {} piena", req.room_id, req.sender_identity, full);
+            return Err(handshake_rejected(gs.epoch, StatusCode::TOO_MANY_REQUESTS));
+        }
+
+        let seq = gs.fan_out(HandshakeKind::Proposal, req.epoch, &req.sender_identity, req.message, &[]);
+        println!("[MLS-DS] Proposal room={} sender={} epoch MLS={}", req.room_id, req.sender_identity, req.epoch);
+
+        Ok((
+            warp::reply::with_status(
+                warp::reply::json(&HandshakeResponse { accepted: true, epoch: gs.epoch, seq: Some(seq) }),
+                StatusCode::OK,
+            ),
+            vec![RoomEvent::Message {
+                seq,
+                kind: HandshakeKind::Proposal,
+                epoch: req.epoch,
+                sender: req.sender_identity,
+            }],
+        ))
+    })
+    .await
+}
+
+// 6. Un membro legge la propria coda (in ordine di seq) dopo `after`. È una
+//    sola lettura: i messaggi escono dalla coda con l'ack firmato.
+async fn handle_messages(
+    query: MessagesQuery,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = groups
+        .blocking(move |groups| {
+            let mut store = groups.inner.lock().unwrap();
+
+            let Some(gs) = store.get_mut(&query.room_id) else {
+                return MessagesResponse {
+                    epoch: 0,
+                    room_id: query.room_id,
+                    messages: vec![],
+                };
+            };
+            gs.touch_read();
+
+            let messages = gs
+                .queues
+                .get(&query.identity)
+                .map(|queue| queue.iter().filter(|m| m.seq > query.after).cloned().collect())
+                .unwrap_or_default();
+
+            MessagesResponse {
+                epoch: gs.epoch,
+                room_id: query.room_id,
+                messages,
+            }
+        })
+        .await;
+    Ok(warp::reply::json(&response))
+}
+
+// 6b. Il membro conferma di aver elaborato la coda fino ad `after`, firmando
+//     come nel leave; la coda ridotta viene salvata
+async fn handle_ack(
+    req: AckRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let rejected = |status| {
+        warp::reply::with_status(warp::reply::json(&GenericResponse { success: false }), status)
+    };
+
+    let room_id = req.room_id.clone();
+    groups.update(&room_id, move |store| {
+        let Some(gs) = store.get_mut(&req.room_id) else {
+            return Err(rejected(StatusCode::NOT_FOUND));
+        };
+        let Some(member) = gs.roster.iter().find(|m| m.identity == req.identity && m.is_active()) else {
+            return Err(rejected(StatusCode::FORBIDDEN));
+        };
+        let verified = auth::decode_key_package(&member.key_package)
+            .and_then(|kp| auth::verify_ack(&kp, &req.room_id, &req.identity, req.after, &req.signature));
+        if let Err(e) = verified {
+            println!("[MLS-DS] Ack rifiutato room={} identity={}: {}", req.room_id, req.identity, e);
+            return Err(rejected(StatusCode::UNAUTHORIZED));
+        }
+
+        let ok = warp::reply::with_status(warp::reply::json(&GenericResponse { success: true }), StatusCode::OK);
+        let Some(queue) = gs.queues.get_mut(&req.identity) else {
+            return Err(ok);
+        };
+        let before = queue.len();
+        queue.retain(|m| m.seq > req.after);
+        if queue.len() == before {
+            return Err(ok); // niente da salvare
+        }
+        gs.touch();
+        Ok((ok, vec![]))
+    })
+    .await
+}
+
+// 7. WebSocket della stanza: snapshot del roster all'apertura, poi eventi.
+//    I messaggi in arrivo dal client vengono ignorati.
+async fn handle_ws(
+    ws: Ws,
+    query: RosterQuery,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(ws.on_upgrade(move |socket| room_socket(socket, query.room_id, groups)))
+}
+
+async fn room_socket(socket: WebSocket, room_id: String, groups: Groups) {
+    let (mut tx, mut rx) = socket.split();
+    let mut events = groups.subscribe(&room_id);
+
+    println!("[MLS-DS] WS aperto room={}", room_id);
+
+    if send_event(&mut tx, &groups.roster_event(&room_id)).await.is_ok() {
+        loop {
+            tokio::select! {
+                received = events.recv() => {
+                    let event = match received {
+                        Ok(event) => event,
+                        // Client troppo lento: al posto degli eventi persi lo stato attuale
+                        Err(RecvError::Lagged(_)) => groups.roster_event(&room_id),
+                        Err(RecvError::Closed) => break,
+                    };
+                    if send_event(&mut tx, &event).await.is_err() {
+                        break;
+                    }
+                }
+                incoming = rx.next() => match incoming {
+                    Some(Ok(msg)) if !msg.is_close() => {}
+                    _ => break,
+                },
+            }
+        }
+    }
+
+    println!("[MLS-DS] WS chiuso room={}", room_id);
+}
+
+async fn send_event(
+    tx: &mut SplitSink<WebSocket, Message>,
+    event: &RoomEvent,
+) -> Result<(), warp::Error> {
+    let text = serde_json::to_string(event).expect("RoomEvent è sempre serializzabile");
+    tx.send(Message::text(text)).await
+}
+
+// 8. Un membro lascia la stanza, firmando la richiesta come nel join
+async fn handle_leave(
+    req: LeaveRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if now_secs().abs_diff(req.timestamp) > MAX_CLOCK_SKEW_SECS {
+        println!("[MLS-DS] Leave rifiutato room={} identity={}: timestamp fuori finestra", req.room_id, req.identity);
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&LeaveResponse { success: false, epoch: 0, committer: None }),
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let (room_id, identity) = (req.room_id.clone(), req.identity.clone());
+    remove_from_room(&groups, room_id, identity, "Leave", move |member| {
+        // Un leave firmato prima dell'ultimo join non vale più
+        if req.timestamp <= member.last_join_ts {
+            println!("[MLS-DS] Leave rifiutato room={} identity={}: richiesta ripetuta", req.room_id, req.identity);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        let verified = auth::decode_key_package(&member.key_package).and_then(|kp| {
+            auth::verify_leave(&kp, &req.room_id, &req.identity, req.timestamp, &req.signature)
+        });
+        verified.map_err(|e| {
+            println!("[MLS-DS] Leave rifiutato room={} identity={}: {}", req.room_id, req.identity, e);
+            StatusCode::UNAUTHORIZED
+        })
+    })
+    .await
+}
+
+// 9. Un amministratore rimuove un membro (header X-Admin-Token)
+async fn handle_remove(
+    req: RemoveRequest,
+    token: Option<String>,
+    admin_token: Arc<Option<String>>,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let authorized = matches!((admin_token.as_ref(), token), (Some(expected), Some(got)) if token_eq(expected, &got));
+    if !authorized {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&LeaveResponse { success: false, epoch: 0, committer: None }),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    remove_from_room(&groups, req.room_id, req.identity, "Remove", |_| Ok(())).await
+}
+
+// `authorize` vede il membro attivo prima della rimozione, sotto lo stesso lock
+async fn remove_from_room(
+    groups: &Groups,
+    room_id: String,
+    identity: String,
+    action: &'static str,
+    authorize: impl FnOnce(&MemberEntry) -> Result<(), StatusCode> + Send + 'static,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    groups.update(&room_id.clone(), move |store| {
+        let Some(gs) = store.get_mut(&room_id) else {
+            return Err(warp::reply::with_status(
+                warp::reply::json(&LeaveResponse { success: false, epoch: 0, committer: None }),
+                StatusCode::NOT_FOUND,
+            ));
+        };
+
+        let Some(member) = gs.roster.iter().find(|m| m.identity == identity && m.is_active()) else {
+            return Err(warp::reply::with_status(
+                warp::reply::json(&LeaveResponse { success: false, epoch: gs.epoch, committer: None }),
+                StatusCode::NOT_FOUND,
+            ));
+        };
+        if let Err(status) = authorize(member) {
+            return Err(warp::reply::with_status(
+                warp::reply::json(&LeaveResponse { success: false, epoch: gs.epoch, committer: None }),
+                status,
+            ));
+        }
+
+        gs.touch();
+
+        let (index, committer) = gs.remove_member(&identity).expect("membro attivo appena trovato");
+
+        println!("[MLS-DS] {} room={} identity={} index={} (Nuova Epoch: {}, commit a carico di {:?})", action, room_id, identity, index, gs.epoch, committer);
+
+        let events = vec![
+            RoomEvent::MemberLeft {
+                index,
+                identity,
+                committer: committer.clone(),
+            },
+            RoomEvent::Epoch { epoch: gs.epoch },
+            gs.roster_event(),
+        ];
+        Ok((
+            warp::reply::with_status(
+                warp::reply::json(&LeaveResponse { success: true, epoch: gs.epoch, committer }),
+                StatusCode::OK,
+            ),
+            events,
+        ))
+    })
+    .await
+}
+
+// 10. Creazione esplicita di una stanza con ID casuale
+async fn handle_create(
+    req: CreateRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let ttl_secs = req.ttl_secs.unwrap_or(groups.room_ttl).min(groups.room_ttl);
+    let max_members = req.max_members.filter(|&max| max > 0);
+
+    let room_id = random_id(ROOM_ID_BYTES);
+    let owner_token = random_id(OWNER_TOKEN_BYTES);
+
+    let state = GroupState::new(owner_token.clone(), max_members, ttl_secs);
+    groups
+        .update(&room_id, {
+            let room_id = room_id.clone();
+            move |store| {
+                store.insert(&room_id, state);
+                Ok(((), vec![]))
+            }
+        })
+        .await?;
+
+    println!("[MLS-DS] Stanza {} creata (max membri: {:?}, TTL: {}s)", room_id, max_members, ttl_secs);
+
+    Ok(warp::reply::json(&CreateResponse {
+        room_id,
+        owner_token,
+        max_members,
+        ttl_secs,
+    }))
+}
+
+// 11. Il creatore chiude la stanza (header X-Owner-Token)
+async fn handle_close(
+    req: CloseRequest,
+    token: Option<String>,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let status = groups
+        .blocking(move |groups| {
+            let mut store = groups.inner.lock().unwrap();
+
+            match store.get(&req.room_id) {
+                None => Ok::<_, warp::Rejection>(StatusCode::NOT_FOUND),
+                Some(gs) if gs.owner_token.is_empty() || !token.is_some_and(|t| token_eq(&gs.owner_token, &t)) => {
+                    Ok(StatusCode::FORBIDDEN)
+                }
+                Some(_) => {
+                    groups.close_room(store.as_mut(), &req.room_id, CloseReason::Owner).map_err(|e| {
+                        eprintln!("[MLS-DS] Errore eliminazione room={}: {}", req.room_id, e);
+                        warp::reject::custom(StorageError)
+                    })?;
+                    println!("[MLS-DS] Stanza {} chiusa dal creatore", req.room_id);
+                    Ok(StatusCode::OK)
+                }
+            }
+        })
+        .await?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&GenericResponse { success: status == StatusCode::OK }),
+        status,
+    ))
+}
+
+// 12. Un client pubblica KeyPackage monouso (e un last resort) nella directory
+async fn handle_publish_key_packages(
+    req: PublishRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reply = |status, (available, last_resort)| {
+        warp::reply::with_status(
+            warp::reply::json(&DirectoryStatus {
+                identity: req.identity.clone(),
+                available,
+                last_resort,
+            }),
+            status,
+        )
+    };
+
+    if req.key_packages.len() > directory::MAX_ONE_TIME {
+        return Ok(reply(StatusCode::PAYLOAD_TOO_LARGE, (0, false)));
+    }
+    if now_secs().abs_diff(req.timestamp) > MAX_CLOCK_SKEW_SECS {
+        println!("[MLS-DS] KeyPackage rifiutati identity={}: timestamp fuori finestra", req.identity);
+        return Ok(reply(StatusCode::UNAUTHORIZED, (0, false)));
+    }
+
+    // Ogni KeyPackage deve essere valido, dello stesso tipo dichiarato
+    // (monouso / last resort) e firmato con la stessa chiave
+    let batch = req.key_packages.iter().map(|kp| (kp, false))
+        .chain(req.last_resort.iter().map(|kp| (kp, true)));
+    let mut signature_key: Option<Vec<u8>> = None;
+    let mut one_time = Vec::with_capacity(req.key_packages.len());
+    let mut last_resort = None;
+    for (encoded, expect_last_resort) in batch {
+        let kp = match auth::decode_key_package(encoded) {
+            Ok(kp) if kp.last_resort == expect_last_resort => kp,
+            Ok(_) => {
+                println!("[MLS-DS] KeyPackage rifiutati identity={}: estensione last_resort errata", req.identity);
+                return Ok(reply(StatusCode::BAD_REQUEST, (0, false)));
+            }
+            Err(e) => {
+                println!("[MLS-DS] KeyPackage rifiutati identity={}: {}", req.identity, e);
+                return Ok(reply(StatusCode::BAD_REQUEST, (0, false)));
+            }
+        };
+
+        match &signature_key {
+            Some(key) if *key != kp.signature_key => {
+                println!("[MLS-DS] KeyPackage rifiutati identity={}: chiavi di firma diverse", req.identity);
+                return Ok(reply(StatusCode::BAD_REQUEST, (0, false)));
+            }
+            Some(_) => {}
+            None => {
+                if let Err(e) = auth::verify_upload(&kp, &req.identity, req.timestamp, &req.signature) {
+                    println!("[MLS-DS] KeyPackage rifiutati identity={}: {}", req.identity, e);
+                    let status = match e {
+                        AuthError::Malformed(_) | AuthError::UnsupportedCiphersuite(_) => StatusCode::BAD_REQUEST,
+                        AuthError::IdentityMismatch | AuthError::BadSignature => StatusCode::UNAUTHORIZED,
+                    };
+                    return Ok(reply(status, (0, false)));
+                }
+                signature_key = Some(kp.signature_key.clone());
+            }
+        }
+
+        // In directory solo il KeyPackage TLS (Base64), qualunque fosse l'involucro
+        let normalized = BASE64.encode(&kp.bytes);
+        match expect_last_resort {
+            true => last_resort = Some(normalized),
+            false => one_time.push(normalized),
+        }
+    }
+    let Some(signature_key) = signature_key else {
+        return Ok(reply(StatusCode::BAD_REQUEST, (0, false)));
+    };
+
+    let published = req.key_packages.len();
+    let (identity, timestamp) = (req.identity.clone(), req.timestamp);
+    let (status, counts) = groups
+        .update_directory(move |directory| {
+            if let Err(e) = directory.publish(
+                &identity,
+                &BASE64.encode(signature_key),
+                timestamp,
+                one_time,
+                last_resort,
+            ) {
+                println!("[MLS-DS] KeyPackage rifiutati identity={}: {}", identity, e);
+                let status = match e {
+                    PublishError::WrongSignatureKey => StatusCode::FORBIDDEN,
+                    PublishError::Replay => StatusCode::UNAUTHORIZED,
+                };
+                return Err((status, directory.status(&identity)));
+            }
+            Ok((StatusCode::OK, directory.status(&identity)))
+        })
+        .await?;
+
+    if status == StatusCode::OK {
+        println!("[MLS-DS] {} KeyPackage pubblicati identity={} (disponibili: {}, last resort: {})", published, req.identity, counts.0, counts.1);
+    }
+    Ok(reply(status, counts))
+}
+
+// 13. Quanti KeyPackage restano a un'identità (per sapere quando ripubblicare)
+async fn handle_key_package_status(
+    query: IdentityQuery,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (available, last_resort) = groups.key_packages.lock().unwrap().status(&query.identity);
+    Ok(warp::reply::json(&DirectoryStatus {
+        identity: query.identity,
+        available,
+        last_resort,
+    }))
+}
+
+// Il claimer deve essere un membro attivo della stanza e firmare la
+// richiesta: restituisce OK oppure lo status con cui rifiutarla
+async fn authorize_claim(req: &ClaimRequest, groups: &Groups) -> Result<StatusCode, warp::Rejection> {
+    if now_secs().abs_diff(req.timestamp) > MAX_CLOCK_SKEW_SECS {
+        println!("[MLS-DS] Claim rifiutato room={} claimer={}: timestamp fuori finestra", req.room_id, req.claimer);
+        return Ok(StatusCode::UNAUTHORIZED);
+    }
+
+    let (room_id, claimer, identity, timestamp, signature) = (
+        req.room_id.clone(),
+        req.claimer.clone(),
+        req.identity.clone(),
+        req.timestamp,
+        req.signature.clone(),
+    );
+    groups
+        .update(&req.room_id, move |store| {
+            let Some(member) = store
+                .get_mut(&room_id)
+                .and_then(|gs| gs.roster.iter_mut().find(|m| m.identity == claimer && m.is_active()))
+            else {
+                println!("[MLS-DS] Claim rifiutato room={} claimer={}: non è un membro", room_id, claimer);
+                return Err(StatusCode::FORBIDDEN);
+            };
+            if timestamp <= member.last_claim_ts {
+                println!("[MLS-DS] Claim rifiutato room={} claimer={}: richiesta ripetuta", room_id, claimer);
+                return Err(StatusCode::TOO_MANY_REQUESTS);
+            }
+            let verified = auth::decode_key_package(&member.key_package)
+                .and_then(|kp| auth::verify_claim(&kp, &room_id, &claimer, &identity, timestamp, &signature));
+            if let Err(e) = verified {
+                println!("[MLS-DS] Claim rifiutato room={} claimer={}: {}", room_id, claimer, e);
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+
+            member.last_claim_ts = timestamp;
+            Ok((StatusCode::OK, vec![]))
+        })
+        .await
+}
+
+// Consuma un KeyPackage di `identity` (e salva la directory)
+async fn claim_key_package(identity: &str, groups: &Groups) -> Result<Option<Claimed>, warp::Rejection> {
+    let identity = identity.to_string();
+    groups
+        .update_directory(move |directory| directory.claim(&identity).map(Some).ok_or(None))
+        .await
+}
+
+// 14. Un membro della stanza prende (in modo atomico) un KeyPackage dell'invitato
+async fn handle_claim_key_package(
+    req: ClaimRequest,
+    groups: Groups,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let rejected = |status| {
+        warp::reply::with_status(
+            warp::reply::json(&GenericResponse { success: false }),
+            status,
+        )
+    };
+
+    let status = authorize_claim(&req, &groups).await?;
+    if status != StatusCode::OK {
+        return Ok(rejected(status));
+    }
+
+    let (key_package, last_resort) = match claim_key_package(&req.identity, &groups).await? {
+        Some(Claimed::OneTime(kp)) => (kp, false),
+        Some(Claimed::LastResort(kp)) => (kp, true),
+        None => return Ok(rejected(StatusCode::NOT_FOUND)),
+    };
+
+    println!("[MLS-DS] KeyPackage di {} preso da {} (last resort: {})", req.identity, req.claimer, last_resort);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ClaimResponse {
+            identity: req.identity,
+            key_package,
+            last_resort,
+        }),
+        StatusCode::OK,
+    ))
+}
+
+// ─────────────────────────────────────────────────────────────
+// ENDPOINT BINARI (application/mls-message)
+// ─────────────────────────────────────────────────────────────
+// Stessa logica degli endpoint JSON: il corpo viene validato come
+// MLSMessage e poi conservato come gli altri messaggi.
+
+fn invalid_frame(what: &str, e: WireError) -> warp::reply::Response {
+    println!("[MLS-DS] {} binario rifiutato: {}", what, e);
+    let status = match e {
+        WireError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    warp::reply::with_status(warp::reply::json(&GenericResponse { success: false }), status).into_response()
+}
+
+fn not_found() -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&GenericResponse { success: false }), StatusCode::NOT_FOUND)
+        .into_response()
+}
+
+// Corpo MLS grezzo con i metadati in intestazioni X-Mls-*
+fn mls_response(bytes: Vec<u8>, headers: &[(&'static str, String)]) -> warp::reply::Response {
+    let mut response = warp::reply::Response::new(bytes.into());
+    let map = response.headers_mut();
+    map.insert(CONTENT_TYPE, HeaderValue::from_static(wire::CONTENT_TYPE));
+    for (name, value) in headers {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            map.insert(HeaderName::from_static(name), value);
+        }
+    }
+    response
+}
+
+// 15. Commit binario, eventualmente seguito dal Welcome per i nuovi membri.
+//     L'epoch si legge dal framing (è in chiaro anche nei PrivateMessage).
+async fn handle_bin_commit(
+    query: BinHandshakeQuery,
+    body: Bytes,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let handshake = [WireFormat::PublicMessage, WireFormat::PrivateMessage];
+    let frame = match wire::parse_mls_message(&body, &handshake) {
+        Ok(frame) if frame.content_type == Some(ContentType::Commit) => frame,
+        Ok(_) => return Ok(invalid_frame("Commit", WireError::Malformed("non è un Commit"))),
+        Err(e) => return Ok(invalid_frame("Commit", e)),
+    };
+    let Some(epoch) = frame.epoch else {
+        return Ok(invalid_frame("Commit", WireError::Malformed("epoch mancante")));
+    };
+    let (commit, rest) = body.split_at(frame.len);
+
+    let targets: Vec<&str> = query
+        .welcome_to
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .collect();
+    let welcomes = match (rest.is_empty(), targets.is_empty()) {
+        (true, true) => vec![],
+        (false, false) => {
+            match wire::parse_mls_message(rest, &[WireFormat::Welcome]) {
+                Ok(welcome) if welcome.len == rest.len() => {}
+                Ok(_) => return Ok(invalid_frame("Commit", WireError::Malformed("byte in eccesso"))),
+                Err(e) => return Ok(invalid_frame("Welcome", e)),
+            }
+            let welcome = BASE64.encode(rest);
+            targets
+                .iter()
+                .map(|t| WelcomeEntry {
+                    target_identity: t.to_string(),
+                    welcome_message: welcome.clone(),
+                })
+                .collect()
+        }
+        _ => return Ok(invalid_frame("Commit", WireError::Malformed("Welcome e welcome_to vanno insieme"))),
+    };
+
+    let req = CommitRequest {
+        room_id: query.room_id,
+        sender_identity: query.sender_identity,
+        epoch,
+        message: BASE64.encode(commit),
+        welcomes,
+        signature: query.signature,
+    };
+    handle_commit(req, groups).await.map(Reply::into_response)
+}
+
+// 16. Proposal binaria
+async fn handle_bin_proposal(
+    query: BinHandshakeQuery,
+    body: Bytes,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let handshake = [WireFormat::PublicMessage, WireFormat::PrivateMessage];
+    let frame = match wire::parse_mls_message(&body, &handshake) {
+        Ok(frame) if frame.content_type == Some(ContentType::Proposal) && frame.len == body.len() => frame,
+        Ok(_) => return Ok(invalid_frame("Proposal", WireError::Malformed("non è una sola Proposal"))),
+        Err(e) => return Ok(invalid_frame("Proposal", e)),
+    };
+    let Some(epoch) = frame.epoch else {
+        return Ok(invalid_frame("Proposal", WireError::Malformed("epoch mancante")));
+    };
+
+    let req = ProposalRequest {
+        room_id: query.room_id,
+        sender_identity: query.sender_identity,
+        epoch,
+        message: BASE64.encode(&body),
+        signature: query.signature,
+    };
+    handle_proposal(req, groups).await.map(Reply::into_response)
+}
+
+// 17. Welcome binario per un nuovo membro
+async fn handle_bin_welcome(
+    query: BinWelcomeQuery,
+    body: Bytes,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    match wire::parse_mls_message(&body, &[WireFormat::Welcome]) {
+        Ok(frame) if frame.len == body.len() => {}
+        Ok(_) => return Ok(invalid_frame("Welcome", WireError::Malformed("byte in eccesso"))),
+        Err(e) => return Ok(invalid_frame("Welcome", e)),
+    }
+
+    let req = WelcomeRequest {
+        room_id: query.room_id,
+        target_identity: query.target_identity,
+        welcome_message: BASE64.encode(&body),
+    };
+    handle_welcome(req, groups).await.map(Reply::into_response)
+}
+
+// 18. Il nuovo membro scarica il suo Welcome (solo se è un MLSMessage)
+async fn handle_bin_get_welcome(
+    query: MemberQuery,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let welcome = groups
+        .blocking(move |groups| {
+            let store = groups.inner.lock().unwrap();
+            store
+                .get(&query.room_id)
+                .and_then(|gs| gs.roster.iter().find(|m| m.identity == query.identity && m.is_active()))
+                .and_then(|m| m.welcome_message.as_deref())
+                .and_then(|w| BASE64.decode(w).ok())
+        })
+        .await;
+    let Some(welcome) = welcome else {
+        return Ok(not_found());
+    };
+
+    // I Welcome caricati in JSON dalla webapp hanno un altro formato
+    if wire::parse_mls_message(&welcome, &[WireFormat::Welcome]).is_err() {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&GenericResponse { success: false }),
+            StatusCode::NOT_ACCEPTABLE,
+        )
+        .into_response());
+    }
+    Ok(mls_response(welcome, &[]))
+}
+
+// 19. Un messaggio della propria coda, per seq
+async fn handle_bin_message(
+    query: BinMessageQuery,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let queued = groups
+        .blocking(move |groups| {
+            let store = groups.inner.lock().unwrap();
+            store
+                .get(&query.room_id)
+                .and_then(|gs| gs.queues.get(&query.identity))
+                .and_then(|queue| queue.iter().find(|m| m.seq == query.seq))
+                .cloned()
+        })
+        .await;
+    let Some(queued) = queued else {
+        return Ok(not_found());
+    };
+    let Ok(bytes) = BASE64.decode(&queued.message) else {
+        return Ok(not_found());
+    };
+
+    let kind = match queued.kind {
+        HandshakeKind::Commit => "commit",
+        HandshakeKind::Proposal => "proposal",
+    };
+    Ok(mls_response(bytes, &[
+        ("x-mls-kind", kind.to_string()),
+        ("x-mls-epoch", queued.epoch.to_string()),
+        ("x-mls-sender", queued.sender),
+    ]))
+}
+
+// 20. Claim dalla directory con il KeyPackage incorniciato come MLSMessage
+async fn handle_bin_claim_key_package(
+    query: ClaimRequest,
+    groups: Groups,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let status = authorize_claim(&query, &groups).await?;
+    if status != StatusCode::OK {
+        return Ok(warp::reply::with_status(warp::reply::json(&GenericResponse { success: false }), status)
+            .into_response());
     }
+
+    let (key_package, last_resort) = match claim_key_package(&query.identity, &groups).await? {
+        Some(Claimed::OneTime(kp)) => (kp, false),
+        Some(Claimed::LastResort(kp)) => (kp, true),
+        None => return Ok(not_found()),
+    };
+    let Ok(bytes) = BASE64.decode(key_package) else {
+        return Ok(not_found());
+    };
+
+    println!("[MLS-DS] KeyPackage di {} preso in binario da {} (last resort: {})", query.identity, query.claimer, last_resort);
+    Ok(mls_response(wire::frame_key_package(&bytes), &[
+        ("x-mls-last-resort", last_resort.to_string()),
+    ]))
 }
 
 // ─────────────────────────────────────────────────────────────
 // MAIN
 // ─────────────────────────────────────────────────────────────
 
+// Valore dell'opzione `--name <valore>` sulla riga di comando
+fn cli_option(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+// --storage memory (default) | --storage file:<dir>
+fn open_store() -> Box<dyn RoomStore> {
+    let storage = cli_option("--storage").unwrap_or_else(|| "memory".to_string());
+
+    match storage.split_once(':') {
+        Some(("file", dir)) => {
+            let store = FileStore::open(dir).unwrap_or_else(|e| {
+                eprintln!("[MLS-DS] Impossibile aprire lo storage in {}: {}", dir, e);
+                std::process::exit(1);
+            });
+            println!("[MLS-DS] Storage su file in {} ({} stanze ripristinate)", dir, store.room_count());
+            Box::new(store)
+        }
+        _ if storage == "memory" => Box::new(MemoryStore::default()),
+        _ => {
+            eprintln!("[MLS-DS] --storage non valido: {} (usa memory oppure file:<dir>)", storage);
+            std::process::exit(2);
+        }
+    }
+}
+
+// --room-ttl <secondi>: inattività massima di una stanza
+fn room_ttl() -> u64 {
+    match cli_option("--room-ttl") {
+        None => DEFAULT_ROOM_TTL_SECS,
+        Some(ttl) => ttl.parse().ok().filter(|&ttl| ttl > 0).unwrap_or_else(|| {
+            eprintln!("[MLS-DS] --room-ttl non valido: {} (secondi > 0)", ttl);
+            std::process::exit(2);
+        }),
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let groups = Groups::new();
+    let room_ttl = room_ttl();
+    let groups = Groups::new(open_store(), room_ttl);
+
+    // Garbage collection delle stanze inattive (e salvataggio dell'attività letta)
+    let gc = groups.clone();
+    let gc_every = std::time::Duration::from_secs(GC_INTERVAL_SECS.min(room_ttl));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(gc_every);
+        loop {
+            interval.tick().await;
+            gc.blocking(Groups::collect_expired).await;
+        }
+    });
+
+    // Senza MLS_ADMIN_TOKEN l'endpoint /mls/remove rifiuta ogni richiesta
+    let admin_token = Arc::new(std::env::var("MLS_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()));
 
     let join_route = warp::path!("mls" / "join")
         .and(warp::post())
@@ -208,14 +1834,148 @@ async fn main() {
         .and(with_groups(groups.clone()))
         .and_then(handle_roster);
 
+    let commit_route = warp::path!("mls" / "commit")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_commit);
+
+    let proposal_route = warp::path!("mls" / "proposal")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_proposal);
+
+    let messages_route = warp::path!("mls" / "messages")
+        .and(warp::get())
+        .and(warp::query::<MessagesQuery>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_messages);
+
+    let ack_route = warp::path!("mls" / "messages" / "ack")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_ack);
+
+    let ws_route = warp::path!("mls" / "ws")
+        .and(warp::ws())
+        .and(warp::query::<RosterQuery>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_ws);
+
+    let leave_route = warp::path!("mls" / "leave")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_leave);
+
+    let remove_route = warp::path!("mls" / "remove")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(warp::header::optional::<String>("x-admin-token"))
+        .and(warp::any().map(move || admin_token.clone()))
+        .and(with_groups(groups.clone()))
+        .and_then(handle_remove);
+
+    let create_route = warp::path!("mls" / "create")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_create);
+
+    let close_route = warp::path!("mls" / "close")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(warp::header::optional::<String>("x-owner-token"))
+        .and(with_groups(groups.clone()))
+        .and_then(handle_close);
+
+    let publish_kp_route = warp::path!("mls" / "keypackages")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_publish_key_packages);
+
+    let kp_status_route = warp::path!("mls" / "keypackages")
+        .and(warp::get())
+        .and(warp::query::<IdentityQuery>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_key_package_status);
+
+    let claim_kp_route = warp::path!("mls" / "keypackages" / "claim")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_claim_key_package);
+
+    let bin_commit_route = warp::path!("mls" / "bin" / "commit")
+        .and(warp::post())
+        .and(warp::query::<BinHandshakeQuery>())
+        .and(mls_body())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_commit);
+
+    let bin_proposal_route = warp::path!("mls" / "bin" / "proposal")
+        .and(warp::post())
+        .and(warp::query::<BinHandshakeQuery>())
+        .and(mls_body())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_proposal);
+
+    let bin_welcome_route = warp::path!("mls" / "bin" / "welcome")
+        .and(warp::post())
+        .and(warp::query::<BinWelcomeQuery>())
+        .and(mls_body())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_welcome);
+
+    let bin_get_welcome_route = warp::path!("mls" / "bin" / "welcome")
+        .and(warp::get())
+        .and(warp::query::<MemberQuery>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_get_welcome);
+
+    let bin_message_route = warp::path!("mls" / "bin" / "message")
+        .and(warp::get())
+        .and(warp::query::<BinMessageQuery>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_message);
+
+    let bin_claim_kp_route = warp::path!("mls" / "bin" / "keypackages" / "claim")
+        .and(warp::post())
+        .and(warp::query::<ClaimRequest>())
+        .and(with_groups(groups.clone()))
+        .and_then(handle_bin_claim_key_package);
+
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_headers(vec!["Content-Type"])
-        .allow_methods(vec!["GET", "POST"]);
+        .allow_headers(vec!["Content-Type", "X-Admin-Token", "X-Owner-Token"])
+        .allow_methods(vec!["GET", "POST"])
+        .expose_headers(vec!["X-Mls-Kind", "X-Mls-Epoch", "X-Mls-Sender", "X-Mls-Last-Resort"]);
 
     let routes = join_route
         .or(welcome_route)
         .or(roster_route)
+        .or(commit_route)
+        .or(proposal_route)
+        .or(messages_route)
+        .or(ack_route)
+        .or(ws_route)
+        .or(leave_route)
+        .or(remove_route)
+        .or(create_route)
+        .or(close_route)
+        .or(publish_kp_route)
+        .or(kp_status_route)
+        .or(claim_kp_route)
+        .or(bin_commit_route)
+        .or(bin_proposal_route)
+        .or(bin_welcome_route)
+        .or(bin_get_welcome_route)
+        .or(bin_message_route)
+        .or(bin_claim_kp_route)
+        .recover(handle_rejection)
         .with(cors);
 
     println!("MLS Delivery Service running on http://0.0.0.0:3000");
@@ -225,6 +1985,13 @@ async fn main() {
         .await;
 }
 
+// Corpo application/mls-message entro il limite globale
+fn mls_body() -> impl Filter<Extract = (Bytes,), Error = warp::Rejection> + Clone {
+    warp::header::exact_ignore_case("content-type", wire::CONTENT_TYPE)
+        .and(warp::body::content_length_limit(wire::MAX_BODY_BYTES))
+        .and(warp::body::bytes())
+}
+
 fn with_groups(
     groups: Groups,
 ) -> impl Filter<Extract = (Groups,), Error = std::convert::Infallible> + Clone {
@@ -0,0 +1,182 @@
+// ─────────────────────────────────────────────────────────────
+// STORAGE – dove vivono le stanze (memoria o file JSON)
+// ─────────────────────────────────────────────────────────────
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::GroupState;
+use crate::directory::KeyPackageDirectory;
+
+const DIRECTORY_FILE: &str = "keypackages.json";
+
+// Gli handler modificano lo stato con get_mut/insert e poi chiamano
+// flush: è lì che un backend persistente scrive la stanza. remove la
+// cancella anche dal backend (chiusura o scadenza). Tutti i metodi
+// possono bloccare: si chiamano da Groups::blocking, mai dal runtime.
+// La directory dei KeyPackage vive in Groups: il backend la restituisce
+// una volta all'avvio e la riscrive a ogni modifica.
+pub trait RoomStore: Send {
+    fn get(&self, room_id: &str) -> Option<&GroupState>;
+    fn get_mut(&mut self, room_id: &str) -> Option<&mut GroupState>;
+    fn insert(&mut self, room_id: &str, state: GroupState) -> &mut GroupState;
+    fn flush(&mut self, room_id: &str) -> io::Result<()>;
+    fn remove(&mut self, room_id: &str) -> io::Result<()>;
+    fn room_ids(&self) -> Vec<String>;
+    fn take_directory(&mut self) -> KeyPackageDirectory;
+    fn flush_directory(&mut self, directory: &KeyPackageDirectory) -> io::Result<()>;
+}
+
+// ─────────────────────────────────────────────────────────────
+// MEMORIA (default): tutto si perde al riavvio
+// ─────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct MemoryStore {
+    rooms: HashMap<String, GroupState>,
+}
+
+impl RoomStore for MemoryStore {
+    fn get(&self, room_id: &str) -> Option<&GroupState> {
+        self.rooms.get(room_id)
+    }
+
+    fn get_mut(&mut self, room_id: &str) -> Option<&mut GroupState> {
+        self.rooms.get_mut(room_id)
+    }
+
+    fn insert(&mut self, room_id: &str, state: GroupState) -> &mut GroupState {
+        self.rooms.entry(room_id.to_string()).insert_entry(state).into_mut()
+    }
+
+    fn flush(&mut self, _room_id: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&mut self, room_id: &str) -> io::Result<()> {
+        self.rooms.remove(room_id);
+        Ok(())
+    }
+
+    fn room_ids(&self) -> Vec<String> {
+        self.rooms.keys().cloned().collect()
+    }
+
+    fn take_directory(&mut self) -> KeyPackageDirectory {
+        KeyPackageDirectory::default()
+    }
+
+    fn flush_directory(&mut self, _directory: &KeyPackageDirectory) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────
+// FILE: un JSON per stanza in `dir`, ricaricati all'avvio, più
+// keypackages.json per la directory
+// ─────────────────────────────────────────────────────────────
+
+pub struct FileStore {
+    dir: PathBuf,
+    rooms: HashMap<String, GroupState>,
+    // Letta da open, consegnata a Groups con take_directory
+    directory: Option<KeyPackageDirectory>,
+}
+
+impl FileStore {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut rooms = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let Some(room_id) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("room-")?.strip_suffix(".json"))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+
+            let state = serde_json::from_slice(&fs::read(&path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+            rooms.insert(room_id, state);
+        }
+
+        let directory = match fs::read(dir.join(DIRECTORY_FILE)) {
+            Ok(bytes) => Some(serde_json::from_slice(&bytes).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{DIRECTORY_FILE}: {e}"))
+            })?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(FileStore { dir, rooms, directory })
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    fn path(&self, room_id: &str) -> PathBuf {
+        self.dir.join(format!("room-{room_id}.json"))
+    }
+}
+
+impl RoomStore for FileStore {
+    fn get(&self, room_id: &str) -> Option<&GroupState> {
+        self.rooms.get(room_id)
+    }
+
+    fn get_mut(&mut self, room_id: &str) -> Option<&mut GroupState> {
+        self.rooms.get_mut(room_id)
+    }
+
+    fn insert(&mut self, room_id: &str, state: GroupState) -> &mut GroupState {
+        self.rooms.entry(room_id.to_string()).insert_entry(state).into_mut()
+    }
+
+    fn flush(&mut self, room_id: &str) -> io::Result<()> {
+        let Some(state) = self.rooms.get(room_id) else {
+            return Ok(());
+        };
+
+        write_atomic(self.path(room_id), &serde_json::to_vec(state)?)
+    }
+
+    // Prima il file: se la cancellazione fallisce la stanza resta com'era
+    fn remove(&mut self, room_id: &str) -> io::Result<()> {
+        if !self.rooms.contains_key(room_id) {
+            return Ok(());
+        }
+        match fs::remove_file(self.path(room_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        self.rooms.remove(room_id);
+        Ok(())
+    }
+
+    fn room_ids(&self) -> Vec<String> {
+        self.rooms.keys().cloned().collect()
+    }
+
+    fn take_directory(&mut self) -> KeyPackageDirectory {
+        self.directory.take().unwrap_or_default()
+    }
+
+    fn flush_directory(&mut self, directory: &KeyPackageDirectory) -> io::Result<()> {
+        write_atomic(self.dir.join(DIRECTORY_FILE), &serde_json::to_vec(directory)?)
+    }
+}
+
+// Scrittura atomica: file temporaneo + rename
+fn write_atomic(path: PathBuf, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, path)
+}
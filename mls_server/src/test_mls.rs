@@ -0,0 +1,199 @@
+// ─────────────────────────────────────────────────────────────
+// TEST – messaggi MLS veri, generati con OpenMLS
+// ─────────────────────────────────────────────────────────────
+//
+// OpenMLS è solo una dev-dependency: il server resta cieco, ma i parser
+// di wire.rs e auth.rs si provano su quello che un client invia davvero.
+
+use openmls::prelude::tls_codec::{Serialize as _, VLBytes};
+use openmls::prelude::*;
+use openmls_basic_credential::SignatureKeyPair;
+use openmls_rust_crypto::OpenMlsRustCrypto;
+use openmls_traits::signatures::Signer as _;
+
+pub const ED25519: Ciphersuite = Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
+pub const P256: Ciphersuite = Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256;
+
+pub struct Client {
+    pub ciphersuite: Ciphersuite,
+    pub provider: OpenMlsRustCrypto,
+    pub signer: SignatureKeyPair,
+    credential: CredentialWithKey,
+}
+
+impl Client {
+    pub fn new(identity: &str, ciphersuite: Ciphersuite) -> Self {
+        Self::with_credential(ciphersuite, |_| BasicCredential::new(identity.as_bytes().to_vec()).into())
+    }
+
+    /// Credential X.509 con un solo certificato, CN `common_name`
+    pub fn new_x509(common_name: &str, ciphersuite: Ciphersuite) -> Self {
+        Self::with_credential(ciphersuite, |public_key| x509_credential(&x509_certificate(common_name, public_key)))
+    }
+
+    /// `credential` riceve la chiave pubblica di firma appena generata
+    pub fn with_credential(ciphersuite: Ciphersuite, credential: impl FnOnce(&[u8]) -> Credential) -> Self {
+        let signer = SignatureKeyPair::new(ciphersuite.signature_algorithm()).unwrap();
+        let credential = CredentialWithKey {
+            credential: credential(signer.public()),
+            signature_key: signer.public().into(),
+        };
+        Client {
+            ciphersuite,
+            provider: OpenMlsRustCrypto::default(),
+            signer,
+            credential,
+        }
+    }
+
+    pub fn key_package(&self, last_resort: bool) -> KeyPackage {
+        let mut builder = KeyPackage::builder();
+        if last_resort {
+            builder = builder.mark_as_last_resort();
+        }
+        builder
+            .build(self.ciphersuite, &self.provider, &self.signer, self.credential.clone())
+            .unwrap()
+            .key_package()
+            .clone()
+    }
+
+    /// KeyPackage TLS nudo, come nella directory e nel join
+    pub fn key_package_bytes(&self, last_resort: bool) -> Vec<u8> {
+        self.key_package(last_resort).tls_serialize_detached().unwrap()
+    }
+
+    /// SignWithLabel (RFC 9420 §5.1.2), come WasmMlsClient::sign_with_label
+    pub fn sign_with_label(&self, label: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut payload = VLBytes::new(label.to_vec()).tls_serialize_detached().unwrap();
+        payload.extend(VLBytes::new(content.to_vec()).tls_serialize_detached().unwrap());
+        self.signer.sign(&payload).unwrap()
+    }
+
+    pub fn create_group(&self, policy: WireFormatPolicy) -> MlsGroup {
+        let config = MlsGroupCreateConfig::builder()
+            .ciphersuite(self.ciphersuite)
+            .wire_format_policy(policy)
+            .use_ratchet_tree_extension(true)
+            .build();
+        MlsGroup::new(&self.provider, &self.signer, &config, self.credential.clone()).unwrap()
+    }
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        len @ 0..0x80 => out.push(len as u8),
+        len @ 0x80..0x100 => out.extend([0x81, len as u8]),
+        len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Certificato X.509 DER con subject CN e chiave pubblica (Ed25519 o P-256
+/// non compressa). La firma è finta: il server non valida la catena.
+pub fn x509_certificate(common_name: &str, public_key: &[u8]) -> Vec<u8> {
+    const ED25519_OID: &[u8] = &[0x2b, 0x65, 0x70];
+    const EC_PUBLIC_KEY_OID: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+    const PRIME256V1_OID: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+    let algorithm = match public_key.len() {
+        32 => der(0x30, &der(0x06, ED25519_OID)),
+        _ => der(0x30, &[der(0x06, EC_PUBLIC_KEY_OID), der(0x06, PRIME256V1_OID)].concat()),
+    };
+    let name = |cn: &str| {
+        let attribute = der(0x30, &[der(0x06, &[0x55, 0x04, 0x03]), der(0x0c, cn.as_bytes())].concat());
+        der(0x30, &der(0x31, &attribute))
+    };
+    let validity = der(0x30, &[der(0x17, b"250101000000Z"), der(0x17, b"350101000000Z")].concat());
+    let spki = der(0x30, &[algorithm.clone(), der(0x03, &[&[0], public_key].concat())].concat());
+
+    let tbs = der(0x30, &[
+        der(0xa0, &der(0x02, &[2])), // v3
+        der(0x02, &[1]),
+        algorithm.clone(),
+        name("test CA"),
+        validity,
+        name(common_name),
+        spki,
+    ].concat());
+    der(0x30, &[tbs, algorithm, der(0x03, &[0; 65])].concat())
+}
+
+/// Credential X.509 con la catena `[leaf]`: OpenMLS aggiunge la lunghezza
+/// di `certificates<V>`, qui va solo il `cert_data<V>` di ogni certificato
+pub fn x509_credential(leaf: &[u8]) -> Credential {
+    let chain = VLBytes::new(leaf.to_vec()).tls_serialize_detached().unwrap();
+    Credential::new(CredentialType::X509, chain)
+}
+
+/// Gruppo di due membri (Alice crea e aggiunge Bob senza UpdatePath) e
+/// i messaggi prodotti
+pub struct Fixture {
+    pub alice: Client,
+    pub group: MlsGroup,
+    pub add_commit: Vec<u8>,
+    pub welcome: Vec<u8>,
+}
+
+impl Fixture {
+    pub fn new(policy: WireFormatPolicy) -> Self {
+        let alice = Client::new("alice", ED25519);
+        let bob = Client::new("bob", ED25519);
+
+        let mut group = alice.create_group(policy);
+        let (commit, welcome, _) = group
+            .add_members_without_update(&alice.provider, &alice.signer, &[bob.key_package(false)])
+            .unwrap();
+        group.merge_pending_commit(&alice.provider).unwrap();
+
+        Fixture {
+            add_commit: commit.tls_serialize_detached().unwrap(),
+            welcome: welcome.tls_serialize_detached().unwrap(),
+            alice,
+            group,
+        }
+    }
+
+    /// Commit con UpdatePath che aggiunge un terzo membro
+    pub fn add_commit_with_path(&mut self, identity: &str) -> Vec<u8> {
+        let member = Client::new(identity, ED25519);
+        let (commit, _, _) = self
+            .group
+            .add_members(&self.alice.provider, &self.alice.signer, &[member.key_package(false)])
+            .unwrap();
+        self.group.merge_pending_commit(&self.alice.provider).unwrap();
+        commit.tls_serialize_detached().unwrap()
+    }
+
+    /// Commit con UpdatePath (self update)
+    pub fn update_commit(&mut self) -> Vec<u8> {
+        let bundle = self
+            .group
+            .self_update(&self.alice.provider, &self.alice.signer, LeafNodeParameters::default())
+            .unwrap();
+        self.group.merge_pending_commit(&self.alice.provider).unwrap();
+        bundle.commit().tls_serialize_detached().unwrap()
+    }
+
+    pub fn update_proposal(&mut self) -> Vec<u8> {
+        let (proposal, _) = self
+            .group
+            .propose_self_update(&self.alice.provider, &self.alice.signer, LeafNodeParameters::default())
+            .unwrap();
+        proposal.tls_serialize_detached().unwrap()
+    }
+
+    pub fn remove_proposal(&mut self) -> Vec<u8> {
+        let (proposal, _) = self
+            .group
+            .propose_remove_member(&self.alice.provider, &self.alice.signer, LeafNodeIndex::new(1))
+            .unwrap();
+        proposal.tls_serialize_detached().unwrap()
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.group.epoch().as_u64()
+    }
+}
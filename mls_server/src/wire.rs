@@ -0,0 +1,503 @@
+// ─────────────────────────────────────────────────────────────
+// WIRE – controllo del framing MLS (application/mls-message)
+// ─────────────────────────────────────────────────────────────
+//
+// Il server non decifra nulla: verifica solo che il corpo sia un
+// MLSMessage (RFC 9420 §6) ben formato e dentro i limiti di dimensione.
+// Dei PublicMessage segue la struttura fino in fondo senza verificare
+// firme né tag: servono solo i confini del messaggio.
+
+use std::fmt;
+
+use tls_codec::{Deserialize, TlsDeserialize, TlsSize, VLBytes};
+
+pub const CONTENT_TYPE: &str = "application/mls-message";
+
+// Limite sul corpo HTTP; quelli per formato sono in `WireFormat::max_len`
+pub const MAX_BODY_BYTES: u64 = 512 * 1024;
+
+const MLS_VERSION_10: u16 = 0x0001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    PublicMessage,
+    PrivateMessage,
+    Welcome,
+    GroupInfo,
+    KeyPackage,
+}
+
+impl WireFormat {
+    fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(WireFormat::PublicMessage),
+            2 => Some(WireFormat::PrivateMessage),
+            3 => Some(WireFormat::Welcome),
+            4 => Some(WireFormat::GroupInfo),
+            5 => Some(WireFormat::KeyPackage),
+            _ => None,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            WireFormat::PublicMessage => 1,
+            WireFormat::PrivateMessage => 2,
+            WireFormat::Welcome => 3,
+            WireFormat::GroupInfo => 4,
+            WireFormat::KeyPackage => 5,
+        }
+    }
+
+    pub fn max_len(self) -> usize {
+        match self {
+            WireFormat::KeyPackage => 16 * 1024,
+            WireFormat::PublicMessage | WireFormat::PrivateMessage => 128 * 1024,
+            WireFormat::Welcome | WireFormat::GroupInfo => 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    Application,
+    Proposal,
+    Commit,
+}
+
+impl ContentType {
+    fn from_u8(value: u8) -> Result<Self, WireError> {
+        match value {
+            1 => Ok(ContentType::Application),
+            2 => Ok(ContentType::Proposal),
+            3 => Ok(ContentType::Commit),
+            _ => Err(WireError::Malformed("content_type")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WireError {
+    TooLarge { len: usize, max: usize },
+    Version(u16),
+    UnknownWireFormat(u16),
+    Unexpected(WireFormat),
+    Malformed(&'static str),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::TooLarge { len, max } => write!(f, "messaggio di {len} byte (massimo {max})"),
+            WireError::Version(v) => write!(f, "versione MLS {v:#06x} non supportata"),
+            WireError::UnknownWireFormat(w) => write!(f, "wire format {w} sconosciuto"),
+            WireError::Unexpected(w) => write!(f, "wire format {w:?} non ammesso qui"),
+            WireError::Malformed(what) => write!(f, "framing non valido: {what}"),
+        }
+    }
+}
+
+/// Ciò che il server può sapere di un MLSMessage senza decifrarlo.
+#[derive(Debug)]
+pub struct MlsFrame {
+    // Epoch e tipo di contenuto: solo per PublicMessage e PrivateMessage
+    pub epoch: Option<u64>,
+    pub content_type: Option<ContentType>,
+    // Byte occupati dal messaggio (il corpo può contenerne altri dopo)
+    pub len: usize,
+}
+
+// ─────────────────────────────────────────────────────────────
+// STRUTTURE TLS (solo la parte pubblica)
+// ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct MessageHeader {
+    version: u16,
+    wire_format: u16,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct PrivateMessage {
+    group_id: VLBytes,
+    epoch: u64,
+    content_type: u8,
+    authenticated_data: VLBytes,
+    encrypted_sender_data: VLBytes,
+    ciphertext: VLBytes,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct HpkeCiphertext {
+    kem_output: VLBytes,
+    ciphertext: VLBytes,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct EncryptedGroupSecrets {
+    new_member: VLBytes,
+    encrypted_group_secrets: HpkeCiphertext,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct Welcome {
+    cipher_suite: u16,
+    secrets: Vec<EncryptedGroupSecrets>,
+    encrypted_group_info: VLBytes,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct GroupContext {
+    version: u16,
+    cipher_suite: u16,
+    group_id: VLBytes,
+    epoch: u64,
+    tree_hash: VLBytes,
+    confirmed_transcript_hash: VLBytes,
+    extensions: VLBytes,
+}
+
+#[derive(Debug, TlsDeserialize, TlsSize)]
+struct GroupInfo {
+    group_context: GroupContext,
+    extensions: VLBytes,
+    confirmation_tag: VLBytes,
+    signer: u32,
+    signature: VLBytes,
+}
+
+// Lettura "a salto" delle strutture di cui servono solo i confini
+type Reader<'a> = &'a [u8];
+
+fn skip_vec(r: &mut Reader) -> Result<(), tls_codec::Error> {
+    VLBytes::tls_deserialize(r).map(|_| ())
+}
+
+fn invalid(what: &str) -> tls_codec::Error {
+    tls_codec::Error::DecodingError(what.into())
+}
+
+fn leaf_node(r: &mut Reader) -> Result<(), tls_codec::Error> {
+    skip_vec(r)?; // encryption_key
+    skip_vec(r)?; // signature_key
+    u16::tls_deserialize(r)?; // credential_type
+    skip_vec(r)?; // identity o certificates
+    for _ in 0..5 {
+        skip_vec(r)?; // capabilities
+    }
+    match u8::tls_deserialize(r)? {
+        1 => {
+            u64::tls_deserialize(r)?; // lifetime
+            u64::tls_deserialize(r)?;
+        }
+        2 => {}
+        3 => skip_vec(r)?, // parent_hash
+        _ => return Err(invalid("leaf_node_source")),
+    }
+    skip_vec(r)?; // extensions
+    skip_vec(r) // signature
+}
+
+fn key_package(r: &mut Reader) -> Result<(), tls_codec::Error> {
+    u16::tls_deserialize(r)?; // version
+    u16::tls_deserialize(r)?; // cipher_suite
+    skip_vec(r)?; // init_key
+    leaf_node(r)?;
+    skip_vec(r)?; // extensions
+    skip_vec(r) // signature
+}
+
+fn proposal(r: &mut Reader) -> Result<(), tls_codec::Error> {
+    match u16::tls_deserialize(r)? {
+        1 => key_package(r),  // add
+        2 => leaf_node(r),    // update
+        3 => u32::tls_deserialize(r).map(|_| ()), // remove
+        4 => {
+            // psk: PreSharedKeyID
+            match u8::tls_deserialize(r)? {
+                1 => skip_vec(r)?,
+                2 => {
+                    u8::tls_deserialize(r)?;
+                    skip_vec(r)?;
+                    u64::tls_deserialize(r)?;
+                }
+                _ => return Err(invalid("psktype")),
+            }
+            skip_vec(r) // psk_nonce
+        }
+        5 => {
+            // reinit
+            skip_vec(r)?;
+            u16::tls_deserialize(r)?;
+            u16::tls_deserialize(r)?;
+            skip_vec(r)
+        }
+        6 | 7 => skip_vec(r), // external_init, group_context_extensions
+        _ => Err(invalid("proposal_type")),
+    }
+}
+
+fn commit(r: &mut Reader) -> Result<(), tls_codec::Error> {
+    let proposals = VLBytes::tls_deserialize(r)?;
+    let mut p: Reader = proposals.as_slice();
+    while !p.is_empty() {
+        match u8::tls_deserialize(&mut p)? {
+            1 => proposal(&mut p)?,
+            2 => skip_vec(&mut p)?, // reference
+            _ => return Err(invalid("ProposalOrRef")),
+        }
+    }
+    match u8::tls_deserialize(r)? {
+        0 => Ok(()),
+        1 => {
+            leaf_node(r)?;
+            skip_vec(r) // nodes
+        }
+        _ => Err(invalid("optional<UpdatePath>")),
+    }
+}
+
+// PublicMessage: FramedContent + FramedContentAuthData (+ membership_tag)
+fn public_message(r: &mut Reader) -> Result<(u64, ContentType), tls_codec::Error> {
+    skip_vec(r)?; // group_id
+    let epoch = u64::tls_deserialize(r)?;
+    let sender_type = u8::tls_deserialize(r)?;
+    match sender_type {
+        // member (leaf_index) o external (sender_index)
+        1 | 2 => {
+            u32::tls_deserialize(r)?;
+        }
+        3 | 4 => {}
+        _ => return Err(invalid("sender")),
+    }
+    skip_vec(r)?; // authenticated_data
+    let content_type = ContentType::from_u8(u8::tls_deserialize(r)?)
+        .map_err(|_| invalid("content_type"))?;
+    match content_type {
+        ContentType::Application => skip_vec(r)?,
+        ContentType::Proposal => proposal(r)?,
+        ContentType::Commit => commit(r)?,
+    }
+
+    skip_vec(r)?; // signature
+    if content_type == ContentType::Commit {
+        skip_vec(r)?; // confirmation_tag
+    }
+    if sender_type == 1 {
+        skip_vec(r)?; // membership_tag
+    }
+    Ok((epoch, content_type))
+}
+
+/// Legge il primo MLSMessage di `bytes` accettando solo i formati in `allowed`.
+pub fn parse_mls_message(bytes: &[u8], allowed: &[WireFormat]) -> Result<MlsFrame, WireError> {
+    let mut reader = bytes;
+    let header = MessageHeader::tls_deserialize(&mut reader)
+        .map_err(|_| WireError::Malformed("intestazione"))?;
+    if header.version != MLS_VERSION_10 {
+        return Err(WireError::Version(header.version));
+    }
+    let wire_format = WireFormat::from_u16(header.wire_format)
+        .ok_or(WireError::UnknownWireFormat(header.wire_format))?;
+    if !allowed.contains(&wire_format) {
+        return Err(WireError::Unexpected(wire_format));
+    }
+
+    let (epoch, content_type) = match wire_format {
+        WireFormat::PublicMessage => {
+            let (epoch, content_type) = public_message(&mut reader)
+                .map_err(|_| WireError::Malformed("PublicMessage"))?;
+            (Some(epoch), Some(content_type))
+        }
+        WireFormat::PrivateMessage => {
+            let msg = PrivateMessage::tls_deserialize(&mut reader)
+                .map_err(|_| WireError::Malformed("PrivateMessage"))?;
+            (Some(msg.epoch), Some(ContentType::from_u8(msg.content_type)?))
+        }
+        WireFormat::Welcome => {
+            Welcome::tls_deserialize(&mut reader).map_err(|_| WireError::Malformed("Welcome"))?;
+            (None, None)
+        }
+        WireFormat::GroupInfo => {
+            GroupInfo::tls_deserialize(&mut reader).map_err(|_| WireError::Malformed("GroupInfo"))?;
+            (None, None)
+        }
+        WireFormat::KeyPackage => {
+            key_package(&mut reader).map_err(|_| WireError::Malformed("KeyPackage"))?;
+            (None, None)
+        }
+    };
+
+    let len = bytes.len() - reader.len();
+    if len > wire_format.max_len() {
+        return Err(WireError::TooLarge { len, max: wire_format.max_len() });
+    }
+
+    Ok(MlsFrame {
+        epoch,
+        content_type,
+        len,
+    })
+}
+
+/// Un KeyPackage della directory (TLS nudo) incorniciato come MLSMessage.
+pub fn frame_key_package(key_package: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + key_package.len());
+    out.extend_from_slice(&MLS_VERSION_10.to_be_bytes());
+    out.extend_from_slice(&WireFormat::KeyPackage.to_u16().to_be_bytes());
+    out.extend_from_slice(key_package);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_mls::{Client, ED25519, Fixture, P256};
+    use openmls::prelude::{PURE_CIPHERTEXT_WIRE_FORMAT_POLICY, PURE_PLAINTEXT_WIRE_FORMAT_POLICY};
+
+    const GROUP_FORMATS: &[WireFormat] = &[WireFormat::PublicMessage, WireFormat::PrivateMessage];
+
+    // Il byte di optional<UpdatePath> di un Commit in PublicMessage
+    fn commit_has_path(bytes: &[u8]) -> bool {
+        let mut r: Reader = &bytes[4..];
+        skip_vec(&mut r).unwrap(); // group_id
+        u64::tls_deserialize(&mut r).unwrap();
+        assert_eq!(u8::tls_deserialize(&mut r).unwrap(), 1);
+        u32::tls_deserialize(&mut r).unwrap();
+        skip_vec(&mut r).unwrap(); // authenticated_data
+        assert_eq!(u8::tls_deserialize(&mut r).unwrap(), 3);
+        skip_vec(&mut r).unwrap(); // proposals
+        u8::tls_deserialize(&mut r).unwrap() == 1
+    }
+
+    // Il messaggio intero viene letto, ogni prefisso è rifiutato e i byte
+    // in coda restano fuori da `len`
+    fn assert_frames(bytes: &[u8], allowed: &[WireFormat]) -> MlsFrame {
+        let frame = parse_mls_message(bytes, allowed).unwrap();
+        assert_eq!(frame.len, bytes.len());
+
+        for cut in 0..bytes.len() {
+            assert!(parse_mls_message(&bytes[..cut], allowed).is_err(), "prefisso di {cut} byte accettato");
+        }
+
+        let mut padded = bytes.to_vec();
+        padded.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_mls_message(&padded, allowed).unwrap().len, bytes.len());
+
+        frame
+    }
+
+    #[test]
+    fn public_commit_without_path() {
+        let fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+        assert!(!commit_has_path(&fixture.add_commit));
+
+        let frame = assert_frames(&fixture.add_commit, GROUP_FORMATS);
+        assert_eq!(frame.epoch, Some(0));
+        assert_eq!(frame.content_type, Some(ContentType::Commit));
+    }
+
+    #[test]
+    fn public_commit_with_path() {
+        let mut fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+
+        let commits = [(1, fixture.add_commit_with_path("carol")), (2, fixture.update_commit())];
+        for (epoch, commit) in commits {
+            assert!(commit_has_path(&commit));
+            let frame = assert_frames(&commit, GROUP_FORMATS);
+            assert_eq!(frame.epoch, Some(epoch));
+            assert_eq!(frame.content_type, Some(ContentType::Commit));
+        }
+    }
+
+    #[test]
+    fn public_proposals() {
+        let mut fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+
+        for proposal in [fixture.update_proposal(), fixture.remove_proposal()] {
+            let frame = assert_frames(&proposal, GROUP_FORMATS);
+            assert_eq!(frame.epoch, Some(fixture.epoch()));
+            assert_eq!(frame.content_type, Some(ContentType::Proposal));
+        }
+    }
+
+    #[test]
+    fn private_messages() {
+        let mut fixture = Fixture::new(PURE_CIPHERTEXT_WIRE_FORMAT_POLICY);
+        let commit = fixture.update_commit();
+        let frame = assert_frames(&commit, GROUP_FORMATS);
+        assert_eq!(frame.epoch, Some(1));
+        assert_eq!(frame.content_type, Some(ContentType::Commit));
+
+        let frame = assert_frames(&fixture.update_proposal(), GROUP_FORMATS);
+        assert_eq!(frame.epoch, Some(2));
+        assert_eq!(frame.content_type, Some(ContentType::Proposal));
+    }
+
+    #[test]
+    fn welcome() {
+        let fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+        let frame = assert_frames(&fixture.welcome, &[WireFormat::Welcome]);
+        assert_eq!(frame.epoch, None);
+        assert_eq!(frame.content_type, None);
+    }
+
+    #[test]
+    fn key_package() {
+        for ciphersuite in [ED25519, P256] {
+            for last_resort in [false, true] {
+                let kp = Client::new("alice", ciphersuite).key_package_bytes(last_resort);
+                assert_frames(&frame_key_package(&kp), &[WireFormat::KeyPackage]);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_formats_not_allowed() {
+        let fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+        assert!(matches!(
+            parse_mls_message(&fixture.welcome, GROUP_FORMATS),
+            Err(WireError::Unexpected(WireFormat::Welcome))
+        ));
+        assert!(matches!(
+            parse_mls_message(&fixture.add_commit, &[WireFormat::Welcome]),
+            Err(WireError::Unexpected(WireFormat::PublicMessage))
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let fixture = Fixture::new(PURE_PLAINTEXT_WIRE_FORMAT_POLICY);
+
+        let mut bytes = fixture.add_commit.clone();
+        bytes[1] = 2;
+        assert!(matches!(parse_mls_message(&bytes, GROUP_FORMATS), Err(WireError::Version(2))));
+
+        let mut bytes = fixture.add_commit.clone();
+        bytes[3] = 9;
+        assert!(matches!(
+            parse_mls_message(&bytes, GROUP_FORMATS),
+            Err(WireError::UnknownWireFormat(9))
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_key_package() {
+        let mut kp = Client::new("alice", ED25519).key_package_bytes(false);
+        // Estensioni del KeyPackage gonfiate oltre il limite: il framing resta valido
+        let signature_len = 2 + 64; // varint a 2 byte + firma Ed25519
+        let extensions = kp.len() - signature_len - 1;
+        assert_eq!(kp[extensions], 0);
+        let filler = WireFormat::KeyPackage.max_len();
+        let mut grown = kp[..extensions].to_vec();
+        grown.extend_from_slice(&(0x8000_0000u32 | filler as u32).to_be_bytes());
+        grown.resize(grown.len() + filler, 0);
+        grown.extend_from_slice(&kp[extensions + 1..]);
+        kp = grown;
+
+        assert!(matches!(
+            parse_mls_message(&frame_key_package(&kp), &[WireFormat::KeyPackage]),
+            Err(WireError::TooLarge { .. })
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+// src/codec.rs
+//
+// Cifratura parziale per le encoded transform WebRTC: un prefisso del
+// frame dipendente dal codec resta in chiaro (e autenticato come AAD),
+// così le SFU possono ancora riconoscere i keyframe e pacchettizzare.
+// Non dipende da wasm_bindgen.
+//
+// Pacchetto risultante: [prefisso in chiaro || header || ciphertext || tag]
+//
+// Per H.264 la parte cifrata passa da `escape`/`unescape`: il ciphertext
+// potrebbe contenere start code e spezzare il frame Annex B nel packetizer.
+
+use std::borrow::Cow;
+
+// VP8 (RFC 6386 9.1): 3 byte di frame tag, più 7 byte (start code e
+// dimensioni) nei keyframe
+const VP8_DELTA_PREFIX: usize = 3;
+const VP8_KEY_PREFIX: usize = 10;
+
+// H.264 (RFC 6184 1.3): tipi NAL delle slice (non-IDR, IDR)
+const H264_NAL_SLICE: u8 = 1;
+const H264_NAL_IDR: u8 = 5;
+
+// H.264 7.4.1: dopo due zeri un byte <= 3 va preceduto da 0x03
+// (emulation prevention); 0x80 chiude i dati come rbsp_trailing_bits,
+// perché l'ultimo byte di una NAL non può essere zero.
+const H264_EMULATION_PREVENTION: u8 = 0x03;
+const H264_TRAILING_BITS: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Vp8,
+    H264,
+    Opus,
+}
+
+impl Codec {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vp8" => Some(Codec::Vp8),
+            "h264" => Some(Codec::H264),
+            "opus" => Some(Codec::Opus),
+            _ => None,
+        }
+    }
+
+    /// Byte iniziali da lasciare in chiaro. Dipende solo da byte che restano
+    /// in chiaro, quindi il receiver lo ricalcola sul pacchetto cifrato.
+    ///
+    /// `None` se il frame non contiene la struttura attesa per il codec.
+    pub fn clear_prefix_len(self, frame: &[u8]) -> Option<usize> {
+        let len = match self {
+            Codec::Vp8 => {
+                // Bit P del frame tag: 0 = keyframe
+                if frame.first()? & 0x01 == 0 {
+                    VP8_KEY_PREFIX
+                } else {
+                    VP8_DELTA_PREFIX
+                }
+            }
+            // Tutto fino all'header NAL della prima slice compreso: SPS/PPS
+            // e SEI che la precedono restano leggibili. Le slice successive
+            // finiscono nella parte cifrata (vedi `escape`).
+            Codec::H264 => h264_first_slice(frame)? + 1,
+            // TOC byte (RFC 6716 3.1)
+            Codec::Opus => 1,
+        };
+        (len <= frame.len()).then_some(len)
+    }
+
+    /// Pacchetto da consegnare al packetizer a partire da quello di
+    /// `Sender::encrypt_frame_partial`. Per H.264 header SFrame, ciphertext
+    /// e tag diventano il corpo della NAL della prima slice: nessuno start
+    /// code al loro interno e il prefisso in chiaro resta lo stesso.
+    pub fn escape(self, packet: &[u8], clear_len: usize) -> Vec<u8> {
+        match self {
+            Codec::H264 => {
+                let (clear, protected) = packet.split_at(clear_len.min(packet.len()));
+                let mut out = Vec::with_capacity(packet.len() + protected.len() / 2 + 1);
+                out.extend_from_slice(clear);
+                h264_escape(protected, &mut out);
+                out
+            }
+            Codec::Vp8 | Codec::Opus => packet.to_vec(),
+        }
+    }
+
+    /// Inverso di `escape`, da applicare prima di
+    /// `Receiver::decrypt_frame_partial`; `clear_len` va calcolato sul
+    /// pacchetto ricevuto. `None` se il corpo H.264 non è stato prodotto
+    /// da `escape`.
+    pub fn unescape(self, packet: &[u8], clear_len: usize) -> Option<Cow<'_, [u8]>> {
+        match self {
+            Codec::H264 => {
+                let (clear, escaped) = packet.split_at_checked(clear_len)?;
+                let mut out = Vec::with_capacity(packet.len());
+                out.extend_from_slice(clear);
+                h264_unescape(escaped, &mut out)?;
+                Some(Cow::Owned(out))
+            }
+            Codec::Vp8 | Codec::Opus => Some(Cow::Borrowed(packet)),
+        }
+    }
+}
+
+// Posizione dell'header NAL della prima slice in un frame Annex B.
+// Gli start code a 4 byte contengono quello a 3.
+fn h264_first_slice(frame: &[u8]) -> Option<usize> {
+    frame
+        .windows(4)
+        .position(|w| w[..3] == [0, 0, 1] && matches!(w[3] & 0x1f, H264_NAL_SLICE | H264_NAL_IDR))
+        .map(|start| start + 3)
+}
+
+fn h264_escape(data: &[u8], out: &mut Vec<u8>) {
+    let mut zeros = 0;
+    for &b in data {
+        if zeros == 2 && b <= H264_EMULATION_PREVENTION {
+            out.push(H264_EMULATION_PREVENTION);
+            zeros = 0;
+        }
+        out.push(b);
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+    }
+    out.push(H264_TRAILING_BITS);
+}
+
+fn h264_unescape(data: &[u8], out: &mut Vec<u8>) -> Option<()> {
+    let (&last, data) = data.split_last()?;
+    if last != H264_TRAILING_BITS {
+        return None;
+    }
+
+    let mut zeros = 0;
+    for &b in data {
+        if zeros == 2 && b <= H264_EMULATION_PREVENTION {
+            // Dopo due zeri 0x00..0x02 non possono comparire
+            if b != H264_EMULATION_PREVENTION {
+                return None;
+            }
+            zeros = 0;
+            continue;
+        }
+        out.push(b);
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receiver::Receiver;
+    use crate::sender::Sender;
+
+    const SECRET: &[u8] = b"segreto di test per i codec";
+
+    // Posizioni degli start code a 3 byte (quelli a 4 li contengono)
+    fn start_codes(data: &[u8]) -> Vec<usize> {
+        data.windows(3)
+            .enumerate()
+            .filter(|(_, w)| *w == [0, 0, 1])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn h264_frame(slices: &[&[u8]]) -> Vec<u8> {
+        let mut frame = vec![0, 0, 0, 1, 0x67, 0x42, 0xc0, 0x1f, 0, 0, 0, 1, 0x68, 0xce, 0x3c, 0x80];
+        for slice in slices {
+            frame.extend_from_slice(&[0, 0, 1]);
+            frame.extend_from_slice(slice);
+        }
+        frame
+    }
+
+    // Il receiver ricalcola il prefisso dal pacchetto ricevuto e ottiene
+    // di nuovo il frame originale
+    fn round_trip(codec: Codec, frame: &[u8]) -> Vec<u8> {
+        let mut sender = Sender::default();
+        sender.set_encryption_key(SECRET).unwrap();
+        let mut receiver = Receiver::default();
+        receiver.set_encryption_key(0u64, SECRET).unwrap();
+
+        let clear_len = codec.clear_prefix_len(frame).unwrap();
+        let packet = codec.escape(sender.encrypt_frame_partial(frame, clear_len).unwrap(), clear_len);
+        assert_eq!(packet[..clear_len], frame[..clear_len]);
+
+        let received_len = codec.clear_prefix_len(&packet).unwrap();
+        assert_eq!(received_len, clear_len);
+        let unescaped = codec.unescape(&packet, received_len).unwrap();
+        assert_eq!(receiver.decrypt_frame_partial(&unescaped, received_len).unwrap(), frame);
+
+        packet
+    }
+
+    #[test]
+    fn vp8_prefix() {
+        let keyframe = [0x50, 0x2a, 0x01, 0x9d, 0x01, 0x2a, 0x80, 0x02, 0xe0, 0x01, 0xaa, 0xbb, 0xcc];
+        let delta = [0x31, 0x12, 0x00, 0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(Codec::Vp8.clear_prefix_len(&keyframe), Some(VP8_KEY_PREFIX));
+        assert_eq!(Codec::Vp8.clear_prefix_len(&delta), Some(VP8_DELTA_PREFIX));
+        assert_eq!(Codec::Vp8.clear_prefix_len(&keyframe[..5]), None);
+        assert_eq!(Codec::Vp8.clear_prefix_len(&[]), None);
+
+        assert_eq!(round_trip(Codec::Vp8, &keyframe)[..VP8_KEY_PREFIX], keyframe[..VP8_KEY_PREFIX]);
+        round_trip(Codec::Vp8, &delta);
+    }
+
+    #[test]
+    fn opus_prefix() {
+        let frame = [0xfc, 0xff, 0xfe, 0x00, 0x01];
+        assert_eq!(Codec::Opus.clear_prefix_len(&frame), Some(1));
+        assert_eq!(Codec::Opus.clear_prefix_len(&[]), None);
+        round_trip(Codec::Opus, &frame);
+        round_trip(Codec::Opus, &frame[..1]);
+    }
+
+    #[test]
+    fn h264_prefix() {
+        let frame = h264_frame(&[&[0x65, 0x88, 0x84, 0x00, 0x00, 0x03, 0x01], &[0x41, 0x9a, 0x00, 0x00, 0x03, 0x00]]);
+        // SPS e PPS in chiaro, fino all'header della slice IDR compreso
+        let idr = frame.iter().position(|&b| b == 0x65).unwrap();
+        assert_eq!(Codec::H264.clear_prefix_len(&frame), Some(idr + 1));
+
+        assert_eq!(Codec::H264.clear_prefix_len(&frame[..idr]), None);
+        assert_eq!(Codec::H264.clear_prefix_len(&[0, 0, 1, 0x67, 0x42]), None);
+    }
+
+    #[test]
+    fn h264_keeps_annex_b_boundaries() {
+        // Slice con start code e zeri finali: senza emulation prevention
+        // il packetizer vedrebbe NAL inesistenti nel ciphertext
+        let frame = h264_frame(&[&[0x65, 0, 0, 1, 0, 0, 0, 0], &[0x41, 0, 0, 2, 0]]);
+        let clear_len = Codec::H264.clear_prefix_len(&frame).unwrap();
+
+        for n in 0..64u8 {
+            let mut frame = frame.clone();
+            frame.push(n);
+            let packet = round_trip(Codec::H264, &frame);
+
+            assert_eq!(start_codes(&packet), start_codes(&frame[..clear_len]));
+            assert_ne!(packet.last(), Some(&0));
+        }
+    }
+
+    #[test]
+    fn h264_escape_round_trip() {
+        let cases: [&[u8]; 7] = [
+            &[],
+            &[0],
+            &[0, 0],
+            &[0, 0, 0],
+            &[0, 0, 3],
+            &[0, 0, 1, 0, 0, 2, 0, 0, 3, 0, 0, 4],
+            &[7, 0, 0, 0, 0, 0, 0, 1],
+        ];
+        for data in cases {
+            let mut escaped = Vec::new();
+            h264_escape(data, &mut escaped);
+            assert!(escaped.windows(3).all(|w| !(w[0] == 0 && w[1] == 0 && w[2] <= 2)), "{escaped:?}");
+            assert_eq!(escaped.last(), Some(&H264_TRAILING_BITS));
+
+            let mut out = Vec::new();
+            h264_unescape(&escaped, &mut out).unwrap();
+            assert_eq!(out, data);
+        }
+    }
+
+    #[test]
+    fn h264_unescape_rejects_unescaped_data() {
+        let mut out = Vec::new();
+        assert!(h264_unescape(&[], &mut out).is_none());
+        assert!(h264_unescape(&[1, 2, 3], &mut out).is_none());
+        assert!(h264_unescape(&[0, 0, 1, H264_TRAILING_BITS], &mut out).is_none());
+        assert!(Codec::H264.unescape(&[0, 0, 1, 0x65], 5).is_none());
+    }
+}
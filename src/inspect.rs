@@ -0,0 +1,523 @@
+// src/inspect.rs
+//
+// Ispezione dei pacchetti SFrame (debug), condivisa dai binari nativi
+// (`mod inspect;`) e dagli export WASM di lib.rs. Non dipende da wasm_bindgen.
+
+use std::fmt;
+use std::fmt::Write as _;
+
+use sframe::{CipherSuite, error::SframeError, header::SframeHeader};
+
+// ------------------------------------------------------------
+// CIPHER SUITE: NOMI E PARAMETRI (RFC 9605 4.5)
+// ------------------------------------------------------------
+// Le suite AES-CTR + HMAC esistono solo con i backend openssl e
+// rust-crypto (ring non implementa AES-CTR).
+
+const SUITE_NAMES: &[(&str, CipherSuite)] = &[
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    ("aes-ctr128-hmac-sha256-80", CipherSuite::AesCtr128HmacSha256_80),
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    ("aes-ctr128-hmac-sha256-64", CipherSuite::AesCtr128HmacSha256_64),
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    ("aes-ctr128-hmac-sha256-32", CipherSuite::AesCtr128HmacSha256_32),
+    ("aes-gcm128-sha256", CipherSuite::AesGcm128Sha256),
+    ("aes-gcm256-sha512", CipherSuite::AesGcm256Sha512),
+];
+
+// Alias storici delle CLI (prima esistevano solo le due suite GCM)
+const SUITE_ALIASES: &[(&str, CipherSuite)] = &[
+    ("aesgcm128", CipherSuite::AesGcm128Sha256),
+    ("128", CipherSuite::AesGcm128Sha256),
+    ("aesgcm256", CipherSuite::AesGcm256Sha512),
+    ("256", CipherSuite::AesGcm256Sha512),
+];
+
+#[derive(Debug)]
+pub struct UnknownSuite(pub String);
+
+impl fmt::Display for UnknownSuite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cipher suite SFrame sconosciuta: {} (disponibili: {})",
+            self.0,
+            suite_names().join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownSuite {}
+
+/// Nomi brevi delle suite disponibili in questa build.
+pub fn suite_names() -> Vec<&'static str> {
+    SUITE_NAMES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Accetta il nome breve, quello del crate sframe (es. "AesGcm128Sha256")
+/// o un alias storico ("128", "aesgcm256", ...), senza distinzione tra
+/// maiuscole e minuscole.
+pub fn parse_suite_name(name: &str) -> Result<CipherSuite, UnknownSuite> {
+    SUITE_NAMES
+        .iter()
+        .find(|(short, cs)| {
+            short.eq_ignore_ascii_case(name) || cs.to_string().eq_ignore_ascii_case(name)
+        })
+        .or_else(|| SUITE_ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(name)))
+        .map(|&(_, cs)| cs)
+        .ok_or_else(|| UnknownSuite(name.to_string()))
+}
+
+/// Dimensioni in byte di chiave (AEAD.Nk), nonce (AEAD.Nn) e tag (AEAD.Nt).
+/// Nelle suite AES-CTR + HMAC la chiave comprende anche quella dell'HMAC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuiteParams {
+    pub key_len: usize,
+    pub nonce_len: usize,
+    pub tag_len: usize,
+}
+
+pub fn suite_params(suite: CipherSuite) -> SuiteParams {
+    let (key_len, tag_len) = match suite {
+        #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+        CipherSuite::AesCtr128HmacSha256_80 => (48, 10),
+        #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+        CipherSuite::AesCtr128HmacSha256_64 => (48, 8),
+        #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+        CipherSuite::AesCtr128HmacSha256_32 => (48, 4),
+        CipherSuite::AesGcm128Sha256 => (16, 16),
+        CipherSuite::AesGcm256Sha512 => (32, 16),
+    };
+    SuiteParams {
+        key_len,
+        nonce_len: 12,
+        tag_len,
+    }
+}
+
+// ------------------------------------------------------------
+// LAYOUT DELL'HEADER (RFC 9605 4.3)
+// ------------------------------------------------------------
+//   0 1 2 3 4 5 6 7
+//  +-+-+-+-+-+-+-+-+
+//  |X|  K  |Y|  C  |
+//  +-+-+-+-+-+-+-+-+
+// Con X (Y) a 0 il KID (CTR) sta nei 3 bit di K (C), altrimenti
+// K+1 (C+1) è la lunghezza in byte del campo che segue.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLayout {
+    pub config: u8,
+    /// 0 se il KID è contenuto nel config byte
+    pub kid_len: usize,
+    /// 0 se il CTR è contenuto nel config byte
+    pub ctr_len: usize,
+}
+
+impl HeaderLayout {
+    fn from_config(config: u8) -> Self {
+        let field_len = |extended: bool, value: u8| {
+            if extended { value as usize + 1 } else { 0 }
+        };
+        Self {
+            config,
+            kid_len: field_len(config & 0x80 != 0, (config >> 4) & 0x07),
+            ctr_len: field_len(config & 0x08 != 0, config & 0x07),
+        }
+    }
+
+    pub fn kid_extended(&self) -> bool {
+        self.kid_len > 0
+    }
+
+    pub fn ctr_extended(&self) -> bool {
+        self.ctr_len > 0
+    }
+}
+
+impl fmt::Display for HeaderLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = |len: usize| {
+            if len > 0 { format!("{len}B") } else { "inline".to_string() }
+        };
+        write!(
+            f,
+            "config={:08b} (kid {}, ctr {})",
+            self.config,
+            field(self.kid_len),
+            field(self.ctr_len)
+        )
+    }
+}
+
+// ------------------------------------------------------------
+// PACCHETTO ISPEZIONATO
+// ------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum InspectError {
+    Header(SframeError),
+    /// Corpo più corto del tag della suite
+    Truncated { body_len: usize, tag_len: usize },
+}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InspectError::Header(e) => write!(f, "errore header SFrame: {e}"),
+            InspectError::Truncated { body_len, tag_len } => {
+                write!(f, "pacchetto troncato: corpo di {body_len}B, tag di {tag_len}B")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InspectError {}
+
+/// Vista di un pacchetto SFrame: header decodificato e confini di
+/// ciphertext e tag secondo la cipher suite.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketInfo<'a> {
+    pub suite: CipherSuite,
+    pub params: SuiteParams,
+    pub kid: u64,
+    pub ctr: u64,
+    pub layout: HeaderLayout,
+    pub header_len: usize,
+    pub ct_len: usize,
+    packet: &'a [u8],
+}
+
+impl<'a> PacketInfo<'a> {
+    pub fn parse(packet: &'a [u8], suite: CipherSuite) -> Result<Self, InspectError> {
+        let header = SframeHeader::deserialize(packet).map_err(InspectError::Header)?;
+        let header_len = header.len();
+        let body_len = packet.len() - header_len;
+        let params = suite_params(suite);
+        if body_len < params.tag_len {
+            return Err(InspectError::Truncated {
+                body_len,
+                tag_len: params.tag_len,
+            });
+        }
+
+        Ok(Self {
+            suite,
+            params,
+            kid: header.key_id(),
+            ctr: header.counter(),
+            layout: HeaderLayout::from_config(packet[0]),
+            header_len,
+            ct_len: body_len - params.tag_len,
+            packet,
+        })
+    }
+
+    pub fn tag_len(&self) -> usize {
+        self.params.tag_len
+    }
+
+    pub fn total_len(&self) -> usize {
+        self.packet.len()
+    }
+
+    /// Header = AAD della cifratura
+    pub fn header(&self) -> &'a [u8] {
+        &self.packet[..self.header_len]
+    }
+
+    pub fn ciphertext(&self) -> &'a [u8] {
+        &self.packet[self.header_len..self.header_len + self.ct_len]
+    }
+
+    pub fn tag(&self) -> &'a [u8] {
+        &self.packet[self.header_len + self.ct_len..]
+    }
+
+    // --------------------------------------------------------
+    // RENDERER
+    // --------------------------------------------------------
+    // Display: riga compatta per i log
+    // to_json: oggetto per la webapp / strumenti esterni
+    // hex_dump: riquadro completo con header in hex e binario
+
+    pub fn to_json(&self) -> String {
+        format!(
+            concat!(
+                "{{\"suite\":\"{}\",\"key_len\":{},\"nonce_len\":{},",
+                "\"kid\":{},\"ctr\":{},\"config\":{},",
+                "\"kid_len\":{},\"ctr_len\":{},\"header_len\":{},",
+                "\"ct_len\":{},\"tag_len\":{},\"total_len\":{},\"header_hex\":\"{}\"}}"
+            ),
+            json_escape(&self.suite.to_string()),
+            self.params.key_len,
+            self.params.nonce_len,
+            self.kid,
+            self.ctr,
+            self.layout.config,
+            self.layout.kid_len,
+            self.layout.ctr_len,
+            self.header_len,
+            self.ct_len,
+            self.tag_len(),
+            self.total_len(),
+            hex::encode(self.header()),
+        )
+    }
+
+    pub fn hex_dump(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "┌─ SFrame Packet ──────────────────────────────────────────");
+        let _ = writeln!(
+            out,
+            "│ Suite          : {} (key {}B, nonce {}B, tag {}B)",
+            self.suite, self.params.key_len, self.params.nonce_len, self.params.tag_len
+        );
+        let _ = writeln!(out, "│ Header len     : {} bytes", self.header_len);
+        let _ = writeln!(out, "│ Header HEX     : {}", hex::encode(self.header()));
+        let _ = writeln!(out, "│ Header BIN     : {}", bytes_to_bin(self.header()));
+        let _ = writeln!(out, "│ Layout         : {}", self.layout);
+        let _ = writeln!(out, "│ KeyId          : {}", self.kid);
+        let _ = writeln!(out, "│ Counter        : {}", self.ctr);
+        let _ = writeln!(out, "│ Body len       : {} bytes (ciphertext + tag)", self.ct_len + self.tag_len());
+        let _ = writeln!(out, "│ Ciphertext HEX : {}", hex::encode(self.ciphertext()));
+        let _ = writeln!(out, "│ Auth Tag HEX   : {}", hex::encode(self.tag()));
+        out.push_str("└──────────────────────────────────────────────────────────");
+        out
+    }
+}
+
+impl fmt::Display for PacketInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
+            self.kid,
+            self.ctr,
+            self.header_len,
+            self.ct_len,
+            self.tag_len(),
+            self.total_len()
+        )
+    }
+}
+
+/// Escape di una stringa JSON (virgolette, backslash e caratteri di controllo).
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn bytes_to_bin(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 9);
+    for (i, b) in bytes.iter().enumerate() {
+        let _ = write!(s, "{b:08b}");
+        if i + 1 != bytes.len() {
+            s.push(' ');
+        }
+    }
+    s
+}
+
+// ------------------------------------------------------------
+// STAMPA SU STDOUT (binari nativi)
+// ------------------------------------------------------------
+
+pub fn print_compact(prefix: &str, packet: &[u8], suite: CipherSuite) {
+    match PacketInfo::parse(packet, suite) {
+        Ok(info) => println!("{prefix} {info}"),
+        Err(e) => println!("{prefix} {e}"),
+    }
+}
+
+pub fn print_dump(packet: &[u8], suite: CipherSuite) {
+    match PacketInfo::parse(packet, suite) {
+        Ok(info) => println!("{}", info.hex_dump()),
+        Err(e) => println!("[inspect] {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // KID 5 e CTR 3 nel config byte, 2 byte di ciphertext, tag GCM da 16
+    fn inline_packet() -> Vec<u8> {
+        let mut packet = vec![0x53, 0xde, 0xad];
+        packet.extend_from_slice(&[0x11; 16]);
+        packet
+    }
+
+    // KID 0x1234 e CTR 0x0102 estesi su 2 byte ciascuno
+    fn extended_packet() -> Vec<u8> {
+        let mut packet = vec![0x99, 0x12, 0x34, 0x01, 0x02, 0xbe, 0xef, 0x00];
+        packet.extend_from_slice(&[0x22; 16]);
+        packet
+    }
+
+    #[test]
+    fn parse_suite_names() {
+        for &(short, cs) in SUITE_NAMES {
+            assert_eq!(parse_suite_name(short).unwrap(), cs);
+            assert_eq!(parse_suite_name(&short.to_uppercase()).unwrap(), cs);
+            assert_eq!(parse_suite_name(&cs.to_string()).unwrap(), cs);
+            assert_eq!(parse_suite_name(&cs.to_string().to_lowercase()).unwrap(), cs);
+        }
+        assert_eq!(suite_names().len(), SUITE_NAMES.len());
+
+        // Alias storici delle CLI
+        assert_eq!(parse_suite_name("aesgcm128").unwrap(), CipherSuite::AesGcm128Sha256);
+        assert_eq!(parse_suite_name("AESGCM128").unwrap(), CipherSuite::AesGcm128Sha256);
+        assert_eq!(parse_suite_name("128").unwrap(), CipherSuite::AesGcm128Sha256);
+        assert_eq!(parse_suite_name("aesgcm256").unwrap(), CipherSuite::AesGcm256Sha512);
+        assert_eq!(parse_suite_name("256").unwrap(), CipherSuite::AesGcm256Sha512);
+
+        let err = parse_suite_name("aes-gcm512").unwrap_err();
+        assert_eq!(err.0, "aes-gcm512");
+        assert!(err.to_string().contains("aes-gcm128-sha256"));
+        assert!(parse_suite_name("").is_err());
+    }
+
+    #[test]
+    fn suite_tag_lengths() {
+        let params = |suite| {
+            let p = suite_params(suite);
+            assert_eq!(p.nonce_len, 12);
+            (p.key_len, p.tag_len)
+        };
+        assert_eq!(params(CipherSuite::AesGcm128Sha256), (16, 16));
+        assert_eq!(params(CipherSuite::AesGcm256Sha512), (32, 16));
+        #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+        {
+            assert_eq!(params(CipherSuite::AesCtr128HmacSha256_80), (48, 10));
+            assert_eq!(params(CipherSuite::AesCtr128HmacSha256_64), (48, 8));
+            assert_eq!(params(CipherSuite::AesCtr128HmacSha256_32), (48, 4));
+        }
+    }
+
+    #[test]
+    fn header_layout() {
+        let inline = HeaderLayout::from_config(0x53);
+        assert_eq!((inline.kid_len, inline.ctr_len), (0, 0));
+        assert!(!inline.kid_extended() && !inline.ctr_extended());
+        assert_eq!(inline.to_string(), "config=01010011 (kid inline, ctr inline)");
+
+        let extended = HeaderLayout::from_config(0x99);
+        assert_eq!((extended.kid_len, extended.ctr_len), (2, 2));
+        assert!(extended.kid_extended() && extended.ctr_extended());
+        assert_eq!(extended.to_string(), "config=10011001 (kid 2B, ctr 2B)");
+
+        // Lunghezze massime: K = C = 7 -> 8 byte
+        let max = HeaderLayout::from_config(0xff);
+        assert_eq!((max.kid_len, max.ctr_len), (8, 8));
+        let mixed = HeaderLayout::from_config(0x0a);
+        assert_eq!((mixed.kid_len, mixed.ctr_len), (0, 3));
+    }
+
+    #[test]
+    fn parse_splits_header_ciphertext_and_tag() {
+        let packet = extended_packet();
+        let info = PacketInfo::parse(&packet, CipherSuite::AesGcm128Sha256).unwrap();
+        assert_eq!((info.kid, info.ctr), (0x1234, 0x0102));
+        assert_eq!(info.header_len, 5);
+        assert_eq!(info.header(), &packet[..5]);
+        assert_eq!(info.ciphertext(), &[0xbe, 0xef, 0x00]);
+        assert_eq!(info.tag(), &[0x22; 16]);
+        assert_eq!(info.total_len(), packet.len());
+        assert_eq!(info.to_string(), "kid=4660 ctr=258 | aad=5B ct=3B tag=16B total=24B");
+
+        // Con un tag più corto il ciphertext si allunga
+        #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+        {
+            let info = PacketInfo::parse(&packet, CipherSuite::AesCtr128HmacSha256_32).unwrap();
+            assert_eq!((info.ct_len, info.tag_len()), (15, 4));
+        }
+    }
+
+    #[test]
+    fn json_output() {
+        let packet = inline_packet();
+        let info = PacketInfo::parse(&packet, CipherSuite::AesGcm128Sha256).unwrap();
+        assert_eq!(
+            info.to_json(),
+            concat!(
+                "{\"suite\":\"AesGcm128Sha256\",\"key_len\":16,\"nonce_len\":12,",
+                "\"kid\":5,\"ctr\":3,\"config\":83,",
+                "\"kid_len\":0,\"ctr_len\":0,\"header_len\":1,",
+                "\"ct_len\":2,\"tag_len\":16,\"total_len\":19,\"header_hex\":\"53\"}"
+            )
+        );
+
+        assert_eq!(json_escape("AesGcm128Sha256"), "AesGcm128Sha256");
+        assert_eq!(json_escape("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(json_escape("x\ny\tz\r"), "x\\ny\\tz\\r");
+        assert_eq!(json_escape("\u{1}"), "\\u0001");
+        assert_eq!(json_escape("perché"), "perché");
+    }
+
+    #[test]
+    fn hex_dump_lines() {
+        let packet = extended_packet();
+        let info = PacketInfo::parse(&packet, CipherSuite::AesGcm128Sha256).unwrap();
+        let dump = info.hex_dump();
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert_eq!(lines.len(), 12);
+        assert!(lines[0].starts_with("┌─ SFrame Packet"));
+        assert_eq!(lines[1], "│ Suite          : AesGcm128Sha256 (key 16B, nonce 12B, tag 16B)");
+        assert_eq!(lines[2], "│ Header len     : 5 bytes");
+        assert_eq!(lines[3], "│ Header HEX     : 9912340102");
+        assert_eq!(
+            lines[4],
+            "│ Header BIN     : 10011001 00010010 00110100 00000001 00000010"
+        );
+        assert_eq!(lines[5], "│ Layout         : config=10011001 (kid 2B, ctr 2B)");
+        assert_eq!(lines[6], "│ KeyId          : 4660");
+        assert_eq!(lines[7], "│ Counter        : 258");
+        assert_eq!(lines[8], "│ Body len       : 19 bytes (ciphertext + tag)");
+        assert_eq!(lines[9], "│ Ciphertext HEX : beef00");
+        assert_eq!(lines[10], format!("│ Auth Tag HEX   : {}", "22".repeat(16)));
+        assert!(lines[11].starts_with('└'));
+
+        assert_eq!(bytes_to_bin(&[]), "");
+        assert_eq!(bytes_to_bin(&[0x01, 0x80]), "00000001 10000000");
+    }
+
+    #[test]
+    fn short_packets_rejected() {
+        let suite = CipherSuite::AesGcm128Sha256;
+
+        // Pacchetto vuoto o header esteso tagliato
+        assert!(matches!(PacketInfo::parse(&[], suite), Err(InspectError::Header(_))));
+        let packet = extended_packet();
+        for cut in 1..5 {
+            assert!(
+                matches!(PacketInfo::parse(&packet[..cut], suite), Err(InspectError::Header(_))),
+                "header di {cut} byte accettato"
+            );
+        }
+
+        // Corpo più corto del tag
+        let packet = inline_packet();
+        let err = PacketInfo::parse(&packet[..16], suite).unwrap_err();
+        assert!(matches!(err, InspectError::Truncated { body_len: 15, tag_len: 16 }));
+        assert_eq!(err.to_string(), "pacchetto troncato: corpo di 15B, tag di 16B");
+
+        // Solo tag: ciphertext vuoto ma pacchetto valido
+        let info = PacketInfo::parse(&packet[..17], suite).unwrap();
+        assert_eq!((info.ct_len, info.ciphertext().len()), (0, 0));
+    }
+}
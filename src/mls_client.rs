@@ -1,111 +1,937 @@
 // src/mls_client.rs
 #![cfg(target_arch = "wasm32")]
 
+use std::collections::HashMap;
+
 use wasm_bindgen::prelude::*;
 use openmls::prelude::*;
 use openmls_rust_crypto::OpenMlsRustCrypto;
 use openmls_basic_credential::SignatureKeyPair;
-use tls_codec::{Deserialize, Serialize};
+use tls_codec::{Deserialize, Serialize, TlsSerialize, TlsSize, VLBytes};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 
 use openmls_traits::OpenMlsProvider;
+use openmls_traits::signatures::Signer;
 use openmls_traits::storage::StorageProvider;
 
+use sframe::mls::{MlsExporter, MlsKeyId, MlsKeyIdBitRange};
+
+use crate::mls_error::MlsClientError;
+use crate::mls_state::{open_state, seal_state};
+use crate::session::WasmSession;
+use crate::track::{TrackKind, WasmReceiver, WasmSender};
+use crate::{new_receiver, new_sender, parse_suite};
+
+// KID SFrame su MLS (RFC 9605 5.2): [context || epoch || leaf index]
+// Con 8 bit di indice il gruppo SFrame ha al massimo 256 foglie: oltre,
+// i leaf index si sovrapporrebbero e due membri avrebbero lo stesso KID.
+const SFRAME_EPOCH_BITS: u8 = 8;
+const SFRAME_INDEX_BITS: u8 = 8;
+
+fn sframe_key_id(context_id: u64, epoch: u64, leaf_index: u32) -> Result<MlsKeyId, MlsClientError> {
+    if leaf_index >= 1 << SFRAME_INDEX_BITS {
+        return Err(MlsClientError::GroupTooLarge(leaf_index));
+    }
+    let bit_range = MlsKeyIdBitRange::new(SFRAME_EPOCH_BITS, SFRAME_INDEX_BITS);
+    Ok(MlsKeyId::new(context_id, epoch, leaf_index as u64, bit_range))
+}
+
+// Richieste autenticate sul Delivery Service: SignWithLabel (RFC 9420 5.1.2)
+// con la chiave di firma dei KeyPackage. Deve coincidere con mls_server/src/auth.rs.
+const JOIN_LABEL: &[u8] = b"MLS 1.0 MLS-DS join";
+const UPLOAD_LABEL: &[u8] = b"MLS 1.0 MLS-DS keypackages";
+const LEAVE_LABEL: &[u8] = b"MLS 1.0 MLS-DS leave";
+const CLAIM_LABEL: &[u8] = b"MLS 1.0 MLS-DS claim";
+const COMMIT_LABEL: &[u8] = b"MLS 1.0 MLS-DS commit";
+const PROPOSAL_LABEL: &[u8] = b"MLS 1.0 MLS-DS proposal";
+const ACK_LABEL: &[u8] = b"MLS 1.0 MLS-DS ack";
+
+// Firmata per join, leave e ack (con etichette diverse); nell'ack
+// `timestamp` è l'ultimo seq letto dalla coda
+#[derive(Debug, TlsSerialize, TlsSize)]
+struct JoinTbs {
+    room_id: VLBytes,
+    identity: VLBytes,
+    timestamp: u64,
+}
+
+#[derive(Debug, TlsSerialize, TlsSize)]
+struct ClaimTbs {
+    room_id: VLBytes,
+    claimer: VLBytes,
+    identity: VLBytes,
+    timestamp: u64,
+}
+
+// Firmata per Commit e Proposal inviati al Delivery Service
+#[derive(Debug, TlsSerialize, TlsSize)]
+struct HandshakeTbs {
+    room_id: VLBytes,
+    sender: VLBytes,
+    epoch: u64,
+    message: VLBytes,
+}
+
+#[derive(Debug, TlsSerialize, TlsSize)]
+struct UploadTbs {
+    identity: VLBytes,
+    timestamp: u64,
+}
+
+#[derive(Debug, TlsSerialize, TlsSize)]
+struct SignContent {
+    label: VLBytes,
+    content: VLBytes,
+}
+
+fn local_storage() -> Result<web_sys::Storage, MlsClientError> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| MlsClientError::Storage("localStorage non disponibile".into()))
+}
+
+// I gruppi sono indicizzati dal GroupId MLS, che JS passa come Uint8Array.
+type Groups = HashMap<GroupId, MlsGroup>;
+
+fn find_group<'a>(groups: &'a Groups, group_id: &[u8]) -> Result<&'a MlsGroup, MlsClientError> {
+    groups
+        .get(&GroupId::from_slice(group_id))
+        .ok_or(MlsClientError::NoGroup)
+}
+
+// Gruppo su cui possiamo ancora creare Commit/Proposal.
+fn active_group<'a>(groups: &'a mut Groups, group_id: &[u8]) -> Result<&'a mut MlsGroup, MlsClientError> {
+    let group = groups
+        .get_mut(&GroupId::from_slice(group_id))
+        .ok_or(MlsClientError::NoGroup)?;
+    if !group.is_active() {
+        return Err(MlsClientError::GroupInactive);
+    }
+    Ok(group)
+}
+
+fn commit_output(
+    group: &MlsGroup,
+    commit: &MlsMessageOut,
+    welcome: Option<&MlsMessageOut>,
+) -> Result<MlsCommitOutput, MlsClientError> {
+    let commit = commit
+        .tls_serialize_detached()
+        .map_err(|_| MlsClientError::Serialization)?;
+    let welcome = welcome
+        .map(|w| w.tls_serialize_detached())
+        .transpose()
+        .map_err(|_| MlsClientError::Serialization)?;
+
+    Ok(MlsCommitOutput {
+        commit,
+        welcome,
+        epoch: group.epoch().as_u64(),
+    })
+}
+
+// Exporter MLS → SFrame (base key "SFrame 1.0 Base Key")
+struct GroupExporter<'a> {
+    group: &'a MlsGroup,
+    provider: &'a OpenMlsRustCrypto,
+}
+
+impl MlsExporter for GroupExporter<'_> {
+    type BaseKey = Vec<u8>;
+    type Error = ExportSecretError;
+
+    fn export_secret(
+        &self,
+        label: &str,
+        context: &[u8],
+        key_length: usize,
+    ) -> Result<Self::BaseKey, Self::Error> {
+        self.group
+            .export_secret(self.provider.crypto(), label, context, key_length)
+    }
+}
+
+// Risultato di un'operazione che produce un Commit MLS (add/remove/update).
+// Il Commit va distribuito ai membri esistenti, il Welcome ai nuovi.
+#[wasm_bindgen]
+pub struct MlsCommitOutput {
+    commit: Vec<u8>,
+    welcome: Option<Vec<u8>>,
+    epoch: u64,
+}
+
+#[wasm_bindgen]
+impl MlsCommitOutput {
+    #[wasm_bindgen(getter)]
+    pub fn commit(&self) -> Vec<u8> {
+        self.commit.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn welcome(&self) -> Option<Vec<u8>> {
+        self.welcome.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+}
+
+// ------------------------------------------------------------
+// CIPHERSUITE E CREDENZIALI MLS
+// ------------------------------------------------------------
+// Suite supportate da openmls_rust_crypto. Sono accettati sia i nomi brevi
+// sia quelli RFC 9420 (es. "MLS_128_DHKEMP256_AES128GCM_SHA256_P256").
+
+const MLS_CIPHERSUITES: &[(&str, Ciphersuite)] = &[
+    (
+        "x25519-aes128gcm-ed25519",
+        Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519,
+    ),
+    (
+        "p256-aes128gcm-p256",
+        Ciphersuite::MLS_128_DHKEMP256_AES128GCM_SHA256_P256,
+    ),
+    (
+        "x25519-chacha20poly1305-ed25519",
+        Ciphersuite::MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519,
+    ),
+];
+
+fn parse_mls_ciphersuite(name: Option<String>) -> Result<Ciphersuite, MlsClientError> {
+    let Some(name) = name else {
+        return Ok(Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519);
+    };
+
+    MLS_CIPHERSUITES
+        .iter()
+        .find(|(short, cs)| *short == name || format!("{:?}", cs) == name)
+        .map(|&(_, cs)| cs)
+        .ok_or(MlsClientError::UnsupportedCiphersuite(name))
+}
+
+// Lettura DER minimale: (tag, contenuto, resto) del primo TLV di `input`
+fn der_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+// id-at-commonName (2.5.4.3)
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+// CN del subject e chiave pubblica raw (BIT STRING della
+// SubjectPublicKeyInfo) di un certificato X.509 DER (RFC 5280 4.1).
+// Deve coincidere con x509_leaf in mls_server/src/auth.rs.
+fn x509_leaf(cert: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (0x30, cert, _) = der_tlv(cert)? else { return None };
+    let (0x30, mut tbs, _) = der_tlv(cert)? else { return None };
+
+    // version [0] opzionale, poi serial, signature, issuer, validity
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_tlv(tbs)?.2;
+    }
+    for _ in 0..4 {
+        tbs = der_tlv(tbs)?.2;
+    }
+    let (0x30, mut subject, tbs) = der_tlv(tbs)? else { return None };
+
+    let (0x30, spki, _) = der_tlv(tbs)? else { return None };
+    let (0x30, _, spki) = der_tlv(spki)? else { return None }; // algorithm
+    let (0x03, bits, _) = der_tlv(spki)? else { return None };
+    // Primo byte: bit inutilizzati, sempre 0 per le chiavi supportate
+    let (0, public_key) = bits.split_first()? else { return None };
+
+    // Name: SEQUENCE OF RelativeDistinguishedName (SET OF AttributeTypeAndValue)
+    while !subject.is_empty() {
+        let (0x31, mut rdn, rest) = der_tlv(subject)? else { return None };
+        subject = rest;
+        while !rdn.is_empty() {
+            let (0x30, attribute, rest) = der_tlv(rdn)? else { return None };
+            rdn = rest;
+            let (0x06, oid, value) = der_tlv(attribute)? else { return None };
+            if oid == OID_COMMON_NAME {
+                return Some((der_tlv(value)?.1, public_key));
+            }
+        }
+    }
+    None
+}
+
+// La chiave privata deve corrispondere a quella pubblica dichiarata
+fn check_keypair(keypair: &SignatureKeyPair) -> Result<(), MlsClientError> {
+    const PROBE: &[u8] = b"sframe_core keypair check";
+    let invalid = || MlsClientError::InvalidCredential("chiave privata e pubblica non corrispondono".into());
+
+    let signature = keypair.sign(PROBE).map_err(|_| invalid())?;
+    OpenMlsRustCrypto::default()
+        .crypto()
+        .verify_signature(keypair.signature_scheme(), PROBE, keypair.public(), &signature)
+        .map_err(|_| invalid())
+}
+
+// Tutti i membri dichiarano Basic e X.509, così gruppi misti restano validi.
+fn client_capabilities() -> Capabilities {
+    Capabilities::builder()
+        .credentials(vec![CredentialType::Basic, CredentialType::X509])
+        .build()
+}
+
+/// Nomi brevi delle ciphersuite MLS accettate da `WasmMlsClient`.
+#[wasm_bindgen]
+pub fn mls_supported_ciphersuites() -> Vec<String> {
+    MLS_CIPHERSUITES.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+// Un client può essere membro di più gruppi (una stanza ciascuno): identità,
+// chiave di firma e KeyPackage nello storage del provider sono condivisi.
 #[wasm_bindgen]
 pub struct WasmMlsClient {
     identity: String,
+    ciphersuite: Ciphersuite,
+    credential: Credential,
     provider: OpenMlsRustCrypto,
     signature_keypair: SignatureKeyPair,
-    group: Option<MlsGroup>,
+    groups: Groups,
 }
 
 #[wasm_bindgen]
 impl WasmMlsClient {
+    /// Client con BasicCredential (identità auto-dichiarata).
+    /// `ciphersuite` è un nome di `mls_supported_ciphersuites()`; default X25519/AES-128-GCM/Ed25519.
     #[wasm_bindgen(constructor)]
-    pub fn new(identity: &str) -> Result<WasmMlsClient, JsValue> {
-        let provider = OpenMlsRustCrypto::default();
-        let ciphersuite = Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
+    pub fn new(identity: &str, ciphersuite: Option<String>) -> Result<WasmMlsClient, MlsClientError> {
+        let ciphersuite = parse_mls_ciphersuite(ciphersuite)?;
         let signature_keypair = SignatureKeyPair::new(ciphersuite.signature_algorithm())
-            .map_err(|_| JsValue::from_str("Errore chiavi"))?;
-        
+            .map_err(|e| MlsClientError::Crypto(format!("{:?}", e)))?;
+        let credential = BasicCredential::new(identity.as_bytes().to_vec()).into();
+
         Ok(Self {
             identity: identity.to_string(),
-            provider,
+            ciphersuite,
+            credential,
+            provider: OpenMlsRustCrypto::default(),
             signature_keypair,
-            group: None,
+            groups: Groups::new(),
         })
     }
 
+    /// Client con credenziale X.509: `cert_chain` è un array di certificati DER
+    /// (Uint8Array), leaf per primo. La coppia di chiavi deve essere quella del
+    /// certificato leaf, nel formato raw dello schema di firma della suite, e
+    /// `identity` il CN del suo subject: è l'identità che il Delivery Service
+    /// legge dal KeyPackage. La validazione della catena verso la CA spetta
+    /// all'Authentication Service.
     #[wasm_bindgen]
-    pub fn generate_key_package(&mut self) -> Result<Vec<u8>, JsValue> {
-        let ciphersuite = Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
-        let credential = BasicCredential::new(self.identity.as_bytes().to_vec());
-        let credential_with_key = CredentialWithKey {
-            credential: credential.into(),
-            signature_key: self.signature_keypair.public().into(),
+    pub fn new_x509(
+        identity: &str,
+        cert_chain: js_sys::Array,
+        private_key: Vec<u8>,
+        public_key: Vec<u8>,
+        ciphersuite: Option<String>,
+    ) -> Result<WasmMlsClient, MlsClientError> {
+        let ciphersuite = parse_mls_ciphersuite(ciphersuite)?;
+
+        if cert_chain.length() == 0 {
+            return Err(MlsClientError::InvalidCredential("catena X.509 vuota".into()));
+        }
+        // certificates<V>: la lunghezza esterna la aggiunge Credential
+        let mut chain = Vec::new();
+        for cert in cert_chain.iter() {
+            let cert = VLBytes::from(js_sys::Uint8Array::new(&cert).to_vec());
+            chain.extend(cert.tls_serialize_detached().map_err(|_| MlsClientError::Serialization)?);
+        }
+
+        let leaf = js_sys::Uint8Array::new(&cert_chain.get(0)).to_vec();
+        let (common_name, leaf_key) = x509_leaf(&leaf)
+            .ok_or_else(|| MlsClientError::InvalidCredential("certificato leaf non leggibile".into()))?;
+        if common_name != identity.as_bytes() {
+            return Err(MlsClientError::InvalidCredential("identità diversa dal CN del certificato leaf".into()));
+        }
+        if leaf_key != public_key.as_slice() {
+            return Err(MlsClientError::InvalidCredential(
+                "chiave pubblica diversa da quella del certificato leaf".into(),
+            ));
+        }
+
+        let signature_keypair =
+            SignatureKeyPair::from_raw(ciphersuite.signature_algorithm(), private_key, public_key);
+        check_keypair(&signature_keypair)?;
+
+        Ok(Self {
+            identity: identity.to_string(),
+            ciphersuite,
+            credential: Credential::new(CredentialType::X509, chain),
+            provider: OpenMlsRustCrypto::default(),
+            signature_keypair,
+            groups: Groups::new(),
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ciphersuite(&self) -> String {
+        MLS_CIPHERSUITES
+            .iter()
+            .find(|(_, cs)| *cs == self.ciphersuite)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| format!("{:?}", self.ciphersuite))
+    }
+
+    #[wasm_bindgen]
+    pub fn generate_key_package(&mut self) -> Result<Vec<u8>, MlsClientError> {
+        self.build_key_package(false)
+    }
+
+    /// KeyPackage riutilizzabile (estensione last_resort) da pubblicare
+    /// nella directory del Delivery Service accanto a quelli monouso.
+    #[wasm_bindgen]
+    pub fn generate_last_resort_key_package(&mut self) -> Result<Vec<u8>, MlsClientError> {
+        self.build_key_package(true)
+    }
+
+    /// Firma (room_id, identity, timestamp) per `/mls/join` con la chiave
+    /// dei nostri KeyPackage. `timestamp` in secondi Unix.
+    #[wasm_bindgen]
+    pub fn sign_join(&self, room_id: &str, timestamp: u64) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = JoinTbs {
+            room_id: room_id.as_bytes().to_vec().into(),
+            identity: self.identity.as_bytes().to_vec().into(),
+            timestamp,
         };
+        self.sign_with_label(JOIN_LABEL, &tbs)
+    }
 
-        let kp_bundle = KeyPackage::builder()
-            .build(ciphersuite, &self.provider, &self.signature_keypair, credential_with_key)
-            .map_err(|_| JsValue::from_str("Errore builder KP"))?;
+    /// Firma (room_id, identity, timestamp) per `/mls/leave`: solo chi ha
+    /// la chiave del KeyPackage usato nel join può lasciare la stanza.
+    #[wasm_bindgen]
+    pub fn sign_leave(&self, room_id: &str, timestamp: u64) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = JoinTbs {
+            room_id: room_id.as_bytes().to_vec().into(),
+            identity: self.identity.as_bytes().to_vec().into(),
+            timestamp,
+        };
+        self.sign_with_label(LEAVE_LABEL, &tbs)
+    }
 
-        let kp = kp_bundle.key_package();
-        let kp_ref = kp.hash_ref(self.provider.crypto()).unwrap();
-        
-        self.provider.storage().write_key_package(&kp_ref, &kp_bundle).unwrap();
+    /// Firma il claim di un KeyPackage di `identity` dalla directory
+    /// (`/mls/keypackages/claim`): il server lo concede solo ai membri
+    /// attivi di `room_id`, al più uno al secondo.
+    #[wasm_bindgen]
+    pub fn sign_claim(&self, room_id: &str, identity: &str, timestamp: u64) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = ClaimTbs {
+            room_id: room_id.as_bytes().to_vec().into(),
+            claimer: self.identity.as_bytes().to_vec().into(),
+            identity: identity.as_bytes().to_vec().into(),
+            timestamp,
+        };
+        self.sign_with_label(CLAIM_LABEL, &tbs)
+    }
 
-        web_sys::console::log_1(&"[RUST-WASM] KeyPackage generato e salvato nel Provider!".into());
-        kp.tls_serialize_detached().map_err(|_| JsValue::from_str("Errore ser"))
+    /// Firma un Commit per `/mls/commit`: `epoch` è quella in cui il Commit
+    /// è stato creato, `message` l'MLSMessage serializzato.
+    #[wasm_bindgen]
+    pub fn sign_commit(&self, room_id: &str, epoch: u64, message: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        self.sign_handshake(COMMIT_LABEL, room_id, epoch, message)
+    }
+
+    /// Come `sign_commit`, per `/mls/proposal`.
+    #[wasm_bindgen]
+    pub fn sign_proposal(&self, room_id: &str, epoch: u64, message: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        self.sign_handshake(PROPOSAL_LABEL, room_id, epoch, message)
+    }
+
+    /// Firma la conferma di lettura della nostra coda fino al seq `after`
+    /// (`/mls/messages/ack`).
+    #[wasm_bindgen]
+    pub fn sign_ack(&self, room_id: &str, after: u64) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = JoinTbs {
+            room_id: room_id.as_bytes().to_vec().into(),
+            identity: self.identity.as_bytes().to_vec().into(),
+            timestamp: after,
+        };
+        self.sign_with_label(ACK_LABEL, &tbs)
     }
 
+    /// Firma (identity, timestamp) per `POST /mls/keypackages`.
     #[wasm_bindgen]
-    pub fn process_welcome(&mut self, welcome_bytes: &[u8]) -> Result<(), JsValue> {
-        let welcome = Welcome::tls_deserialize(&mut &welcome_bytes[..])
-            .map_err(|_| JsValue::from_str("Welcome corrotto"))?;
+    pub fn sign_key_package_upload(&self, timestamp: u64) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = UploadTbs {
+            identity: self.identity.as_bytes().to_vec().into(),
+            timestamp,
+        };
+        self.sign_with_label(UPLOAD_LABEL, &tbs)
+    }
+
+    // --------------------------------------------------------
+    // GRUPPI
+    // --------------------------------------------------------
+    // Tutte le operazioni di gruppo prendono il GroupId restituito da
+    // `create_group` o `process_welcome`.
+
+    /// Entra nel gruppo del Welcome e ne restituisce il GroupId.
+    #[wasm_bindgen]
+    pub fn process_welcome(&mut self, welcome_bytes: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        // add_member produce un MlsMessageOut: il Welcome è nel body
+        let message = MlsMessageIn::tls_deserialize(&mut &welcome_bytes[..])
+            .map_err(|e| MlsClientError::InvalidWelcome(format!("{:?}", e)))?;
+        let MlsMessageBodyIn::Welcome(welcome) = message.extract() else {
+            return Err(MlsClientError::InvalidWelcome("il messaggio non è un Welcome".into()));
+        };
 
         let join_config = MlsGroupJoinConfig::builder().use_ratchet_tree_extension(true).build();
 
         web_sys::console::log_1(&"[RUST-WASM] Tento di decifrare il Welcome...".into());
 
         let staged_welcome = StagedWelcome::new_from_welcome(&self.provider, &join_config, welcome, None)
-            .map_err(|e| JsValue::from_str(&format!("Errore StagedWelcome: {:?}", e)))?;
+            .map_err(|e| MlsClientError::InvalidWelcome(format!("StagedWelcome: {:?}", e)))?;
 
         let group = staged_welcome.into_group(&self.provider)
-            .map_err(|e| JsValue::from_str(&format!("Errore IntoGroup: {:?}", e)))?;
+            .map_err(|e| MlsClientError::InvalidWelcome(format!("IntoGroup: {:?}", e)))?;
 
-        self.group = Some(group);
+        let group_id = group.group_id().clone();
+        self.groups.insert(group_id.clone(), group);
         web_sys::console::log_1(&"[RUST-WASM] Welcome APERTO CON SUCCESSO!".into());
-        Ok(())
+        Ok(group_id.to_vec())
     }
 
+    /// Crea un gruppo di cui siamo l'unico membro e ne restituisce il GroupId.
+    /// Senza `group_id` ne viene generato uno casuale.
     #[wasm_bindgen]
-    pub fn create_group(&mut self) -> Result<(), JsValue> {
-        let config = MlsGroupCreateConfig::builder().use_ratchet_tree_extension(true).build();
-        let credential = BasicCredential::new(self.identity.as_bytes().to_vec());
-        let credential_with_key = CredentialWithKey {
-            credential: credential.into(),
-            signature_key: self.signature_keypair.public().into(),
+    pub fn create_group(&mut self, group_id: Option<Vec<u8>>) -> Result<Vec<u8>, MlsClientError> {
+        let group_id = match group_id {
+            Some(id) => GroupId::from_slice(&id),
+            None => GroupId::random(self.provider.rand()),
+        };
+        if self.groups.contains_key(&group_id) {
+            return Err(MlsClientError::GroupOperation("gruppo già presente".into()));
+        }
+
+        let config = MlsGroupCreateConfig::builder()
+            .ciphersuite(self.ciphersuite)
+            .capabilities(client_capabilities())
+            .use_ratchet_tree_extension(true)
+            .build();
+
+        let group = MlsGroup::new_with_group_id(
+            &self.provider,
+            &self.signature_keypair,
+            &config,
+            group_id.clone(),
+            self.credential_with_key(),
+        )
+        .map_err(|e| MlsClientError::GroupOperation(format!("NewGroup: {:?}", e)))?;
+        self.groups.insert(group_id.clone(), group);
+        Ok(group_id.to_vec())
+    }
+
+    /// GroupId (Uint8Array) di tutti i gruppi di questo client.
+    #[wasm_bindgen]
+    pub fn group_ids(&self) -> js_sys::Array {
+        self.groups
+            .keys()
+            .map(|id| JsValue::from(js_sys::Uint8Array::from(id.as_slice())))
+            .collect()
+    }
+
+    /// Esce localmente dal gruppo e ne cancella lo stato dallo storage.
+    /// Non produce messaggi: la rimozione dal gruppo spetta a un Commit.
+    #[wasm_bindgen]
+    pub fn forget_group(&mut self, group_id: &[u8]) -> Result<(), MlsClientError> {
+        let mut group = self
+            .groups
+            .remove(&GroupId::from_slice(group_id))
+            .ok_or(MlsClientError::NoGroup)?;
+        group
+            .delete(self.provider.storage())
+            .map_err(|e| MlsClientError::Storage(format!("DeleteGroup: {:?}", e)))
+    }
+
+    #[wasm_bindgen]
+    pub fn add_member(&mut self, group_id: &[u8], kp_bytes: &[u8]) -> Result<MlsCommitOutput, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+        let kp = KeyPackageIn::tls_deserialize(&mut &kp_bytes[..])
+            .map_err(|e| MlsClientError::InvalidKeyPackage(format!("{:?}", e)))?
+            .validate(self.provider.crypto(), ProtocolVersion::Mls10)
+            .map_err(|e| MlsClientError::InvalidKeyPackage(format!("{:?}", e)))?;
+
+        let (commit, welcome, _) = group
+            .add_members(&self.provider, &self.signature_keypair, &[kp])
+            .map_err(|e| MlsClientError::GroupOperation(format!("AddMembers: {:?}", e)))?;
+        group
+            .merge_pending_commit(&self.provider)
+            .map_err(|e| MlsClientError::GroupOperation(format!("MergeCommit: {:?}", e)))?;
+        commit_output(group, &commit, Some(&welcome))
+    }
+
+    // --------------------------------------------------------
+    // COMMIT / PROPOSAL (avanzamento epoch)
+    // --------------------------------------------------------
+    // Ogni Commit creato qui viene applicato subito (merge_pending_commit):
+    // il Delivery Service deve solo inoltrarlo agli altri membri.
+
+    #[wasm_bindgen]
+    pub fn remove_member(&mut self, group_id: &[u8], leaf_index: u32) -> Result<MlsCommitOutput, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+
+        let (commit, welcome, _) = group
+            .remove_members(&self.provider, &self.signature_keypair, &[LeafNodeIndex::new(leaf_index)])
+            .map_err(|e| MlsClientError::GroupOperation(format!("RemoveMembers: {:?}", e)))?;
+        group
+            .merge_pending_commit(&self.provider)
+            .map_err(|e| MlsClientError::GroupOperation(format!("MergeCommit: {:?}", e)))?;
+
+        commit_output(group, &commit, welcome.as_ref())
+    }
+
+    #[wasm_bindgen]
+    pub fn self_update(&mut self, group_id: &[u8]) -> Result<MlsCommitOutput, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+
+        let bundle = group
+            .self_update(&self.provider, &self.signature_keypair, LeafNodeParameters::default())
+            .map_err(|e| MlsClientError::GroupOperation(format!("SelfUpdate: {:?}", e)))?;
+        group
+            .merge_pending_commit(&self.provider)
+            .map_err(|e| MlsClientError::GroupOperation(format!("MergeCommit: {:?}", e)))?;
+
+        commit_output(group, bundle.commit(), bundle.to_welcome_msg().as_ref())
+    }
+
+    #[wasm_bindgen]
+    pub fn propose_remove_member(&mut self, group_id: &[u8], leaf_index: u32) -> Result<Vec<u8>, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+
+        let (proposal, _) = group
+            .propose_remove_member(&self.provider, &self.signature_keypair, LeafNodeIndex::new(leaf_index))
+            .map_err(|e| MlsClientError::GroupOperation(format!("ProposeRemove: {:?}", e)))?;
+
+        proposal.tls_serialize_detached().map_err(|_| MlsClientError::Serialization)
+    }
+
+    #[wasm_bindgen]
+    pub fn propose_self_update(&mut self, group_id: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+
+        let (proposal, _) = group
+            .propose_self_update(&self.provider, &self.signature_keypair, LeafNodeParameters::default())
+            .map_err(|e| MlsClientError::GroupOperation(format!("ProposeUpdate: {:?}", e)))?;
+
+        proposal.tls_serialize_detached().map_err(|_| MlsClientError::Serialization)
+    }
+
+    /// Crea un Commit con tutte le Proposal ricevute/create finora.
+    #[wasm_bindgen]
+    pub fn commit_pending_proposals(&mut self, group_id: &[u8]) -> Result<MlsCommitOutput, MlsClientError> {
+        let group = active_group(&mut self.groups, group_id)?;
+
+        let (commit, welcome, _) = group
+            .commit_to_pending_proposals(&self.provider, &self.signature_keypair)
+            .map_err(|e| MlsClientError::GroupOperation(format!("CommitProposals: {:?}", e)))?;
+        group
+            .merge_pending_commit(&self.provider)
+            .map_err(|e| MlsClientError::GroupOperation(format!("MergeCommit: {:?}", e)))?;
+
+        commit_output(group, &commit, welcome.as_ref())
+    }
+
+    /// Elabora un Commit o una Proposal ricevuti da un altro membro.
+    /// Le Proposal vengono accodate, i Commit applicati.
+    /// Restituisce l'epoch corrente dopo l'elaborazione.
+    #[wasm_bindgen]
+    pub fn process_message(&mut self, group_id: &[u8], message_bytes: &[u8]) -> Result<u64, MlsClientError> {
+        let group = self
+            .groups
+            .get_mut(&GroupId::from_slice(group_id))
+            .ok_or(MlsClientError::NoGroup)?;
+
+        let message = MlsMessageIn::tls_deserialize(&mut &message_bytes[..])
+            .map_err(|e| MlsClientError::InvalidMessage(format!("{:?}", e)))?;
+        let protocol_message = message
+            .try_into_protocol_message()
+            .map_err(|e| MlsClientError::InvalidMessage(format!("non di gruppo: {:?}", e)))?;
+        if protocol_message.group_id() != group.group_id() {
+            return Err(MlsClientError::InvalidMessage("messaggio di un altro gruppo".into()));
+        }
+
+        let processed = group
+            .process_message(&self.provider, protocol_message)
+            .map_err(|e| match e {
+                ProcessMessageError::ValidationError(ValidationError::WrongEpoch) => {
+                    MlsClientError::WrongEpoch
+                }
+                e => MlsClientError::InvalidMessage(format!("{:?}", e)),
+            })?;
+
+        match processed.into_content() {
+            ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
+                group
+                    .merge_staged_commit(&self.provider, *staged_commit)
+                    .map_err(|e| MlsClientError::GroupOperation(format!("MergeCommit: {:?}", e)))?;
+                web_sys::console::log_1(&format!("[RUST-WASM] Commit applicato, epoch {}", group.epoch().as_u64()).into());
+            }
+            ProcessedMessageContent::ProposalMessage(proposal) => {
+                group
+                    .store_pending_proposal(self.provider.storage(), *proposal)
+                    .map_err(|e| MlsClientError::Storage(format!("StoreProposal: {:?}", e)))?;
+            }
+            ProcessedMessageContent::ExternalJoinProposalMessage(_) => {
+                return Err(MlsClientError::InvalidMessage("external join non supportati".into()));
+            }
+            ProcessedMessageContent::ApplicationMessage(_) => {
+                return Err(MlsClientError::InvalidMessage("messaggi applicativi non supportati".into()));
+            }
+        }
+
+        Ok(group.epoch().as_u64())
+    }
+
+    #[wasm_bindgen]
+    pub fn epoch(&self, group_id: &[u8]) -> Result<u64, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        Ok(group.epoch().as_u64())
+    }
+
+    #[wasm_bindgen]
+    pub fn own_leaf_index(&self, group_id: &[u8]) -> Result<u32, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        Ok(group.own_leaf_index().u32())
+    }
+
+    /// `false` dopo che un Commit ci ha rimosso dal gruppo.
+    #[wasm_bindgen]
+    pub fn is_active(&self, group_id: &[u8]) -> bool {
+        find_group(&self.groups, group_id).is_ok_and(|g| g.is_active())
+    }
+
+    #[wasm_bindgen]
+    pub fn get_master_secret(&self, group_id: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        group
+            .export_secret(self.provider.crypto(), "SFRAME_MASTER", &[], 32)
+            .map_err(|e| MlsClientError::Crypto(format!("ExportSecret: {:?}", e)))
+    }
+
+    // --------------------------------------------------------
+    // PERSISTENZA (ripresa dello stesso leaf dopo un reload)
+    // --------------------------------------------------------
+    // `key` è un segreto gestito da JS (es. derivato con WebCrypto):
+    // senza di esso il blob non è decifrabile.
+
+    /// Esporta chiavi di firma, KeyPackage privati e stato di tutti i gruppi
+    /// come blob cifrato.
+    #[wasm_bindgen]
+    pub fn export_state(&self, key: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        seal_state(
+            &self.identity,
+            self.ciphersuite,
+            &self.credential,
+            &self.provider,
+            &self.signature_keypair,
+            self.groups.keys(),
+            key,
+        )
+    }
+
+    /// Ricostruisce un client da un blob di `export_state`.
+    #[wasm_bindgen]
+    pub fn import_state(blob: &[u8], key: &[u8]) -> Result<WasmMlsClient, MlsClientError> {
+        let state = open_state(blob, key)?;
+
+        let mut groups = Groups::new();
+        for group_id in state.group_ids {
+            let group = MlsGroup::load(state.provider.storage(), &group_id)
+                .map_err(|e| MlsClientError::Storage(format!("LoadGroup: {:?}", e)))?
+                .ok_or_else(|| MlsClientError::InvalidState("gruppo assente nello storage".into()))?;
+            groups.insert(group_id, group);
+        }
+
+        web_sys::console::log_1(&"[RUST-WASM] Stato MLS ripristinato".into());
+        Ok(Self {
+            identity: state.identity,
+            ciphersuite: state.ciphersuite,
+            credential: state.credential,
+            provider: state.provider,
+            signature_keypair: state.signature_keypair,
+            groups,
+        })
+    }
+
+    /// Salva lo stato cifrato in `localStorage` (base64) sotto `slot`.
+    #[wasm_bindgen]
+    pub fn save_state(&self, slot: &str, key: &[u8]) -> Result<(), MlsClientError> {
+        let blob = self.export_state(key)?;
+        local_storage()?
+            .set_item(slot, &BASE64.encode(blob))
+            .map_err(|e| MlsClientError::Storage(format!("localStorage: {:?}", e)))
+    }
+
+    /// Ripristina da `localStorage`; `undefined` se lo slot è vuoto.
+    #[wasm_bindgen]
+    pub fn load_state(slot: &str, key: &[u8]) -> Result<Option<WasmMlsClient>, MlsClientError> {
+        let Some(encoded) = local_storage()?
+            .get_item(slot)
+            .map_err(|e| MlsClientError::Storage(format!("localStorage: {:?}", e)))?
+        else {
+            return Ok(None);
         };
 
-        let group = MlsGroup::new(&self.provider, &self.signature_keypair, &config, credential_with_key).unwrap();
-        self.group = Some(group);
-        Ok(())
+        let blob = BASE64
+            .decode(encoded)
+            .map_err(|e| MlsClientError::InvalidState(format!("base64: {e}")))?;
+        Self::import_state(&blob, key).map(Some)
+    }
+
+    // --------------------------------------------------------
+    // CHIAVI SFRAME DERIVATE DAL GRUPPO (RFC 9605 5.2)
+    // --------------------------------------------------------
+    // `context_id` distingue le tracce dello stesso membro (es. 0 audio, 1 video).
+
+    #[wasm_bindgen]
+    pub fn sframe_kid(&self, group_id: &[u8], context_id: u64, leaf_index: u32) -> Result<u64, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        Ok(sframe_key_id(context_id, group.epoch().as_u64(), leaf_index)?.into())
+    }
+
+    #[wasm_bindgen]
+    pub fn sframe_sender(
+        &self,
+        group_id: &[u8],
+        kind: TrackKind,
+        context_id: u64,
+        suite: Option<String>,
+    ) -> Result<WasmSender, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        let exporter = GroupExporter { group, provider: &self.provider };
+        let key_id = sframe_key_id(context_id, group.epoch().as_u64(), group.own_leaf_index().u32())?;
+
+        let mut sender = new_sender(key_id.into(), parse_suite(suite)?, None);
+        sender.set_encryption_key_from_mls(&exporter, key_id)?;
+
+        Ok(WasmSender::from_sender(kind, sender))
     }
 
     #[wasm_bindgen]
-    pub fn add_member(&mut self, kp_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
-        let group = self.group.as_mut().unwrap();
-        let kp = KeyPackageIn::tls_deserialize(&mut &kp_bytes[..]).unwrap()
-            .validate(self.provider.crypto(), ProtocolVersion::Mls10).unwrap();
-        
-        let (_commit, welcome, _) = group.add_members(&self.provider, &self.signature_keypair, &[kp]).unwrap();
-        group.merge_pending_commit(&self.provider).unwrap();
-        welcome.tls_serialize_detached().map_err(|_| JsValue::from_str("Errore ser"))
+    pub fn sframe_receiver(
+        &self,
+        group_id: &[u8],
+        kind: TrackKind,
+        context_id: u64,
+        leaf_index: u32,
+        suite: Option<String>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmReceiver, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        let exporter = GroupExporter { group, provider: &self.provider };
+        let key_id = sframe_key_id(context_id, group.epoch().as_u64(), leaf_index)?;
+
+        let mut receiver = new_receiver(parse_suite(suite)?, None, replay_window);
+        receiver.set_encryption_key_from_mls(&exporter, key_id)?;
+
+        Ok(WasmReceiver::from_receiver(kind, receiver))
     }
 
+    /// Sessione di gruppo completa per l'epoch corrente: una traccia locale
+    /// per ogni `context_id` e le chiavi RX di tutti gli altri membri.
     #[wasm_bindgen]
-    pub fn get_master_secret(&self) -> Result<Vec<u8>, JsValue> {
-        let group = self.group.as_ref().unwrap();
-        let secret = group.export_secret(self.provider.crypto(), "SFRAME_MASTER", &[], 32).unwrap();
-        Ok(secret)
+    pub fn sframe_session(
+        &self,
+        group_id: &[u8],
+        context_ids: Vec<u64>,
+        suite: Option<String>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmSession, MlsClientError> {
+        let group = find_group(&self.groups, group_id)?;
+        let exporter = GroupExporter { group, provider: &self.provider };
+        let epoch = group.epoch().as_u64();
+        let own_index = group.own_leaf_index().u32();
+
+        let mut session = WasmSession::with_suite(parse_suite(suite)?, None, replay_window);
+        let suite = session.suite();
+
+        for &context_id in &context_ids {
+            let key_id = sframe_key_id(context_id, epoch, own_index)?;
+            let mut sender = new_sender(key_id.into(), suite, None);
+            sender.set_encryption_key_from_mls(&exporter, key_id)?;
+            session.insert_local_sender(sender);
+
+            for member in group.members().filter(|m| m.index.u32() != own_index) {
+                let key_id = sframe_key_id(context_id, epoch, member.index.u32())?;
+                session
+                    .receiver_mut()
+                    .set_encryption_key_from_mls(&exporter, key_id)?;
+            }
+        }
+
+        Ok(session)
+    }
+}
+
+impl WasmMlsClient {
+    fn credential_with_key(&self) -> CredentialWithKey {
+        CredentialWithKey {
+            credential: self.credential.clone(),
+            signature_key: self.signature_keypair.public().into(),
+        }
+    }
+
+    fn build_key_package(&mut self, last_resort: bool) -> Result<Vec<u8>, MlsClientError> {
+        let mut builder = KeyPackage::builder().leaf_node_capabilities(client_capabilities());
+        if last_resort {
+            builder = builder.mark_as_last_resort();
+        }
+        let kp_bundle = builder
+            .build(self.ciphersuite, &self.provider, &self.signature_keypair, self.credential_with_key())
+            .map_err(|e| MlsClientError::Crypto(format!("KeyPackageBuilder: {:?}", e)))?;
+
+        let kp = kp_bundle.key_package();
+        let kp_ref = kp
+            .hash_ref(self.provider.crypto())
+            .map_err(|e| MlsClientError::Crypto(format!("HashRef: {:?}", e)))?;
+
+        self.provider
+            .storage()
+            .write_key_package(&kp_ref, &kp_bundle)
+            .map_err(|e| MlsClientError::Storage(format!("{:?}", e)))?;
+
+        web_sys::console::log_1(&"[RUST-WASM] KeyPackage generato e salvato nel Provider!".into());
+        kp.tls_serialize_detached().map_err(|_| MlsClientError::Serialization)
+    }
+
+    fn sign_handshake(&self, label: &[u8], room_id: &str, epoch: u64, message: &[u8]) -> Result<Vec<u8>, MlsClientError> {
+        let tbs = HandshakeTbs {
+            room_id: room_id.as_bytes().to_vec().into(),
+            sender: self.identity.as_bytes().to_vec().into(),
+            epoch,
+            message: message.to_vec().into(),
+        };
+        self.sign_with_label(label, &tbs)
     }
-}
\ No newline at end of file
+
+    fn sign_with_label(&self, label: &[u8], tbs: &impl Serialize) -> Result<Vec<u8>, MlsClientError> {
+        let content = SignContent {
+            label: label.to_vec().into(),
+            content: tbs
+                .tls_serialize_detached()
+                .map_err(|_| MlsClientError::Serialization)?
+                .into(),
+        };
+        let payload = content
+            .tls_serialize_detached()
+            .map_err(|_| MlsClientError::Serialization)?;
+
+        self.signature_keypair
+            .sign(&payload)
+            .map_err(|e| MlsClientError::Crypto(format!("Firma: {:?}", e)))
+    }
+}
@@ -0,0 +1,100 @@
+// src/mls_error.rs
+#![cfg(target_arch = "wasm32")]
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+// ------------------------------------------------------------
+// ERRORI DEL CLIENT MLS
+// ------------------------------------------------------------
+// Verso JS diventano un `Error` con un campo `code` stabile, così la
+// webapp può distinguere i casi recuperabili senza fare parsing del testo.
+
+#[derive(Debug)]
+pub enum MlsClientError {
+    NoGroup,
+    GroupInactive,
+    UnsupportedCiphersuite(String),
+    InvalidCredential(String),
+    InvalidKeyPackage(String),
+    InvalidWelcome(String),
+    InvalidMessage(String),
+    WrongEpoch,
+    Storage(String),
+    InvalidState(String),
+    Crypto(String),
+    Serialization,
+    GroupOperation(String),
+    Sframe(String),
+    GroupTooLarge(u32),
+}
+
+impl MlsClientError {
+    /// Codice stabile esposto a JS come `err.code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MlsClientError::NoGroup => "NO_GROUP",
+            MlsClientError::GroupInactive => "GROUP_INACTIVE",
+            MlsClientError::UnsupportedCiphersuite(_) => "UNSUPPORTED_CIPHERSUITE",
+            MlsClientError::InvalidCredential(_) => "INVALID_CREDENTIAL",
+            MlsClientError::InvalidKeyPackage(_) => "INVALID_KEY_PACKAGE",
+            MlsClientError::InvalidWelcome(_) => "INVALID_WELCOME",
+            MlsClientError::InvalidMessage(_) => "INVALID_MESSAGE",
+            MlsClientError::WrongEpoch => "WRONG_EPOCH",
+            MlsClientError::Storage(_) => "STORAGE_ERROR",
+            MlsClientError::InvalidState(_) => "INVALID_STATE",
+            MlsClientError::Crypto(_) => "CRYPTO_ERROR",
+            MlsClientError::Serialization => "SERIALIZATION_ERROR",
+            MlsClientError::GroupOperation(_) => "GROUP_OPERATION_FAILED",
+            MlsClientError::Sframe(_) => "SFRAME_ERROR",
+            MlsClientError::GroupTooLarge(_) => "GROUP_TOO_LARGE",
+        }
+    }
+}
+
+impl fmt::Display for MlsClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MlsClientError::NoGroup => write!(f, "Nessun gruppo MLS"),
+            MlsClientError::GroupInactive => write!(f, "Non più membro del gruppo MLS"),
+            MlsClientError::UnsupportedCiphersuite(e) => write!(f, "Ciphersuite MLS non supportata: {e}"),
+            MlsClientError::InvalidCredential(e) => write!(f, "Credenziale MLS non valida: {e}"),
+            MlsClientError::InvalidKeyPackage(e) => write!(f, "KeyPackage non valido: {e}"),
+            MlsClientError::InvalidWelcome(e) => write!(f, "Welcome non valido: {e}"),
+            MlsClientError::InvalidMessage(e) => write!(f, "Messaggio MLS non valido: {e}"),
+            MlsClientError::WrongEpoch => write!(f, "Messaggio MLS di un'altra epoch"),
+            MlsClientError::Storage(e) => write!(f, "Errore storage MLS: {e}"),
+            MlsClientError::InvalidState(e) => write!(f, "Stato MLS salvato non valido: {e}"),
+            MlsClientError::Crypto(e) => write!(f, "Errore crittografico MLS: {e}"),
+            MlsClientError::Serialization => write!(f, "Errore di serializzazione TLS"),
+            MlsClientError::GroupOperation(e) => write!(f, "Operazione di gruppo fallita: {e}"),
+            MlsClientError::Sframe(e) => write!(f, "Errore SFrame: {e}"),
+            MlsClientError::GroupTooLarge(i) => write!(f, "Leaf index {i} fuori dal KID SFrame"),
+        }
+    }
+}
+
+impl std::error::Error for MlsClientError {}
+
+impl From<sframe::error::SframeError> for MlsClientError {
+    fn from(e: sframe::error::SframeError) -> Self {
+        MlsClientError::Sframe(e.to_string())
+    }
+}
+
+impl From<crate::inspect::UnknownSuite> for MlsClientError {
+    fn from(e: crate::inspect::UnknownSuite) -> Self {
+        MlsClientError::Sframe(e.to_string())
+    }
+}
+
+impl From<MlsClientError> for JsValue {
+    fn from(err: MlsClientError) -> Self {
+        let js_err = js_sys::Error::new(&err.to_string());
+        js_err.set_name("MlsClientError");
+        // Reflect::set su un Error appena creato non può fallire
+        let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+        js_err.into()
+    }
+}
@@ -0,0 +1,197 @@
+// src/mls_state.rs
+#![cfg(target_arch = "wasm32")]
+
+use std::collections::HashMap;
+
+use openmls::prelude::{Ciphersuite, Credential, GroupId};
+use openmls_basic_credential::SignatureKeyPair;
+use openmls_rust_crypto::OpenMlsRustCrypto;
+use openmls_traits::OpenMlsProvider;
+use openmls_traits::crypto::OpenMlsCrypto;
+use openmls_traits::random::OpenMlsRand;
+use openmls_traits::types::{AeadType, HashType};
+use tls_codec::{Deserialize, Serialize, TlsDeserialize, TlsSerialize, TlsSize, VLBytes};
+
+use crate::mls_error::MlsClientError;
+
+// ------------------------------------------------------------
+// STATO PERSISTENTE DEL CLIENT MLS (blob cifrato)
+// ------------------------------------------------------------
+// Formato: [versione (1B) || salt (16B) || nonce (12B) || AES-256-GCM(stato)]
+// La chiave AEAD è HKDF-SHA256(salt, segreto fornito da JS); l'header in
+// chiaro è nell'AAD, così versione, salt e nonce non si possono alterare.
+// Lo storage di OpenMLS contiene già gruppo, KeyPackage privati e
+// proposal in coda: basta copiarlo insieme alla coppia di chiavi di firma.
+
+const STATE_VERSION: u8 = 1;
+const STATE_LABEL: &[u8] = b"sframe_core MLS client state";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = 1 + SALT_LEN + NONCE_LEN;
+const KEY_LEN: usize = 32;
+
+// AAD: etichetta || header del blob
+fn state_aad(header: &[u8]) -> Vec<u8> {
+    [STATE_LABEL, header].concat()
+}
+
+#[derive(Debug, TlsSerialize, TlsDeserialize, TlsSize)]
+struct StorageEntry {
+    key: VLBytes,
+    value: VLBytes,
+}
+
+#[derive(Debug, TlsSerialize, TlsDeserialize, TlsSize)]
+struct ClientState {
+    identity: VLBytes,
+    ciphersuite: u16,
+    credential: Credential,
+    signature_keypair: VLBytes,
+    group_ids: Vec<VLBytes>,
+    storage: Vec<StorageEntry>,
+}
+
+/// Stato ricostruito da `open_state`.
+pub(crate) struct RestoredState {
+    pub identity: String,
+    pub ciphersuite: Ciphersuite,
+    pub credential: Credential,
+    pub provider: OpenMlsRustCrypto,
+    pub signature_keypair: SignatureKeyPair,
+    pub group_ids: Vec<GroupId>,
+}
+
+fn state_key(
+    provider: &OpenMlsRustCrypto,
+    secret: &[u8],
+    salt: &[u8],
+) -> Result<Vec<u8>, MlsClientError> {
+    if secret.is_empty() {
+        return Err(MlsClientError::InvalidState("chiave di cifratura vuota".into()));
+    }
+
+    let crypto = provider.crypto();
+    let prk = crypto
+        .hkdf_extract(HashType::Sha2_256, salt, secret)
+        .map_err(|e| MlsClientError::Crypto(format!("HKDF: {:?}", e)))?;
+    let key = crypto
+        .hkdf_expand(HashType::Sha2_256, prk.as_slice(), STATE_LABEL, KEY_LEN)
+        .map_err(|e| MlsClientError::Crypto(format!("HKDF: {:?}", e)))?;
+
+    Ok(key.as_slice().to_vec())
+}
+
+/// Serializza e cifra lo stato completo del client.
+pub(crate) fn seal_state<'a>(
+    identity: &str,
+    ciphersuite: Ciphersuite,
+    credential: &Credential,
+    provider: &OpenMlsRustCrypto,
+    signature_keypair: &SignatureKeyPair,
+    group_ids: impl Iterator<Item = &'a GroupId>,
+    secret: &[u8],
+) -> Result<Vec<u8>, MlsClientError> {
+    let storage = provider
+        .storage()
+        .values
+        .read()
+        .map_err(|_| MlsClientError::Storage("storage MLS non leggibile".into()))?
+        .iter()
+        .map(|(k, v)| StorageEntry {
+            key: k.clone().into(),
+            value: v.clone().into(),
+        })
+        .collect();
+
+    let state = ClientState {
+        identity: identity.as_bytes().to_vec().into(),
+        ciphersuite: ciphersuite.into(),
+        credential: credential.clone(),
+        signature_keypair: signature_keypair
+            .tls_serialize_detached()
+            .map_err(|_| MlsClientError::Serialization)?
+            .into(),
+        group_ids: group_ids.map(|id| id.as_slice().to_vec().into()).collect(),
+        storage,
+    };
+    let plaintext = state
+        .tls_serialize_detached()
+        .map_err(|_| MlsClientError::Serialization)?;
+
+    let rand = provider.rand();
+    let salt = rand
+        .random_vec(SALT_LEN)
+        .map_err(|e| MlsClientError::Crypto(format!("Random: {:?}", e)))?;
+    let nonce = rand
+        .random_vec(NONCE_LEN)
+        .map_err(|e| MlsClientError::Crypto(format!("Random: {:?}", e)))?;
+    let key = state_key(provider, secret, &salt)?;
+
+    let mut blob = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    blob.push(STATE_VERSION);
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+
+    let ciphertext = provider
+        .crypto()
+        .aead_encrypt(AeadType::Aes256Gcm, &key, &plaintext, &nonce, &state_aad(&blob))
+        .map_err(|e| MlsClientError::Crypto(format!("AEAD: {:?}", e)))?;
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// Decifra un blob prodotto da `seal_state` e ricostruisce provider e chiavi.
+pub(crate) fn open_state(blob: &[u8], secret: &[u8]) -> Result<RestoredState, MlsClientError> {
+    let Some(&version) = blob.first() else {
+        return Err(MlsClientError::InvalidState("blob vuoto".into()));
+    };
+    if version != STATE_VERSION {
+        return Err(MlsClientError::InvalidState(format!("versione {version} non supportata")));
+    }
+    if blob.len() < HEADER_LEN {
+        return Err(MlsClientError::InvalidState("blob troncato".into()));
+    }
+    let (header, ciphertext) = blob.split_at(HEADER_LEN);
+    let (salt, nonce) = header[1..].split_at(SALT_LEN);
+
+    let provider = OpenMlsRustCrypto::default();
+    let key = state_key(&provider, secret, salt)?;
+    let plaintext = provider
+        .crypto()
+        .aead_decrypt(AeadType::Aes256Gcm, &key, ciphertext, nonce, &state_aad(header))
+        .map_err(|_| MlsClientError::InvalidState("chiave errata o blob alterato".into()))?;
+
+    let state = ClientState::tls_deserialize_exact(&plaintext)
+        .map_err(|_| MlsClientError::Serialization)?;
+
+    let signature_keypair =
+        SignatureKeyPair::tls_deserialize_exact(state.signature_keypair.as_slice())
+            .map_err(|_| MlsClientError::Serialization)?;
+    let ciphersuite = Ciphersuite::try_from(state.ciphersuite)
+        .map_err(|_| MlsClientError::UnsupportedCiphersuite(format!("{:#06x}", state.ciphersuite)))?;
+    let identity = String::from_utf8(state.identity.into())
+        .map_err(|_| MlsClientError::InvalidState("identità non UTF-8".into()))?;
+    let values: HashMap<Vec<u8>, Vec<u8>> = state
+        .storage
+        .into_iter()
+        .map(|e| (e.key.into(), e.value.into()))
+        .collect();
+    *provider
+        .storage()
+        .values
+        .write()
+        .map_err(|_| MlsClientError::Storage("storage MLS non scrivibile".into()))? = values;
+
+    Ok(RestoredState {
+        identity,
+        ciphersuite,
+        credential: state.credential,
+        provider,
+        signature_keypair,
+        group_ids: state
+            .group_ids
+            .iter()
+            .map(|id| GroupId::from_slice(id.as_slice()))
+            .collect(),
+    })
+}
@@ -1,17 +1,32 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 
+#[cfg(feature = "bytes")]
+use bytes::{Buf, BytesMut};
 use sframe::{
     CipherSuite,
-    error::Result,
+    error::{Result, SframeError},
     frame::EncryptedFrameView,
-    header::KeyId,
+    header::{Counter, KeyId, SframeHeader},
     key::DecryptionKey,
-    ratchet::RatchetingKeyStore,
+    mls::{MlsExporter, MlsKeyId},
+    ratchet::{RatchetingBaseKey, RatchetingKeyId},
 };
 
+/// Counter accettati dietro al più alto già ricevuto, per KID: copre il
+/// riordino tipico su UDP (qualche decina di frame) con margine.
+pub const DEFAULT_REPLAY_WINDOW: u64 = 128;
+
+/// Limite della finestra: la bitmap costa `size / 8` byte per KID.
+pub const MAX_REPLAY_WINDOW: u64 = 4096;
+
 pub struct ReceiverOptions {
     pub cipher_suite: CipherSuite,
     pub n_ratchet_bits: Option<u8>,
+    /// Ampiezza della finestra anti-replay (RFC 9605 9.3), limitata a
+    /// `MAX_REPLAY_WINDOW`; `None` la disattiva.
+    pub replay_window: Option<u64>,
 }
 
 impl Default for ReceiverOptions {
@@ -19,15 +34,68 @@ impl Default for ReceiverOptions {
         Self {
             cipher_suite: CipherSuite::AesGcm256Sha512,
             n_ratchet_bits: None,
+            replay_window: Some(DEFAULT_REPLAY_WINDOW),
+        }
+    }
+}
+
+/// Errori della decifratura: quelli di sframe più il replay, distinto
+/// perché un frame ripetuto va scartato senza trattarlo come attacco alle chiavi.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReceiverError {
+    Sframe(SframeError),
+    /// Counter già ricevuto o più vecchio della finestra per questo KID
+    Replay { key_id: KeyId, counter: Counter },
+    /// Ratchet step precedente a quello corrente: la sua chiave non c'è più
+    StaleRatchetStep { key_id: KeyId },
+}
+
+impl ReceiverError {
+    /// Codice stabile esposto a JS come `err.code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReceiverError::Sframe(SframeError::MissingDecryptionKey(_)) => "MISSING_KEY",
+            ReceiverError::Sframe(SframeError::DecryptionFailure) => "DECRYPTION_FAILED",
+            ReceiverError::Sframe(_) => "SFRAME_ERROR",
+            ReceiverError::Replay { .. } => "REPLAY",
+            ReceiverError::StaleRatchetStep { .. } => "STALE_RATCHET_STEP",
+        }
+    }
+}
+
+impl fmt::Display for ReceiverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiverError::Sframe(e) => write!(f, "{e}"),
+            ReceiverError::Replay { key_id, counter } => {
+                write!(f, "frame ripetuto o troppo vecchio (kid={key_id}, ctr={counter})")
+            }
+            ReceiverError::StaleRatchetStep { key_id } => {
+                write!(f, "ratchet step già superato (kid={key_id})")
+            }
         }
     }
 }
 
+impl std::error::Error for ReceiverError {}
+
+impl From<SframeError> for ReceiverError {
+    fn from(e: SframeError) -> Self {
+        ReceiverError::Sframe(e)
+    }
+}
+
+pub type DecryptResult<T> = std::result::Result<T, ReceiverError>;
+
 /// Receiver: gestisce la decifratura lato ricezione.
-/// Non esegue frame validation esplicita (evita problemi legati a Send).
+/// La protezione anti-replay non usa la frame validation di sframe
+/// (`Cell` + `Box<dyn>`, quindi non Send): le finestre per KID sono
+/// dati semplici e il Receiver resta Send.
 pub struct Receiver {
     keys: KeyStore,
     cipher_suite: CipherSuite,
+    n_ratchet_bits: Option<u8>,
+    replay: Option<ReplayWindows>,
     buffer: Vec<u8>,
 }
 
@@ -36,21 +104,103 @@ impl Receiver {
     /// [SFrame header || ciphertext || tag]
     ///
     /// Restituisce il payload in chiaro.
-    pub fn decrypt_frame<F>(&mut self, packet: F) -> Result<&[u8]>
+    pub fn decrypt_frame<F>(&mut self, packet: F) -> DecryptResult<&[u8]>
     where
         F: AsRef<[u8]>,
+    {
+        self.decrypt_frame_with_meta(packet, [])
+    }
+
+    /// Come `decrypt_frame`, verificando anche i metadati autenticati dal
+    /// sender con `Sender::encrypt_frame_with_meta`. Metadati diversi da
+    /// quelli usati in cifratura fanno fallire la verifica del tag.
+    pub fn decrypt_frame_with_meta<F, M>(&mut self, packet: F, meta_data: M) -> DecryptResult<&[u8]>
+    where
+        F: AsRef<[u8]>,
+        M: AsRef<[u8]>,
     {
         let data = packet.as_ref();
+        let meta_data = meta_data.as_ref();
 
-        let encrypted = EncryptedFrameView::try_from(data)?;
+        let encrypted = EncryptedFrameView::try_with_meta_data(data, meta_data)?;
+        let key_id = encrypted.header().key_id();
+        let counter = encrypted.header().counter();
 
-        // Se è attivo il ratcheting, tenta l’avanzamento della chiave
-        if let KeyStore::Ratcheting(keys) = &mut self.keys {
-            keys.try_ratchet(encrypted.header().key_id())?;
+        if let Some(replay) = &self.replay {
+            replay.check(key_id, counter)?;
         }
 
-        encrypted.decrypt_into(&self.keys, &mut self.buffer)?;
+        // Con il ratcheting uno step nuovo si deriva su una copia e si salva
+        // solo se il tag torna: un KID forgiato non fa avanzare la chiave
+        let ratcheted = match &self.keys {
+            KeyStore::Ratcheting(store) => store.ratchet_to(key_id, self.cipher_suite)?,
+            KeyStore::Standard(_) => None,
+        };
+
+        match &ratcheted {
+            Some(state) => encrypted.decrypt_into(&state.dec_key, &mut self.buffer)?,
+            None => encrypted.decrypt_into(&self.keys, &mut self.buffer)?,
+        };
 
+        if let (Some(state), KeyStore::Ratcheting(store)) = (ratcheted, &mut self.keys) {
+            store.commit(state);
+        }
+
+        // Il counter entra nella finestra solo dopo la verifica del tag:
+        // un pacchetto forgiato non può spostarla in avanti
+        if let Some(replay) = &mut self.replay
+            && replay.accept(key_id, counter)
+            && let Some(bits) = self.n_ratchet_bits
+        {
+            // Primo frame di un nuovo ratchet step: la chiave degli step
+            // precedenti non c'è più, e nemmeno le loro finestre servono
+            replay.retire_ratcheted(key_id, bits);
+        }
+
+        // Il buffer contiene [meta_data || payload]
+        Ok(&self.buffer[meta_data.len()..])
+    }
+
+    /// Decifra il pacchetto nella sua stessa memoria: il payload in chiaro
+    /// prende il posto del ciphertext e la Range restituita ne indica la
+    /// posizione in `packet` (subito dopo l'header).
+    ///
+    /// sframe non espone l'AEAD su memoria esterna, quindi il payload passa
+    /// comunque dal buffer interno, ma nessun frame viene allocato.
+    pub fn decrypt_in_place(&mut self, packet: &mut [u8]) -> DecryptResult<Range<usize>> {
+        let header_len = SframeHeader::deserialize(&*packet)?.len();
+        let payload_len = self.decrypt_frame(&*packet)?.len();
+
+        let range = header_len..header_len + payload_len;
+        packet[range.clone()].copy_from_slice(&self.buffer);
+        Ok(range)
+    }
+
+    /// Come `decrypt_in_place`, riducendo `packet` al solo payload in chiaro.
+    #[cfg(feature = "bytes")]
+    pub fn decrypt_in_place_bytes(&mut self, packet: &mut BytesMut) -> DecryptResult<()> {
+        let range = self.decrypt_in_place(packet)?;
+        packet.truncate(range.end);
+        packet.advance(range.start);
+        Ok(())
+    }
+
+    /// Inverso di `Sender::encrypt_frame_partial`: i primi `clear_len` byte
+    /// del pacchetto sono il prefisso in chiaro. Restituisce il frame
+    /// originale [prefisso || payload].
+    pub fn decrypt_frame_partial<F>(&mut self, packet: F, clear_len: usize) -> DecryptResult<&[u8]>
+    where
+        F: AsRef<[u8]>,
+    {
+        let packet = packet.as_ref();
+        if clear_len > packet.len() {
+            return Err(SframeError::DecryptionFailure.into());
+        }
+
+        let (clear, sframe) = packet.split_at(clear_len);
+        self.decrypt_frame_with_meta(sframe, clear)?;
+
+        // Il prefisso è già in testa al buffer
         Ok(&self.buffer)
     }
 
@@ -74,13 +224,56 @@ impl Receiver {
                 );
             }
             KeyStore::Ratcheting(store) => {
-                store.insert(self.cipher_suite, key_id, key_material)?;
+                store.insert(self.cipher_suite, key_id, key_material.as_ref())?;
             }
         }
 
         Ok(())
     }
 
+    /// Deriva la chiave di decifratura di un membro di un gruppo MLS (RFC 9605 5.2).
+    /// Non disponibile con il ratcheting attivo.
+    pub fn set_encryption_key_from_mls<E>(&mut self, exporter: &E, key_id: MlsKeyId) -> Result<()>
+    where
+        E: MlsExporter,
+    {
+        match &mut self.keys {
+            KeyStore::Standard(map) => {
+                map.insert(
+                    key_id.into(),
+                    DecryptionKey::derive_from_mls(self.cipher_suite, exporter, key_id)?,
+                );
+                Ok(())
+            }
+            KeyStore::Ratcheting(_) => Err(SframeError::Other(
+                "chiavi MLS non supportate con il ratcheting".into(),
+            )),
+        }
+    }
+
+    /// Rimuove la chiave di decifratura associata a un KeyId.
+    /// Restituisce `true` se la chiave era presente.
+    pub fn remove_encryption_key<K>(&mut self, key_id: K) -> bool
+    where
+        K: Into<KeyId>,
+    {
+        let key_id = key_id.into();
+
+        if let Some(replay) = &mut self.replay {
+            replay.windows.remove(&key_id);
+        }
+
+        match &mut self.keys {
+            KeyStore::Standard(map) => map.remove(&key_id).is_some(),
+            KeyStore::Ratcheting(store) => store.remove(key_id),
+        }
+    }
+
+    /// Cipher suite attesa sui pacchetti in ingresso (per l'ispezione).
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
     /// Crea un Receiver specificando la cipher suite.
     pub fn with_cipher_suite(cipher_suite: CipherSuite) -> Self {
         ReceiverOptions {
@@ -94,13 +287,15 @@ impl Receiver {
 impl From<ReceiverOptions> for Receiver {
     fn from(opts: ReceiverOptions) -> Self {
         let keys = match opts.n_ratchet_bits {
-            Some(bits) => KeyStore::Ratcheting(RatchetingKeyStore::new(bits)),
+            Some(bits) => KeyStore::Ratcheting(RatchetStore::new(bits)),
             None => KeyStore::default(),
         };
 
         Self {
             keys,
             cipher_suite: opts.cipher_suite,
+            n_ratchet_bits: opts.n_ratchet_bits,
+            replay: opts.replay_window.map(ReplayWindows::new),
             buffer: Default::default(),
         }
     }
@@ -114,10 +309,10 @@ impl Default for Receiver {
 
 /// Gestione delle chiavi lato receiver:
 /// - Standard: mappa KeyId -> DecryptionKey
-/// - Ratcheting: store con avanzamento automatico
+/// - Ratcheting: chiave dello step corrente per generation
 enum KeyStore {
     Standard(HashMap<KeyId, DecryptionKey>),
-    Ratcheting(RatchetingKeyStore),
+    Ratcheting(RatchetStore),
 }
 
 impl Default for KeyStore {
@@ -138,4 +333,459 @@ impl sframe::key::KeyStore for KeyStore {
             KeyStore::Ratcheting(store) => store.get_key(key_id),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Sostituisce `RatchetingKeyStore` di sframe, che avanza la chiave prima
+/// della verifica del tag e calcola la distanza tra step modulo 2^R, così
+/// anche uno step precedente in ritardo la farebbe avanzare.
+/// Qui il materiale dello step corrente resta disponibile e ogni step
+/// successivo si deriva su una copia (`ratchet_to`), salvata con `commit`
+/// solo dopo la decifratura.
+struct RatchetStore {
+    n_ratchet_bits: u8,
+    /// generation -> step corrente
+    keys: HashMap<u64, RatchetState>,
+}
+
+struct RatchetState {
+    key_id: RatchetingKeyId,
+    material: Vec<u8>,
+    dec_key: DecryptionKey,
+}
+
+impl RatchetState {
+    fn derive(cipher_suite: CipherSuite, key_id: RatchetingKeyId, material: Vec<u8>) -> Result<Self> {
+        Ok(Self {
+            key_id,
+            dec_key: DecryptionKey::derive_from(cipher_suite, key_id, &material)?,
+            material,
+        })
+    }
+}
+
+impl RatchetStore {
+    fn new(n_ratchet_bits: u8) -> Self {
+        Self {
+            n_ratchet_bits,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn ratcheting_id(&self, key_id: KeyId) -> RatchetingKeyId {
+        RatchetingKeyId::from_key_id(key_id, self.n_ratchet_bits)
+    }
+
+    fn insert(&mut self, cipher_suite: CipherSuite, key_id: KeyId, material: &[u8]) -> Result<()> {
+        let key_id = self.ratcheting_id(key_id);
+        let state = RatchetState::derive(cipher_suite, key_id, material.to_vec())?;
+        self.keys.insert(key_id.generation(), state);
+        Ok(())
+    }
+
+    fn remove(&mut self, key_id: KeyId) -> bool {
+        let generation = self.ratcheting_id(key_id).generation();
+        self.keys.remove(&generation).is_some()
+    }
+
+    fn get_key(&self, key_id: KeyId) -> Option<&DecryptionKey> {
+        let generation = self.ratcheting_id(key_id).generation();
+        self.keys
+            .get(&generation)
+            .map(|state| &state.dec_key)
+            .filter(|dec_key| dec_key.key_id() == key_id)
+    }
+
+    /// Chiave dello step di `key_id` se è successivo a quello corrente,
+    /// `None` se è quello corrente. Gli step sono modulo 2^R: la metà
+    /// che segue lo step corrente conta come avanti, il resto come passato.
+    fn ratchet_to(&self, key_id: KeyId, cipher_suite: CipherSuite) -> DecryptResult<Option<RatchetState>> {
+        let target = self.ratcheting_id(key_id);
+        let current = self
+            .keys
+            .get(&target.generation())
+            .ok_or(SframeError::MissingDecryptionKey(key_id))?;
+
+        let n_steps = 1u64 << self.n_ratchet_bits;
+        let step_diff = target.ratchet_step().wrapping_sub(current.key_id.ratchet_step()) % n_steps;
+        if step_diff == 0 {
+            return Ok(None);
+        }
+        if step_diff > (n_steps / 2).max(1) {
+            return Err(ReceiverError::StaleRatchetStep { key_id });
+        }
+
+        // ratchet_forward restituisce già la base dello step successivo
+        let mut base_key = RatchetingBaseKey::ratchet_forward(current.key_id, &current.material, cipher_suite)?;
+        let mut next = base_key.next_base_key()?;
+        for _ in 1..step_diff {
+            next = base_key.next_base_key()?;
+        }
+        let (next_id, material) = next;
+        Ok(Some(RatchetState::derive(cipher_suite, next_id, material)?))
+    }
+
+    fn commit(&mut self, state: RatchetState) {
+        self.keys.insert(state.key_id.generation(), state);
+    }
+}
+
+/// Finestre anti-replay per KID, create alla prima decifratura riuscita.
+struct ReplayWindows {
+    size: u64,
+    windows: HashMap<KeyId, ReplayWindow>,
+}
+
+impl ReplayWindows {
+    fn new(size: u64) -> Self {
+        Self {
+            size: size.clamp(1, MAX_REPLAY_WINDOW),
+            windows: HashMap::new(),
+        }
+    }
+
+    fn check(&self, key_id: KeyId, counter: Counter) -> DecryptResult<()> {
+        match self.windows.get(&key_id) {
+            Some(window) if !window.is_fresh(counter) => Err(ReceiverError::Replay { key_id, counter }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restituisce `true` se il KID non aveva ancora una finestra.
+    fn accept(&mut self, key_id: KeyId, counter: Counter) -> bool {
+        let size = self.size;
+        let mut created = false;
+        self.windows
+            .entry(key_id)
+            .or_insert_with(|| {
+                created = true;
+                ReplayWindow::new(size, counter)
+            })
+            .accept(counter);
+        created
+    }
+
+    // Con il ratcheting il KID è [generation || ratchet step]: tiene solo
+    // lo step corrente di ogni generation
+    fn retire_ratcheted(&mut self, key_id: KeyId, n_ratchet_bits: u8) {
+        let generation = |kid: KeyId| kid.checked_shr(n_ratchet_bits.into()).unwrap_or(0);
+        let current = generation(key_id);
+        self.windows
+            .retain(|&kid, _| kid == key_id || generation(kid) != current);
+    }
+}
+
+/// Bitmap circolare degli ultimi `size` counter rispetto al più alto
+/// ricevuto (bit `counter % size`).
+struct ReplayWindow {
+    size: u64,
+    highest: Counter,
+    seen: Vec<u64>,
+}
+
+impl ReplayWindow {
+    fn new(size: u64, first: Counter) -> Self {
+        Self {
+            size,
+            highest: first,
+            seen: vec![0; size.div_ceil(64) as usize],
+        }
+    }
+
+    fn bit(&self, counter: Counter) -> (usize, u64) {
+        let pos = counter % self.size;
+        ((pos / 64) as usize, 1 << (pos % 64))
+    }
+
+    fn is_fresh(&self, counter: Counter) -> bool {
+        if counter > self.highest {
+            return true;
+        }
+        if self.highest - counter >= self.size {
+            return false;
+        }
+        let (word, mask) = self.bit(counter);
+        self.seen[word] & mask == 0
+    }
+
+    fn accept(&mut self, counter: Counter) {
+        if counter > self.highest {
+            // Libera le posizioni dei counter saltati, ora fuori finestra
+            if counter - self.highest >= self.size {
+                self.seen.fill(0);
+            } else {
+                for skipped in self.highest + 1..counter {
+                    let (word, mask) = self.bit(skipped);
+                    self.seen[word] &= !mask;
+                }
+            }
+            self.highest = counter;
+        }
+        let (word, mask) = self.bit(counter);
+        self.seen[word] |= mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::Sender;
+
+    const SECRET: &[u8] = b"segreto di test per il receiver";
+
+    fn pair(key_id: u64) -> (Sender, Receiver) {
+        let mut sender = Sender::new(key_id);
+        sender.set_encryption_key(SECRET).unwrap();
+        let mut receiver = Receiver::default();
+        receiver.set_encryption_key(key_id, SECRET).unwrap();
+        (sender, receiver)
+    }
+
+    #[test]
+    fn decrypt_in_place() {
+        let (mut sender, mut receiver) = pair(4);
+
+        let mut packet = sender.encrypt_frame(b"payload in chiaro").unwrap().to_vec();
+        let header_len = SframeHeader::deserialize(&packet).unwrap().len();
+
+        let range = receiver.decrypt_in_place(&mut packet).unwrap();
+        assert_eq!(range.start, header_len);
+        assert_eq!(&packet[range], b"payload in chiaro");
+    }
+
+    #[test]
+    fn decrypt_in_place_rejects_tampering() {
+        let (mut sender, mut receiver) = pair(4);
+
+        let mut packet = sender.encrypt_frame(b"payload").unwrap().to_vec();
+        let last = packet.len() - 1;
+        packet[last] ^= 0x01;
+        let original = packet.clone();
+
+        assert!(matches!(receiver.decrypt_in_place(&mut packet), Err(ReceiverError::Sframe(_))));
+        // Il pacchetto non viene toccato se la verifica fallisce
+        assert_eq!(packet, original);
+        assert!(receiver.decrypt_in_place(&mut []).is_err());
+    }
+
+    #[test]
+    fn window_rejects_exact_replay() {
+        let mut window = ReplayWindow::new(16, 10);
+        window.accept(10);
+        assert!(!window.is_fresh(10));
+        assert!(window.is_fresh(11));
+    }
+
+    #[test]
+    fn window_accepts_reordering() {
+        let mut window = ReplayWindow::new(16, 10);
+        window.accept(10);
+        window.accept(15);
+
+        for counter in [12, 11, 14, 13] {
+            assert!(window.is_fresh(counter), "ctr={counter}");
+            window.accept(counter);
+            assert!(!window.is_fresh(counter), "ctr={counter}");
+        }
+        assert_eq!(window.highest, 15);
+    }
+
+    #[test]
+    fn window_edge() {
+        let mut window = ReplayWindow::new(16, 30);
+        window.accept(30);
+        // 30 - 14 = 16: appena fuori; 15 è l'ultimo dentro
+        assert!(!window.is_fresh(14));
+        assert!(window.is_fresh(15));
+    }
+
+    #[test]
+    fn window_forward_jump() {
+        for jump in [16, 17, 1000] {
+            let mut window = ReplayWindow::new(16, 5);
+            window.accept(5);
+            window.accept(6);
+            window.accept(5 + jump);
+
+            let highest = 5 + jump;
+            assert!(!window.is_fresh(highest));
+            // 5 è fuori finestra; 6 lo è oppure è ancora marcato come visto
+            assert!(!window.is_fresh(5));
+            assert!(!window.is_fresh(6));
+            // Le posizioni dei counter saltati sono libere
+            for counter in (highest - 15).max(7)..highest {
+                assert!(window.is_fresh(counter), "jump={jump} ctr={counter}");
+            }
+        }
+    }
+
+    #[test]
+    fn window_size_is_clamped() {
+        assert_eq!(ReplayWindows::new(0).size, 1);
+        assert_eq!(ReplayWindows::new(u64::MAX).size, MAX_REPLAY_WINDOW);
+
+        let (mut sender, _) = pair(4);
+        let mut receiver: Receiver = ReceiverOptions {
+            replay_window: Some(u64::MAX),
+            ..Default::default()
+        }
+        .into();
+        receiver.set_encryption_key(4u64, SECRET).unwrap();
+        let packet = sender.encrypt_frame(b"frame").unwrap().to_vec();
+        receiver.decrypt_frame(&packet).unwrap();
+        assert_eq!(receiver.replay.as_ref().unwrap().windows[&4].seen.len(), (MAX_REPLAY_WINDOW / 64) as usize);
+    }
+
+    #[test]
+    fn receiver_rejects_replay() {
+        let (mut sender, mut receiver) = pair(4);
+        let first = sender.encrypt_frame(b"uno").unwrap().to_vec();
+        let second = sender.encrypt_frame(b"due").unwrap().to_vec();
+
+        receiver.decrypt_frame(&second).unwrap();
+        receiver.decrypt_frame(&first).unwrap();
+        assert_eq!(
+            receiver.decrypt_frame(&first),
+            Err(ReceiverError::Replay { key_id: 4, counter: 0 })
+        );
+        assert_eq!(receiver.decrypt_frame(&first).unwrap_err().code(), "REPLAY");
+
+        // Senza finestra il replay passa
+        let mut open: Receiver = ReceiverOptions { replay_window: None, ..Default::default() }.into();
+        open.set_encryption_key(4u64, SECRET).unwrap();
+        open.decrypt_frame(&first).unwrap();
+        open.decrypt_frame(&first).unwrap();
+    }
+
+    #[test]
+    fn forged_frame_does_not_move_window() {
+        let (mut sender, mut receiver) = pair(4);
+        let mut forger = Sender::new(4u64);
+        forger.set_encryption_key(b"chiave sbagliata").unwrap();
+
+        let legit: Vec<Vec<u8>> = (0..3).map(|_| sender.encrypt_frame(b"ok").unwrap().to_vec()).collect();
+        receiver.decrypt_frame(&legit[0]).unwrap();
+
+        for _ in 0..1000 {
+            forger.encrypt_frame(b"falso").unwrap();
+        }
+        let forged = forger.encrypt_frame(b"falso").unwrap().to_vec();
+        assert!(matches!(receiver.decrypt_frame(&forged), Err(ReceiverError::Sframe(_))));
+        assert_eq!(receiver.replay.as_ref().unwrap().windows[&4].highest, 0);
+
+        // I frame veri in ritardo passano ancora
+        receiver.decrypt_frame(&legit[2]).unwrap();
+        receiver.decrypt_frame(&legit[1]).unwrap();
+    }
+
+    #[test]
+    fn ratchet_retires_old_windows() {
+        const BITS: u8 = 8;
+        let mut sender: Sender = crate::sender::SenderOptions {
+            key_id: 3 << BITS,
+            n_ratchet_bits: Some(BITS),
+            ..Default::default()
+        }
+        .into();
+        sender.set_encryption_key(SECRET).unwrap();
+
+        let mut receiver: Receiver = ReceiverOptions { n_ratchet_bits: Some(BITS), ..Default::default() }.into();
+        receiver.set_encryption_key(3u64 << BITS, SECRET).unwrap();
+        // Un'altra generation resta intatta
+        let (mut other, _) = pair(7 << BITS);
+        receiver.set_encryption_key(7u64 << BITS, SECRET).unwrap();
+        receiver.decrypt_frame(other.encrypt_frame(b"altra").unwrap()).unwrap();
+
+        for _ in 0..3 {
+            receiver.decrypt_frame(sender.encrypt_frame(b"frame").unwrap()).unwrap();
+            let kid = sender.ratchet().unwrap();
+            receiver.decrypt_frame(sender.encrypt_frame(b"frame").unwrap()).unwrap();
+
+            let mut kids: Vec<_> = receiver.replay.as_ref().unwrap().windows.keys().copied().collect();
+            kids.sort();
+            assert_eq!(kids, [kid, 7 << BITS]);
+        }
+    }
+
+    fn ratcheting_pair(bits: u8, generation: u64) -> (Sender, Receiver) {
+        let mut sender: Sender = crate::sender::SenderOptions {
+            key_id: generation << bits,
+            n_ratchet_bits: Some(bits),
+            ..Default::default()
+        }
+        .into();
+        sender.set_encryption_key(SECRET).unwrap();
+        let mut receiver: Receiver = ReceiverOptions { n_ratchet_bits: Some(bits), ..Default::default() }.into();
+        receiver.set_encryption_key(generation << bits, SECRET).unwrap();
+        (sender, receiver)
+    }
+
+    #[test]
+    fn forged_ratchet_step_does_not_advance_key() {
+        const BITS: u8 = 8;
+        let (mut sender, mut receiver) = ratcheting_pair(BITS, 3);
+
+        let first = sender.encrypt_frame(b"uno").unwrap().to_vec();
+        let second = sender.encrypt_frame(b"due").unwrap().to_vec();
+        receiver.decrypt_frame(&first).unwrap();
+
+        // Stessa generation, step successivo, chiave sbagliata
+        for step in [1u64, 5, 128] {
+            let mut forger = Sender::new((3 << BITS) | step);
+            forger.set_encryption_key(b"chiave sbagliata").unwrap();
+            let forged = forger.encrypt_frame(b"falso").unwrap().to_vec();
+            assert!(matches!(receiver.decrypt_frame(&forged), Err(ReceiverError::Sframe(_))), "step={step}");
+        }
+
+        // La chiave e la finestra dello step 0 sono intatte
+        assert_eq!(receiver.decrypt_frame(&second).unwrap(), b"due");
+        assert_eq!(receiver.decrypt_frame(&first), Err(ReceiverError::Replay { key_id: 3 << BITS, counter: 0 }));
+
+        // e il ratchet vero funziona ancora
+        sender.ratchet().unwrap();
+        assert_eq!(receiver.decrypt_frame(sender.encrypt_frame(b"tre").unwrap()).unwrap(), b"tre");
+    }
+
+    #[test]
+    fn late_previous_step_is_rejected() {
+        const BITS: u8 = 2;
+        let (mut sender, mut receiver) = ratcheting_pair(BITS, 3);
+
+        // Più giri completi degli step per coprire il wrap a 2^R
+        for _ in 0..6 {
+            let late = sender.encrypt_frame(b"in ritardo").unwrap().to_vec();
+            receiver.decrypt_frame(sender.encrypt_frame(b"prima").unwrap()).unwrap();
+
+            let kid = sender.ratchet().unwrap();
+            let current = sender.encrypt_frame(b"dopo").unwrap().to_vec();
+            receiver.decrypt_frame(&current).unwrap();
+
+            let late_kid = SframeHeader::deserialize(&late).unwrap().key_id();
+            assert_eq!(
+                receiver.decrypt_frame(&late),
+                Err(ReceiverError::StaleRatchetStep { key_id: late_kid })
+            );
+            assert_eq!(receiver.decrypt_frame(&late).unwrap_err().code(), "STALE_RATCHET_STEP");
+            // Lo step corrente non si è mosso e la sua finestra resta
+            let counter = SframeHeader::deserialize(&current).unwrap().counter();
+            assert_eq!(receiver.decrypt_frame(&current), Err(ReceiverError::Replay { key_id: kid, counter }));
+            assert_eq!(receiver.decrypt_frame(sender.encrypt_frame(b"ancora").unwrap()).unwrap(), b"ancora");
+        }
+    }
+
+    #[test]
+    fn with_cipher_suite() {
+        let mut sender = Sender::with_cipher_suite(2u64, CipherSuite::AesGcm128Sha256);
+        sender.set_encryption_key(SECRET).unwrap();
+        let packet = sender.encrypt_frame(b"frame").unwrap().to_vec();
+
+        let mut receiver = Receiver::with_cipher_suite(CipherSuite::AesGcm128Sha256);
+        assert_eq!(receiver.cipher_suite(), CipherSuite::AesGcm128Sha256);
+        receiver.set_encryption_key(2u64, SECRET).unwrap();
+        assert_eq!(receiver.decrypt_frame(&packet).unwrap(), b"frame");
+
+        // Stessa chiave, suite diversa: il tag non torna
+        let mut other = Receiver::default();
+        other.set_encryption_key(2u64, SECRET).unwrap();
+        assert!(other.decrypt_frame(&packet).is_err());
+    }
+}
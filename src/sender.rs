@@ -1,10 +1,14 @@
+#[cfg(feature = "bytes")]
+use bytes::BytesMut;
 use sframe::frame::MonotonicCounter;
 use sframe::{
     CipherSuite,
     error::{Result, SframeError},
-    frame::MediaFrameView,
+    frame::{FrameBuffer, MediaFrameView},
     header::KeyId,
     key::EncryptionKey,
+    mls::{MlsExporter, MlsKeyId},
+    ratchet::{RatchetingBaseKey, RatchetingKeyId},
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -12,6 +16,7 @@ pub struct SenderOptions {
     pub key_id: KeyId,
     pub cipher_suite: CipherSuite,
     pub max_counter: u64,
+    pub n_ratchet_bits: Option<u8>,
 }
 
 impl Default for SenderOptions {
@@ -20,6 +25,7 @@ impl Default for SenderOptions {
             key_id: 0,
             cipher_suite: CipherSuite::AesGcm256Sha512,
             max_counter: u64::MAX,
+            n_ratchet_bits: None,
         }
     }
 }
@@ -30,6 +36,8 @@ pub struct Sender {
     counter: MonotonicCounter,
     key_id: KeyId,
     cipher_suite: CipherSuite,
+    n_ratchet_bits: Option<u8>,
+    ratchet_key: Option<RatchetingBaseKey>,
     enc_key: Option<EncryptionKey>,
     buffer: Vec<u8>,
 }
@@ -54,25 +62,82 @@ impl Sender {
             counter: MonotonicCounter::default(),
             key_id,
             cipher_suite,
+            n_ratchet_bits: None,
+            ratchet_key: None,
             enc_key: None,
             buffer: Vec::new(),
         }
     }
 
     /// Imposta la chiave di cifratura derivandola dal key material fornito.
+    ///
+    /// Se il ratcheting è attivo (`n_ratchet_bits`), il KID viene interpretato come
+    /// [generation || ratchet step] e il key material diventa il base key da cui
+    /// derivare i ratchet step successivi.
     pub fn set_encryption_key<M>(&mut self, key_material: M) -> Result<()>
     where
         M: AsRef<[u8]>,
     {
+        let key_material = key_material.as_ref();
+
         self.enc_key = Some(EncryptionKey::derive_from(
             self.cipher_suite,
             self.key_id,
             key_material,
         )?);
 
+        self.ratchet_key = match self.n_ratchet_bits {
+            Some(bits) => Some(RatchetingBaseKey::ratchet_forward(
+                RatchetingKeyId::from_key_id(self.key_id, bits),
+                key_material,
+                self.cipher_suite,
+            )?),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    /// Deriva la chiave di cifratura da un gruppo MLS (RFC 9605 5.2):
+    /// il base key viene esportato dal gruppo e il KID codifica
+    /// context, epoch e indice del membro.
+    pub fn set_encryption_key_from_mls<E>(&mut self, exporter: &E, key_id: MlsKeyId) -> Result<()>
+    where
+        E: MlsExporter,
+    {
+        self.key_id = key_id.into();
+        self.enc_key = Some(EncryptionKey::derive_from_mls(
+            self.cipher_suite,
+            exporter,
+            key_id,
+        )?);
+        self.ratchet_key = None;
+
         Ok(())
     }
 
+    /// Avanza di un ratchet step (RFC 9605 5.1) senza nuovo key material:
+    /// - incrementa il ratchet step nel KID (la generation resta invariata)
+    /// - deriva la nuova chiave dal base key successivo
+    ///
+    /// Restituisce il nuovo KeyId. Fallisce se il ratcheting non è attivo.
+    pub fn ratchet(&mut self) -> Result<KeyId> {
+        let base_key = self
+            .ratchet_key
+            .as_mut()
+            .ok_or(SframeError::RatchetingFailure)?;
+
+        let (key_id, key_material) = base_key.next_base_key()?;
+        self.key_id = key_id.into();
+        self.enc_key = Some(EncryptionKey::derive_from(
+            self.cipher_suite,
+            self.key_id,
+            key_material,
+        )?);
+
+        Ok(self.key_id)
+    }
+
     /// Esegue un ratchet:
     /// - aggiorna il key_id
     /// - deriva e imposta una nuova chiave di cifratura
@@ -92,6 +157,20 @@ impl Sender {
     pub fn encrypt_frame<F>(&mut self, payload: F) -> Result<&[u8]>
     where
         F: AsRef<[u8]>,
+    {
+        self.encrypt_frame_with_meta(payload, [])
+    }
+
+    /// Come `encrypt_frame`, ma autentica anche `meta_data` (es. campi
+    /// dell'header RTP o un descrittore del codec) senza cifrarli:
+    /// AAD = [meta_data || header SFrame].
+    ///
+    /// I metadati non entrano nel pacchetto: il receiver deve ricostruirli
+    /// e passarli identici a `Receiver::decrypt_frame_with_meta`.
+    pub fn encrypt_frame_with_meta<F, M>(&mut self, payload: F, meta_data: M) -> Result<&[u8]>
+    where
+        F: AsRef<[u8]>,
+        M: AsRef<[u8]>,
     {
         let enc_key = self
             .enc_key
@@ -99,20 +178,83 @@ impl Sender {
             .ok_or(SframeError::EncryptionFailure)?;
 
         let data = payload.as_ref();
+        let meta_data = meta_data.as_ref();
 
-        // Nessuna AAD esterna: meta = []
-        let media_frame = MediaFrameView::with_meta_data(&mut self.counter, data, &[]);
+        let media_frame = MediaFrameView::with_meta_data(&mut self.counter, data, meta_data);
 
         self.buffer.clear();
 
-        // Riserva spazio: payload + overhead stimato (header + tag)
-        self.buffer.reserve(data.len() + 64);
+        // Riserva spazio: metadati + payload + overhead stimato (header + tag)
+        self.buffer.reserve(meta_data.len() + data.len() + 64);
 
         media_frame.encrypt_into(enc_key, &mut self.buffer)?;
 
+        // Il buffer contiene [meta_data || header || ciphertext || tag]
+        Ok(&self.buffer[meta_data.len()..])
+    }
+
+    /// Cifratura parziale: i primi `clear_len` byte del frame restano in
+    /// chiaro in testa al pacchetto e vengono autenticati come metadati.
+    /// Restituisce [frame[..clear_len] || header || ciphertext || tag].
+    pub fn encrypt_frame_partial<F>(&mut self, frame: F, clear_len: usize) -> Result<&[u8]>
+    where
+        F: AsRef<[u8]>,
+    {
+        let frame = frame.as_ref();
+        if clear_len > frame.len() {
+            return Err(SframeError::EncryptionFailure);
+        }
+
+        let (clear, payload) = frame.split_at(clear_len);
+        self.encrypt_frame_with_meta(payload, clear)?;
+
+        // Il prefisso in chiaro è già in testa al buffer
         Ok(&self.buffer)
     }
 
+    /// Cifra direttamente in `out`, che viene sovrascritto con
+    /// [header || ciphertext || tag]; restituisce la lunghezza del pacchetto.
+    ///
+    /// A differenza di `encrypt_frame` il risultato appartiene al chiamante:
+    /// può essere conservato o passato a un altro thread senza copie.
+    pub fn encrypt_into<F>(&mut self, payload: F, out: &mut Vec<u8>) -> Result<usize>
+    where
+        F: AsRef<[u8]>,
+    {
+        self.encrypt_into_buffer(payload.as_ref(), out)?;
+        Ok(out.len())
+    }
+
+    /// Come `encrypt_into`, ma accoda il pacchetto a `out` usandone la
+    /// capacità libera: con `out.split()` il frame diventa un `BytesMut`
+    /// indipendente e l'allocazione resta riutilizzabile.
+    #[cfg(feature = "bytes")]
+    pub fn encrypt_into_bytes<F>(&mut self, payload: F, out: &mut BytesMut) -> Result<usize>
+    where
+        F: AsRef<[u8]>,
+    {
+        let mut tail = BytesBuffer(out.split_off(out.len()));
+        self.encrypt_into_buffer(payload.as_ref(), &mut tail)?;
+
+        let len = tail.0.len();
+        // O(1): la coda è contigua a `out` (salvo riallocazione)
+        out.unsplit(tail.0);
+        Ok(len)
+    }
+
+    fn encrypt_into_buffer<B>(&mut self, data: &[u8], out: &mut B) -> Result<()>
+    where
+        B: FrameBuffer,
+    {
+        let enc_key = self
+            .enc_key
+            .as_ref()
+            .ok_or(SframeError::EncryptionFailure)?;
+
+        MediaFrameView::new(&mut self.counter, data).encrypt_into(enc_key, out)?;
+        Ok(())
+    }
+
     /// Reset opzionale del counter (utile in fase di test).
     pub fn reset_counter(&mut self) {
         self.counter = MonotonicCounter::default();
@@ -122,6 +264,11 @@ impl Sender {
     pub fn key_id(&self) -> KeyId {
         self.key_id
     }
+
+    /// Cipher suite dei pacchetti prodotti (per l'ispezione).
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
 }
 
 impl From<SenderOptions> for Sender {
@@ -130,6 +277,8 @@ impl From<SenderOptions> for Sender {
             counter: MonotonicCounter::new(opts.max_counter),
             key_id: opts.key_id,
             cipher_suite: opts.cipher_suite,
+            n_ratchet_bits: opts.n_ratchet_bits,
+            ratchet_key: None,
             enc_key: None,
             buffer: Vec::new(),
         }
@@ -140,4 +289,118 @@ impl Default for Sender {
     fn default() -> Self {
         SenderOptions::default().into()
     }
-}
\ No newline at end of file
+}
+
+// FrameBuffer di sframe sopra un BytesMut (Vec<u8> è già supportato)
+#[cfg(feature = "bytes")]
+struct BytesBuffer(BytesMut);
+
+#[cfg(feature = "bytes")]
+impl FrameBuffer for BytesBuffer {
+    type BufferSlice = Self;
+
+    fn allocate(&mut self, size: usize) -> Result<&mut Self::BufferSlice> {
+        self.0.resize(size, 0);
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl AsRef<[u8]> for BytesBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl AsMut<[u8]> for BytesBuffer {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl sframe::frame::Truncate for BytesBuffer {
+    fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receiver::Receiver;
+
+    const SECRET: &[u8] = b"segreto di test per il sender";
+
+    fn pair(key_id: u64, cipher_suite: CipherSuite) -> (Sender, Receiver) {
+        let mut sender = Sender::with_cipher_suite(key_id, cipher_suite);
+        sender.set_encryption_key(SECRET).unwrap();
+        let mut receiver = Receiver::with_cipher_suite(cipher_suite);
+        receiver.set_encryption_key(key_id, SECRET).unwrap();
+        (sender, receiver)
+    }
+
+    #[test]
+    fn encrypt_into_overwrites_out() {
+        let (mut sender, mut receiver) = pair(7, CipherSuite::AesGcm128Sha256);
+
+        let mut out = vec![0xaa; 500];
+        let len = sender.encrypt_into(b"primo frame", &mut out).unwrap();
+        assert_eq!(len, out.len());
+        let first = out.clone();
+
+        // Il pacchetto resta del chiamante anche dopo un'altra cifratura
+        sender.encrypt_into(b"secondo frame", &mut out).unwrap();
+        assert_eq!(receiver.decrypt_frame(&first).unwrap(), b"primo frame");
+        assert_eq!(receiver.decrypt_frame(&out).unwrap(), b"secondo frame");
+    }
+
+    #[test]
+    fn encrypt_into_matches_encrypt_frame() {
+        let (mut a, _) = pair(3, CipherSuite::AesGcm256Sha512);
+        let (mut b, _) = pair(3, CipherSuite::AesGcm256Sha512);
+
+        let mut out = Vec::new();
+        a.encrypt_into(b"frame", &mut out).unwrap();
+        assert_eq!(b.encrypt_frame(b"frame").unwrap(), out);
+    }
+
+    #[test]
+    fn reset_counter_restarts_from_zero() {
+        let (mut sender, mut receiver) = pair(1, CipherSuite::AesGcm256Sha512);
+        let first = sender.encrypt_frame(b"a").unwrap().to_vec();
+        sender.encrypt_frame(b"b").unwrap();
+
+        sender.reset_counter();
+        assert_eq!(sender.encrypt_frame(b"a").unwrap(), first);
+        receiver.decrypt_frame(&first).unwrap();
+    }
+
+    #[test]
+    fn new_uses_default_suite() {
+        let mut sender = Sender::new(9u64);
+        assert_eq!(sender.key_id(), 9);
+        assert_eq!(sender.cipher_suite(), CipherSuite::AesGcm256Sha512);
+        // Senza chiave non si cifra
+        assert!(sender.encrypt_into(b"x", &mut Vec::new()).is_err());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn encrypt_into_bytes_appends() {
+        let (mut sender, mut receiver) = pair(5, CipherSuite::AesGcm256Sha512);
+
+        let mut out = BytesMut::with_capacity(256);
+        let first = sender.encrypt_into_bytes(b"uno", &mut out).unwrap();
+        let second = sender.encrypt_into_bytes(b"due", &mut out).unwrap();
+        assert_eq!(out.len(), first + second);
+
+        let mut packet = out.split_to(first);
+        receiver.decrypt_in_place_bytes(&mut packet).unwrap();
+        assert_eq!(&packet[..], b"uno");
+
+        receiver.decrypt_in_place_bytes(&mut out).unwrap();
+        assert_eq!(&out[..], b"due");
+    }
+}
@@ -0,0 +1,151 @@
+// src/session.rs
+#![cfg(target_arch = "wasm32")]
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use sframe::CipherSuite;
+
+use crate::receiver::Receiver;
+use crate::sender::Sender;
+use crate::{capture_header, new_receiver, new_sender, parse_suite};
+
+// ------------------------------------------------------------
+// SESSIONE SFRAME DI GRUPPO (N MITTENTI REMOTI)
+// ------------------------------------------------------------
+// A differenza di WasmPeer (1 Sender + 1 Receiver per audio/video),
+// una sessione possiede:
+//   - un Sender per ogni traccia locale, indicizzato per KID
+//   - un unico Receiver con le chiavi di tutti i mittenti remoti
+// La decifratura viene instradata dal KID presente nell'header SFrame.
+
+#[wasm_bindgen]
+pub struct WasmSession {
+    suite: CipherSuite,
+    n_ratchet_bits: Option<u8>,
+    senders: HashMap<u64, Sender>,
+    receiver: Receiver,
+}
+
+#[wasm_bindgen]
+impl WasmSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        suite: Option<String>,
+        n_ratchet_bits: Option<u8>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmSession, JsValue> {
+        let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+        Ok(Self::with_suite(suite, n_ratchet_bits, replay_window))
+    }
+
+    // --------------------------------------------------------
+    // TRACCE LOCALI (TX)
+    // --------------------------------------------------------
+
+    /// Aggiunge (o sostituisce) il Sender di una traccia locale.
+    #[wasm_bindgen]
+    pub fn add_local_track(&mut self, kid: u64, secret: Vec<u8>) -> Result<(), JsValue> {
+        let mut sender = new_sender(kid, self.suite, self.n_ratchet_bits);
+        sender
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        self.senders.insert(kid, sender);
+        Ok(())
+    }
+
+    /// Ratchet SFrame della traccia `kid`: restituisce il nuovo KID,
+    /// con cui la traccia va indirizzata da qui in poi.
+    #[wasm_bindgen]
+    pub fn ratchet_local_track(&mut self, kid: u64) -> Result<u64, JsValue> {
+        let mut sender = self
+            .senders
+            .remove(&kid)
+            .ok_or_else(|| JsValue::from_str(&format!("Nessuna traccia locale con KID {kid}")))?;
+
+        let result = sender.ratchet();
+        self.senders.insert(sender.key_id(), sender);
+
+        result.map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_local_track(&mut self, kid: u64) -> bool {
+        self.senders.remove(&kid).is_some()
+    }
+
+    // --------------------------------------------------------
+    // MITTENTI REMOTI (RX)
+    // --------------------------------------------------------
+
+    /// Installa la chiave di un mittente remoto; da qui in poi i suoi
+    /// pacchetti vengono decifrati da `decrypt`.
+    #[wasm_bindgen]
+    pub fn add_remote_sender(&mut self, kid: u64, secret: Vec<u8>) -> Result<(), JsValue> {
+        self.receiver
+            .set_encryption_key(kid, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_remote_sender(&mut self, kid: u64) -> bool {
+        self.receiver.remove_encryption_key(kid)
+    }
+
+    // --------------------------------------------------------
+    // CIFRATURA / DECIFRATURA
+    // --------------------------------------------------------
+
+    #[wasm_bindgen]
+    pub fn encrypt(&mut self, kid: u64, input: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let sender = self
+            .senders
+            .get_mut(&kid)
+            .ok_or_else(|| JsValue::from_str(&format!("Nessuna traccia locale con KID {kid}")))?;
+
+        let packet = sender
+            .encrypt_frame(&input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?
+            .to_vec();
+        capture_header(true, self.suite, &packet);
+
+        Ok(packet)
+    }
+
+    /// Decifra un pacchetto di un qualsiasi mittente remoto registrato,
+    /// scegliendo la chiave in base al KID dell'header.
+    #[wasm_bindgen]
+    pub fn decrypt(&mut self, packet: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.suite, &packet);
+
+        self.receiver
+            .decrypt_frame(&packet)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+}
+
+impl WasmSession {
+    /// Usati da WasmMlsClient per popolare la sessione con chiavi MLS.
+    pub(crate) fn with_suite(suite: CipherSuite, n_ratchet_bits: Option<u8>, replay_window: Option<u32>) -> Self {
+        Self {
+            suite,
+            n_ratchet_bits,
+            senders: HashMap::new(),
+            receiver: new_receiver(suite, n_ratchet_bits, replay_window),
+        }
+    }
+
+    pub(crate) fn suite(&self) -> CipherSuite {
+        self.suite
+    }
+
+    pub(crate) fn insert_local_sender(&mut self, sender: Sender) {
+        self.senders.insert(sender.key_id(), sender);
+    }
+
+    pub(crate) fn receiver_mut(&mut self) -> &mut Receiver {
+        &mut self.receiver
+    }
+}
@@ -0,0 +1,183 @@
+// src/track.rs
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen::prelude::*;
+
+use crate::receiver::Receiver;
+use crate::sender::Sender;
+use crate::{capture_header, new_receiver, new_sender, parse_suite};
+
+// ------------------------------------------------------------
+// HANDLE TIPIZZATI PER SINGOLA TRACCIA (TX-only / RX-only)
+// ------------------------------------------------------------
+// Mappano 1:1 su sender::Sender e receiver::Receiver, così
+// createTxPeer/createRxPeer non devono più passare KID fittizi.
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackKind {
+    Audio = "audio",
+    Video = "video",
+}
+
+// ------------------------------------------------------------
+// SENDER (TX-only)
+// ------------------------------------------------------------
+
+#[wasm_bindgen]
+pub struct WasmSender {
+    kind: TrackKind,
+    inner: Sender,
+}
+
+#[wasm_bindgen]
+impl WasmSender {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        kind: TrackKind,
+        kid: u64,
+        suite: Option<String>,
+        secret: Vec<u8>,
+        n_ratchet_bits: Option<u8>,
+    ) -> Result<WasmSender, JsValue> {
+        let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+        let mut inner = new_sender(kid, suite, n_ratchet_bits);
+        inner
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        Ok(Self { kind, inner })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> TrackKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kid(&self) -> u64 {
+        self.inner.key_id()
+    }
+
+    /// Reinstalla la chiave mantenendo il KID corrente.
+    #[wasm_bindgen]
+    pub fn set_key(&mut self, secret: Vec<u8>) -> Result<(), JsValue> {
+        self.inner
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    /// Passa a un nuovo KID con nuovo key material (es. cambio epoch).
+    #[wasm_bindgen]
+    pub fn rekey(&mut self, kid: u64, secret: Vec<u8>) -> Result<(), JsValue> {
+        self.inner
+            .ratchet_encryption_key(kid, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    /// Ratchet SFrame (RFC 9605 5.1): avanza il ratchet step nel KID
+    /// senza nuovo key material. Restituisce il nuovo KID.
+    /// Richiede `n_ratchet_bits` nel costruttore.
+    #[wasm_bindgen]
+    pub fn ratchet(&mut self) -> Result<u64, JsValue> {
+        self.inner
+            .ratchet()
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt(&mut self, input: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let packet = self
+            .inner
+            .encrypt_frame(&input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?
+            .to_vec();
+        capture_header(true, self.inner.cipher_suite(), &packet);
+
+        Ok(packet)
+    }
+}
+
+impl WasmSender {
+    /// Usato da WasmMlsClient per consegnare Sender con chiave già derivata.
+    pub(crate) fn from_sender(kind: TrackKind, inner: Sender) -> Self {
+        Self { kind, inner }
+    }
+}
+
+// ------------------------------------------------------------
+// RECEIVER (RX-only)
+// ------------------------------------------------------------
+
+#[wasm_bindgen]
+pub struct WasmReceiver {
+    kind: TrackKind,
+    inner: Receiver,
+}
+
+#[wasm_bindgen]
+impl WasmReceiver {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        kind: TrackKind,
+        kid: u64,
+        suite: Option<String>,
+        secret: Vec<u8>,
+        n_ratchet_bits: Option<u8>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmReceiver, JsValue> {
+        let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+        let mut inner = new_receiver(suite, n_ratchet_bits, replay_window);
+        inner
+            .set_encryption_key(kid, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        Ok(Self { kind, inner })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> TrackKind {
+        self.kind
+    }
+
+    /// Installa (o sostituisce) la chiave per un KID.
+    #[wasm_bindgen]
+    pub fn set_key(&mut self, kid: u64, secret: Vec<u8>) -> Result<(), JsValue> {
+        self.inner
+            .set_encryption_key(kid, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_key(&mut self, kid: u64) -> bool {
+        self.inner.remove_encryption_key(kid)
+    }
+
+    /// Sostituisce la chiave di `from_kid` con quella di `to_kid`.
+    /// Con `n_ratchet_bits` i ratchet step dello stesso KID vengono invece
+    /// seguiti automaticamente in `decrypt`.
+    #[wasm_bindgen]
+    pub fn rekey(&mut self, from_kid: u64, to_kid: u64, secret: Vec<u8>) -> Result<(), JsValue> {
+        // Prima la rimozione: con il ratcheting KID della stessa generation
+        // condividono lo slot nello store.
+        self.inner.remove_encryption_key(from_kid);
+        self.set_key(to_kid, secret)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt(&mut self, packet: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.inner.cipher_suite(), &packet);
+
+        self.inner
+            .decrypt_frame(&packet)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+}
+
+impl WasmReceiver {
+    /// Usato da WasmMlsClient per consegnare Receiver con chiave già derivata.
+    pub(crate) fn from_receiver(kind: TrackKind, inner: Receiver) -> Self {
+        Self { kind, inner }
+    }
+}
@@ -14,103 +14,42 @@ use std::{
 
 mod receiver;
 mod sender;
+mod inspect;
 
 use clap::{Parser, ValueEnum};
 use receiver::{Receiver, ReceiverOptions};
 use sender::{Sender, SenderOptions};
 use sframe::{
     CipherSuite,
-    header::SframeHeader,
     ratchet::{RatchetingBaseKey, RatchetingKeyId},
 };
-use hex;
-
-const AES_GCM_TAG_LEN: usize = 16;
-
-/* ───────────────────────────── Helpers ───────────────────────────── */
-
-fn bytes_to_bin(bytes: &[u8]) -> String {
-    let mut s = String::with_capacity(bytes.len() * 9);
-    for (i, b) in bytes.iter().enumerate() {
-        use std::fmt::Write as _;
-        let _ = write!(s, "{b:08b}");
-        if i + 1 != bytes.len() {
-            s.push(' ');
-        }
-    }
-    s
-}
-
-fn inspect_packet(packet: &[u8]) {
-    let header = match SframeHeader::deserialize(packet) {
-        Ok(h) => h,
-        Err(e) => {
-            println!("[inspect_packet] errore deserializzazione header: {e:?}");
-            return;
-        }
-    };
-    let header_len = header.len();
-    let body_len = packet.len().saturating_sub(header_len);
-
-    let header_bytes = &packet[..header_len];
-    let body_bytes = &packet[header_len..];
-
-    println!("┌─ SFrame Packet ──────────────────────────────────────────");
-    println!("│ Header struct  : {header}");
-    println!("│ Header len     : {header_len} bytes");
-    println!("│ Header HEX     : {}", hex::encode(header_bytes));
-    println!("│ Header BIN     : {}", bytes_to_bin(header_bytes));
-    println!("│ KeyId          : {}", header.key_id());
-    println!("│ Counter        : {}", header.counter());
-    println!("│ Body len       : {body_len} bytes (ciphertext + tag)");
-    if body_len >= AES_GCM_TAG_LEN {
-        let ct = &body_bytes[..body_len - AES_GCM_TAG_LEN];
-        let tag = &body_bytes[body_len - AES_GCM_TAG_LEN..];
-        println!("│ Ciphertext HEX : {}", hex::encode(ct));
-        println!("│ Auth Tag HEX   : {}", hex::encode(tag));
-    } else {
-        println!("│ Body HEX       : {}", hex::encode(body_bytes));
-    }
-    println!("└──────────────────────────────────────────────────────────");
-}
-
-fn inspect_packet_compact(packet: &[u8]) {
-    match SframeHeader::deserialize(packet) {
-        Ok(h) => {
-            let header_len = h.len();
-            let body_len = packet.len().saturating_sub(header_len);
-            let (ct_len, tag_len) = if body_len >= AES_GCM_TAG_LEN {
-                (body_len - AES_GCM_TAG_LEN, AES_GCM_TAG_LEN)
-            } else {
-                (body_len, 0)
-            };
-            println!(
-                "[frame] kid={} ctr={} | aad(header)={}B, ct={}B, tag={}B, total={}B",
-                h.key_id(),
-                h.counter(),
-                header_len,
-                ct_len,
-                tag_len,
-                packet.len()
-            );
-            println!("        IV/nonce: derivato internamente (non serializzato)");
-        }
-        Err(e) => {
-            println!("[frame] errore header: {e:?}");
-        }
-    }
-}
 
 /* ───────────────────────────── CLI ───────────────────────────── */
 
+// Le suite AES-CTR + HMAC richiedono il backend openssl o rust-crypto
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum ArgCipherSuiteVariant {
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    #[value(name = "aes-ctr128-hmac-sha256-80")]
+    AesCtr128HmacSha256_80,
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    #[value(name = "aes-ctr128-hmac-sha256-64")]
+    AesCtr128HmacSha256_64,
+    #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+    #[value(name = "aes-ctr128-hmac-sha256-32")]
+    AesCtr128HmacSha256_32,
     AesGcm128Sha256,
     AesGcm256Sha512,
 }
 impl From<ArgCipherSuiteVariant> for CipherSuite {
     fn from(v: ArgCipherSuiteVariant) -> Self {
         match v {
+            #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+            ArgCipherSuiteVariant::AesCtr128HmacSha256_80 => CipherSuite::AesCtr128HmacSha256_80,
+            #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+            ArgCipherSuiteVariant::AesCtr128HmacSha256_64 => CipherSuite::AesCtr128HmacSha256_64,
+            #[cfg(any(feature = "sframe-openssl", feature = "sframe-rust-crypto"))]
+            ArgCipherSuiteVariant::AesCtr128HmacSha256_32 => CipherSuite::AesCtr128HmacSha256_32,
             ArgCipherSuiteVariant::AesGcm128Sha256 => CipherSuite::AesGcm128Sha256,
             ArgCipherSuiteVariant::AesGcm256Sha512 => CipherSuite::AesGcm256Sha512,
         }
@@ -197,6 +136,7 @@ fn encrypt_file_sframe(
     output: &PathBuf,
     chunk: usize,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let infile = File::open(input)?;
     let mut r = BufReader::new(infile);
@@ -213,7 +153,7 @@ fn encrypt_file_sframe(
         w.write_all(frame)?;
         if inspect {
             println!("[enc:file] chunk #{i} pt_in={}B", n);
-            inspect_packet_compact(frame);
+            inspect::print_compact("[frame]", frame, suite);
         }
         i += 1;
     }
@@ -226,6 +166,7 @@ fn decrypt_file_sframe(
     input: &PathBuf,
     output: &PathBuf,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let infile = File::open(input)?;
     let mut r = BufReader::new(infile);
@@ -239,7 +180,7 @@ fn decrypt_file_sframe(
         r.read_exact(&mut frame)?;
         if inspect {
             println!("[dec:file] frame #{i} enc_len={}B", len);
-            inspect_packet_compact(&frame);
+            inspect::print_compact("[frame]", &frame, suite);
         }
         let dec = receiver.decrypt_frame(&frame).map_err(|e| anyhow::anyhow!("{e:?}"))?;
         if inspect {
@@ -263,6 +204,7 @@ fn tcp_send(
     mut source: impl Read,
     chunk: usize,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let addr = format!("{host}:{port}");
     println!("[tcp-send] connecting to {addr} …");
@@ -278,7 +220,7 @@ fn tcp_send(
         stream.write_all(frame)?;
         if inspect {
             println!("[tcp-send] frame #{i} pt_in={}B", n);
-            inspect_packet_compact(frame);
+            inspect::print_compact("[frame]", frame, suite);
         }
         i += 1;
     }
@@ -298,6 +240,7 @@ fn tcp_recv(
     port: u16,
     mut sink: impl Write,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let addr = format!("{host}:{port}");
     println!("[tcp-recv] listening on {addr} …");
@@ -316,7 +259,7 @@ fn tcp_recv(
         stream.read_exact(&mut frame)?;
         if inspect {
             println!("[tcp-recv] frame #{i} enc_len={}B", len);
-            inspect_packet_compact(&frame);
+            inspect::print_compact("[frame]", &frame, suite);
         }
         let dec = receiver.decrypt_frame(&frame).map_err(|e| anyhow::anyhow!("{e:?}"))?;
         if inspect {
@@ -341,6 +284,7 @@ fn udp_send(
     mut source: impl Read,
     chunk: usize,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let addr = format!("{host}:{port}");
     println!("[udp-send] will send to {addr}");
@@ -356,7 +300,7 @@ fn udp_send(
         let sent = socket.send(frame)?;
         if inspect {
             println!("[udp-send] frame #{i} pt_in={}B, sent={}B", n, sent);
-            inspect_packet_compact(frame);
+            inspect::print_compact("[frame]", frame, suite);
         }
         i += 1;
         // pacing minimo per simulare un framerate e non saturare
@@ -372,6 +316,7 @@ fn udp_recv(
     port: u16,
     mut sink: impl Write,
     inspect: bool,
+    suite: CipherSuite,
 ) -> anyhow::Result<()> {
     let addr = format!("{host}:{port}");
     println!("[udp-recv] binding {addr}");
@@ -386,7 +331,7 @@ fn udp_recv(
                 let frame = &buf[..n];
                 if inspect {
                     println!("[udp-recv] from {} frame #{i} enc_len={}B", peer, n);
-                    inspect_packet_compact(frame);
+                    inspect::print_compact("[frame]", frame, suite);
                 }
                 match receiver.decrypt_frame(frame) {
                     Ok(dec) => {
@@ -463,7 +408,7 @@ fn main() -> anyhow::Result<()> {
 
     // Receiver
     let mut receiver =
-        Receiver::from(ReceiverOptions { cipher_suite, frame_validation: None, n_ratchet_bits });
+        Receiver::from(ReceiverOptions { cipher_suite, n_ratchet_bits, ..Default::default() });
     receiver.set_encryption_key(runtime_key_id, &secret).unwrap();
 
     match mode {
@@ -518,7 +463,7 @@ fn main() -> anyhow::Result<()> {
                 };
                 let packet: Vec<u8> = encrypted.to_vec();
                 println!("Sender → frame cifrato ({} byte totali)", packet.len());
-                inspect_packet(&packet);
+                inspect::print_dump(&packet, cipher_suite);
 
                 match receiver.decrypt_frame(&packet) {
                     Ok(decrypted) => {
@@ -538,7 +483,7 @@ fn main() -> anyhow::Result<()> {
             let input = input.expect("--input è richiesto in --mode enc");
             let output = output.unwrap_or_else(|| { let mut p = input.clone(); p.set_extension("sframe"); p });
             println!("- Encrypting file: {} → {}", input.display(), output.display());
-            encrypt_file_sframe(&mut sender, &input, &output, chunk, inspect)?;
+            encrypt_file_sframe(&mut sender, &input, &output, chunk, inspect, cipher_suite)?;
             println!("✓ Done");
             Ok(())
         }
@@ -546,47 +491,47 @@ fn main() -> anyhow::Result<()> {
             let input = input.expect("--input è richiesto in --mode dec");
             let output = output.unwrap_or_else(|| { let mut p = input.clone(); p.set_extension("dec"); p });
             println!("- Decrypting file: {} → {}", input.display(), output.display());
-            decrypt_file_sframe(&mut receiver, &input, &output, inspect)?;
+            decrypt_file_sframe(&mut receiver, &input, &output, inspect, cipher_suite)?;
             println!("✓ Done");
             Ok(())
         }
         ArgMode::TcpSend => {
             if let Some(path) = input {
                 let mut f = File::open(&path)?;
-                tcp_send(sender, &host, port, &mut f, chunk, inspect)?;
+                tcp_send(sender, &host, port, &mut f, chunk, inspect, cipher_suite)?;
             } else {
                 let stdin = io::stdin();
-                tcp_send(sender, &host, port, stdin.lock(), chunk, inspect)?;
+                tcp_send(sender, &host, port, stdin.lock(), chunk, inspect, cipher_suite)?;
             }
             Ok(())
         }
         ArgMode::TcpRecv => {
             if let Some(path) = output {
                 let mut f = File::create(&path)?;
-                tcp_recv(receiver, &host, port, &mut f, inspect)?;
+                tcp_recv(receiver, &host, port, &mut f, inspect, cipher_suite)?;
             } else {
                 let stdout = io::stdout();
-                tcp_recv(receiver, &host, port, stdout.lock(), inspect)?;
+                tcp_recv(receiver, &host, port, stdout.lock(), inspect, cipher_suite)?;
             }
             Ok(())
         }
         ArgMode::UdpSend => {
             if let Some(path) = input {
                 let mut f = File::open(&path)?;
-                udp_send(sender, &host, port, &mut f, chunk, inspect)?;
+                udp_send(sender, &host, port, &mut f, chunk, inspect, cipher_suite)?;
             } else {
                 let stdin = io::stdin();
-                udp_send(sender, &host, port, stdin.lock(), chunk, inspect)?;
+                udp_send(sender, &host, port, stdin.lock(), chunk, inspect, cipher_suite)?;
             }
             Ok(())
         }
         ArgMode::UdpRecv => {
             if let Some(path) = output {
                 let mut f = File::create(&path)?;
-                udp_recv(receiver, &host, port, &mut f, inspect)?;
+                udp_recv(receiver, &host, port, &mut f, inspect, cipher_suite)?;
             } else {
                 let stdout = io::stdout();
-                udp_recv(receiver, &host, port, stdout.lock(), inspect)?;
+                udp_recv(receiver, &host, port, stdout.lock(), inspect, cipher_suite)?;
             }
             Ok(())
             // termina con CTRL+C
@@ -17,10 +17,10 @@ use nokhwa::pixel_format::RgbFormat;
 use nokhwa::utils::{ApiBackend, CameraFormat, CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType};
 use nokhwa::{query, Camera};
 
-use sframe::CipherSuite;
 
 mod sender;
 mod receiver;
+mod inspect;
 mod mls_peer_output;          // <── nuovo modulo su file separato
 
 use sender::Sender;
@@ -74,14 +74,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
     } else { def }
 }
 
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
 /* ───────────── OS/backend helpers ───────────── */
 
 #[inline]
@@ -178,8 +170,7 @@ fn main() -> Result<()> {
     let want_h   = read_flag_u32(&args, "--height", 480);
     let want_fps = read_flag_u32(&args, "--fps", 30);
     let quality  = read_flag_u32(&args, "--quality", 70) as u8;
-    let suite    = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))
-        .unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite    = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect       = has_flag(&args, "--inspect");
     let list          = has_flag(&args, "--list");
     let prefer_mjpeg  = has_flag(&args, "--prefer-mjpeg");
@@ -283,12 +274,14 @@ fn main() -> Result<()> {
     let mut r_audio = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r_audio.set_encryption_key(kids.recv_aud, &mls_keys.audio_secret)?;
 
     let mut r_video = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r_video.set_encryption_key(kids.recv_vid, &mls_keys.video_secret)?;
 
@@ -383,9 +376,9 @@ fn main() -> Result<()> {
 
                 if inspect {
                     match sid {
-                        SID_VIDEO => output::inspect_packet_compact("[RX][VID]", pkt),
-                        SID_AUDIO => output::inspect_packet_compact("[RX][AUD]", pkt),
-                        _         => output::inspect_packet_compact("[RX][UNK]", pkt),
+                        SID_VIDEO => inspect::print_compact("[RX][VID]", pkt, suite),
+                        SID_AUDIO => inspect::print_compact("[RX][AUD]", pkt, suite),
+                        _         => inspect::print_compact("[RX][UNK]", pkt, suite),
                     }
                 }
 
@@ -576,7 +569,7 @@ fn main() -> Result<()> {
                 };
 
                 if inspect && (n % 30 == 0) {
-                    output::inspect_packet_compact("[TX][VID]", pkt);
+                    inspect::print_compact("[TX][VID]", pkt, suite);
                 }
 
                 if let Err(e) = send_frame(&stream, SID_VIDEO, pkt) {
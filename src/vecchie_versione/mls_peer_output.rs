@@ -2,8 +2,6 @@
 
 use std::sync::{Arc, Mutex};
 
-use sframe::header::SframeHeader;
-
 use winit::{
     dpi::LogicalSize,
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
@@ -12,19 +10,6 @@ use winit::{
 };
 use pixels::{Pixels, SurfaceTexture};
 
-/// Stampa compatta delle informazioni dell'header SFrame per debug/inspect.
-pub fn inspect_packet_compact(prefix: &str, packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "{prefix} kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(), h.counter(), hdr, ct, tag, packet.len()
-        );
-    }
-}
-
 /// Event loop grafico per mostrare il video RX in una finestra.
 ///
 /// Non ritorna mai (tipo `!`), come `EventLoop::run`.
@@ -30,8 +30,6 @@ use nokhwa::pixel_format::RgbFormat;
 use nokhwa::utils::{ApiBackend, CameraFormat, CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType};
 use nokhwa::{query, Camera};
 
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 use winit::{
     dpi::LogicalSize,
@@ -43,6 +41,7 @@ use pixels::{Pixels, SurfaceTexture};
 
 mod sender;     // riusa i tuoi mod
 mod receiver;   // riusa i tuoi mod
+mod inspect;
 use sender::Sender;
 use receiver::Receiver;
 
@@ -74,18 +73,6 @@ fn recv_frame<'a>(s: &mut TcpStream, buf: &'a mut Vec<u8>) -> std::io::Result<(u
 }
 
 /* ───────────── Inspect ───────────── */
-fn inspect_packet_compact(prefix: &str, packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "{prefix} kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(), h.counter(), hdr, ct, tag, packet.len()
-        );
-    }
-}
-
 /* ───────────── Helpers CLI ───────────── */
 fn has_flag(args: &[String], f: &str) -> bool { args.iter().any(|a| a == f) }
 fn read_flag_u32(args: &[String], name: &str, def: u32) -> u32 {
@@ -97,14 +84,6 @@ fn read_flag_u64(args: &[String], name: &str, def: u64) -> u64 {
 fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
     if let Some(i) = args.iter().position(|a| a == name) { args.get(i + 1).map(|s| s.as_str()).unwrap_or(def) } else { def }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
 /* ───────────── OS/backend helpers ───────────── */
 #[inline]
 fn default_backend() -> ApiBackend {
@@ -191,7 +170,7 @@ fn main() -> Result<()> {
     let key_audio = read_flag_u64(&args, "--key-audio", 1);
     let key_video = read_flag_u64(&args, "--key-video", 2);
     let secret = read_flag_str(&args, "--secret", "SUPER_SECRET");
-    let suite = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512")).unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect = has_flag(&args, "--inspect");
     let list = has_flag(&args, "--list");
     let prefer_mjpeg = has_flag(&args, "--prefer-mjpeg");
@@ -250,9 +229,9 @@ fn main() -> Result<()> {
     let mut s_video = Sender::with_cipher_suite(key_video, suite);
     s_video.set_encryption_key(secret.as_bytes())?;
 
-    let mut r_audio = Receiver::from(receiver::ReceiverOptions { cipher_suite: suite, n_ratchet_bits: None });
+    let mut r_audio = Receiver::from(receiver::ReceiverOptions { cipher_suite: suite, n_ratchet_bits: None, ..Default::default() });
     r_audio.set_encryption_key(key_audio, secret.as_bytes())?;
-    let mut r_video = Receiver::from(receiver::ReceiverOptions { cipher_suite: suite, n_ratchet_bits: None });
+    let mut r_video = Receiver::from(receiver::ReceiverOptions { cipher_suite: suite, n_ratchet_bits: None, ..Default::default() });
     r_video.set_encryption_key(key_video, secret.as_bytes())?;
 
     // AUDIO OUTPUT (RX) — come rx_av, creato PRIMA dell'event loop
@@ -319,9 +298,9 @@ fn main() -> Result<()> {
                 };
                 if inspect {
                     match sid {
-                        SID_VIDEO => inspect_packet_compact("[RX][VID]", pkt),
-                        SID_AUDIO => inspect_packet_compact("[RX][AUD]", pkt),
-                        _ => inspect_packet_compact("[RX][UNK]", pkt),
+                        SID_VIDEO => inspect::print_compact("[RX][VID]", pkt, suite),
+                        SID_AUDIO => inspect::print_compact("[RX][AUD]", pkt, suite),
+                        _ => inspect::print_compact("[RX][UNK]", pkt, suite),
                     }
                 }
                 match sid {
@@ -474,7 +453,7 @@ fn main() -> Result<()> {
 
                 let pkt = match s_video.encrypt_frame(&jpeg_buf) { Ok(p) => p, Err(e) => { eprintln!("[peer_av][tx][video] sframe err: {e:?}"); continue; } };
 
-                if inspect && (n % 30 == 0) { inspect_packet_compact("[TX][VID]", pkt); }
+                if inspect && (n % 30 == 0) { inspect::print_compact("[TX][VID]", pkt, suite); }
 
                 if let Err(e) = send_frame(&stream, SID_VIDEO, pkt) { eprintln!("[peer_av][tx][video] send err: {e}"); break; }
 
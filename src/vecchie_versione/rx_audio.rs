@@ -6,65 +6,14 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, Stream, StreamConfig};
 
 mod receiver;
+mod inspect;
 use receiver::Receiver;
 
 // ───────────────────────── Debug helpers (inspect) ─────────────────────────
-use sframe::header::SframeHeader;
-use hex;
-
-fn bytes_to_bin(bytes: &[u8]) -> String {
-    let mut s = String::with_capacity(bytes.len() * 9);
-    for (i, b) in bytes.iter().enumerate() {
-        use std::fmt::Write as _;
-        let _ = write!(s, "{b:08b}");
-        if i + 1 != bytes.len() { s.push(' '); }
-    }
-    s
-}
-
-fn inspect_packet_compact(packet: &[u8]) {
-    match SframeHeader::deserialize(packet) {
-        Ok(h) => {
-            let header_len = h.len();
-            let body_len = packet.len().saturating_sub(header_len);
-            let (ct_len, tag_len) = if body_len >= 16 { (body_len - 16, 16) } else { (body_len, 0) };
-            println!(
-                "[RX][SFRAME] kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-                h.key_id(), h.counter(), header_len, ct_len, tag_len, packet.len()
-            );
-        }
-        Err(e) => println!("[RX][SFRAME] header parse error: {e:?}"),
-    }
-}
+use sframe::CipherSuite;
 
-fn inspect_packet_verbose(packet: &[u8]) {
-    match SframeHeader::deserialize(packet) {
-        Ok(h) => {
-            let header_len = h.len();
-            let body_len = packet.len().saturating_sub(header_len);
-            let header_bytes = &packet[..header_len];
-            let body_bytes = &packet[header_len..];
-            println!("┌─ [RX] SFrame Packet ─────────────────────────────────────");
-            println!("│ Header struct  : {h}");
-            println!("│ Header len     : {header_len} bytes");
-            println!("│ Header HEX     : {}", hex::encode(header_bytes));
-            println!("│ Header BIN     : {}", bytes_to_bin(header_bytes));
-            println!("│ KeyId          : {}", h.key_id());
-            println!("│ Counter        : {}", h.counter());
-            println!("│ Body len       : {body_len} bytes (ciphertext + tag)");
-            if body_len >= 16 {
-                let ct = &body_bytes[..body_len - 16];
-                let tag = &body_bytes[body_len - 16..];
-                
-                println!("│ Auth Tag HEX   : {}", hex::encode(tag));
-            } else {
-                println!("│ Body HEX       : {}", hex::encode(body_bytes));
-            }
-            println!("└──────────────────────────────────────────────────────────");
-        }
-        Err(e) => println!("[RX][SFRAME] header parse error: {e:?}"),
-    }
-}
+// Suite di default di Sender::new / Receiver::default
+const AUDIO_SUITE: CipherSuite = CipherSuite::AesGcm256Sha512;
 
 // ───────────────────────── Playback ─────────────────────────
 
@@ -191,9 +140,9 @@ fn rx_loop(
 
         if inspect {
             if verbose {
-                inspect_packet_verbose(pkt);
+                inspect::print_dump(pkt, AUDIO_SUITE);
             } else {
-                if i % 50 == 0 { inspect_packet_compact(pkt); }
+                if i % 50 == 0 { inspect::print_compact("[RX][SFRAME]", pkt, AUDIO_SUITE); }
             }
         }
 
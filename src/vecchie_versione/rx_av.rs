@@ -7,8 +7,6 @@ use std::time::{Duration, Instant};
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use image::GenericImageView;
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 use winit::{
     dpi::LogicalSize,
@@ -19,6 +17,7 @@ use winit::{
 use pixels::{Error, Pixels, SurfaceTexture};
 
 mod receiver;
+mod inspect;
 use receiver::Receiver;
 
 const SID_VIDEO: u8 = 0x01;
@@ -39,23 +38,6 @@ fn recv_frame<'a>(s: &mut TcpStream, buf: &'a mut Vec<u8>) -> std::io::Result<(u
     Ok((sid[0], &buf[..]))
 }
 
-fn inspect_packet(prefix: &str, packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "{prefix} kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(),
-            h.counter(),
-            hdr,
-            ct,
-            tag,
-            packet.len()
-        );
-    }
-}
-
 fn has_flag(args: &[String], f: &str) -> bool {
     args.iter().any(|a| a == f)
 }
@@ -73,14 +55,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
         def
     }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
 fn main() -> Result<()> {
     // USO:
     // rx_av <BIND:PORT> [--key-audio KA] [--key-video KV] [--secret S] [--suite SUITE] [--inspect]
@@ -94,18 +68,19 @@ fn main() -> Result<()> {
     let key_audio = read_flag_u64(&args, "--key-audio", 1);
     let key_video = read_flag_u64(&args, "--key-video", 2);
     let secret = read_flag_str(&args, "--secret", "SUPER_SECRET");
-    let suite = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))
-        .unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect = has_flag(&args, "--inspect");
 
     let mut r_audio = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r_audio.set_encryption_key(key_audio, secret.as_bytes())?;
     let mut r_video = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r_video.set_encryption_key(key_video, secret.as_bytes())?;
 
@@ -223,9 +198,9 @@ fn main() -> Result<()> {
 
             if inspect {
                 match sid {
-                    SID_VIDEO => inspect_packet("[RX][VID]", pkt),
-                    SID_AUDIO => inspect_packet("[RX][AUD]", pkt),
-                    _ => inspect_packet("[RX][UNK]", pkt),
+                    SID_VIDEO => inspect::print_compact("[RX][VID]", pkt, suite),
+                    SID_AUDIO => inspect::print_compact("[RX][AUD]", pkt, suite),
+                    _ => inspect::print_compact("[RX][UNK]", pkt, suite),
                 }
             }
 
@@ -6,10 +6,9 @@ use std::time::Duration;
 use image::{DynamicImage, GenericImageView};
 use minifb::{Key, Window, WindowOptions};
 
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 mod receiver;
+mod inspect;
 use receiver::Receiver;
 
 // --- u32 LE ---
@@ -33,14 +32,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
         args.get(i + 1).map(|s| s.as_str()).unwrap_or(def)
     } else { def }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
 // --- pixel packing helpers ---
 fn img_to_rgbx32(img: &DynamicImage) -> (usize, usize, Vec<u32>) {
     // 0x00RRGGBB (default)
@@ -65,18 +56,6 @@ fn img_to_bgrx32(img: &DynamicImage) -> (usize, usize, Vec<u32>) {
     (w as usize, h as usize, buf)
 }
 
-fn inspect_packet_compact(packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "[RX][SFRAME] kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(), h.counter(), hdr, ct, tag, packet.len()
-        );
-    }
-}
-
 // semplice checkerboard per verificare che minifb disegni
 fn make_checkerboard(w: usize, h: usize) -> Vec<u32> {
     let mut fb = vec![0u32; w * h];
@@ -101,16 +80,15 @@ fn main() -> Result<()> {
     let bind = &args[1];
     let key_id = read_flag_u64(&args, "--key-id", 2);
     let secret = read_flag_str(&args, "--secret", "SUPER_SECRET");
-    let suite = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))
-        .unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect = has_flag(&args, "--inspect");
     let use_bgr = has_flag(&args, "--bgr");
 
     // Receiver SFrame
     let mut r = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
-        frame_validation: None,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r.set_encryption_key(key_id, secret.as_bytes())?;
 
@@ -131,7 +109,7 @@ fn main() -> Result<()> {
             eprintln!("[rx_video] first: read payload err: {e}");
             continue;
         }
-        if inspect { inspect_packet_compact(&buf); }
+        if inspect { inspect::print_compact("[RX][SFRAME]", &buf, suite); }
         let plain = match r.decrypt_frame(&buf) {
             Ok(p) => p,
             Err(e) => { eprintln!("[rx_video] first: decrypt err: {e:?}"); continue; }
@@ -185,7 +163,7 @@ fn main() -> Result<()> {
             eprintln!("[rx_video] read payload err: {e}");
             break;
         }
-        if inspect { inspect_packet_compact(&buf); }
+        if inspect { inspect::print_compact("[RX][SFRAME]", &buf, suite); }
 
         let plain = match r.decrypt_frame(&buf) {
             Ok(p) => p,
@@ -5,10 +5,9 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 mod receiver;
+mod inspect;
 use receiver::Receiver;
 
 // ----------- utils -----------
@@ -31,26 +30,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
         args.get(i + 1).map(|s| s.as_str()).unwrap_or(def)
     } else { def }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
-fn inspect_packet_compact(packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "[RX][SFRAME] kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(), h.counter(), hdr, ct, tag, packet.len()
-        );
-    }
-}
-
 // ----------- HTTP MJPEG server -----------
 // Manteniamo una lista di client HTTP connessi a cui pushare i JPEG.
 type Clients = Arc<Mutex<Vec<TcpStream>>>;
@@ -127,15 +106,14 @@ fn main() -> Result<()> {
     let http_addr = read_flag_str(&args, "--http", "127.0.0.1:8080");
     let key_id = read_flag_u64(&args, "--key-id", 2);
     let secret = read_flag_str(&args, "--secret", "SUPER_SECRET");
-    let suite = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))
-        .unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect = has_flag(&args, "--inspect");
 
     // Receiver SFrame
     let mut r = Receiver::from(receiver::ReceiverOptions {
         cipher_suite: suite,
-        frame_validation: None,
         n_ratchet_bits: None,
+        ..Default::default()
     });
     r.set_encryption_key(key_id, secret.as_bytes())?;
 
@@ -168,7 +146,7 @@ fn main() -> Result<()> {
             eprintln!("[rx] first: read payload err: {e}");
             continue;
         }
-        if inspect { inspect_packet_compact(&buf); }
+        if inspect { inspect::print_compact("[RX][SFRAME]", &buf, suite); }
         let plain = match r.decrypt_frame(&buf) {
             Ok(p) => p,
             Err(e) => { eprintln!("[rx] first: decrypt err: {e:?}"); continue; }
@@ -192,7 +170,7 @@ fn main() -> Result<()> {
             eprintln!("[rx] read payload err: {e}");
             break;
         }
-        if inspect { inspect_packet_compact(&buf); }
+        if inspect { inspect::print_compact("[RX][SFRAME]", &buf, suite); }
         let plain = match r.decrypt_frame(&buf) {
             Ok(p) => p,
             Err(e) => { eprintln!("[rx] decrypt err: {e:?}"); continue; }
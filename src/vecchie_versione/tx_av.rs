@@ -17,10 +17,9 @@ use nokhwa::utils::{
 };
 use nokhwa::{query, Camera};
 
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 mod sender;
+mod inspect;
 use sender::Sender;
 
 /* ───────────── CLI helpers ───────────── */
@@ -42,14 +41,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
         def
     }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
 /* ───────────── framing ───────────── */
 
 const SID_VIDEO: u8 = 0x01;
@@ -65,23 +56,6 @@ fn send_frame(stream: &Arc<Mutex<TcpStream>>, sid: u8, pkt: &[u8]) -> std::io::R
 
 /* ───────────── inspect ───────────── */
 
-fn inspect_packet_compact(prefix: &str, packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "{prefix} kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(),
-            h.counter(),
-            hdr,
-            ct,
-            tag,
-            packet.len()
-        );
-    }
-}
-
 /* ───────────── OS/backend helpers ───────────── */
 
 #[inline]
@@ -151,8 +125,7 @@ fn main() -> Result<()> {
     let key_audio = read_flag_u32(&args, "--key-audio", 1) as u64;
     let key_video = read_flag_u32(&args, "--key-video", 2) as u64;
     let secret = read_flag_str(&args, "--secret", "SUPER_SECRET");
-    let suite = parse_suite(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))
-        .unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(read_flag_str(&args, "--suite", "aes-gcm256-sha512"))?;
     let inspect = has_flag(&args, "--inspect");
 
     let backend = default_backend();
@@ -300,7 +273,7 @@ fn main() -> Result<()> {
                 };
 
                 if inspect && (n % 30 == 0) {
-                    inspect_packet_compact("[TX][VID]", pkt);
+                    inspect::print_compact("[TX][VID]", pkt, suite);
                 }
 
                 if let Err(e) = send_frame(&stream, SID_VIDEO, pkt) {
@@ -12,10 +12,9 @@ use nokhwa::utils::{
 };
 use nokhwa::{query, Camera};
 
-use sframe::header::SframeHeader;
-use sframe::CipherSuite;
 
 mod sender;
+mod inspect;
 use sender::Sender;
 
 // ---- util TCP prefix u32 LE ----
@@ -37,27 +36,6 @@ fn read_flag_str<'a>(args: &'a [String], name: &str, def: &'a str) -> &'a str {
         args.get(i + 1).map(|s| s.as_str()).unwrap_or(def)
     } else { def }
 }
-fn parse_suite(s: &str) -> Option<CipherSuite> {
-    match s.to_ascii_lowercase().as_str() {
-        "aes-gcm128-sha256" | "aesgcm128" | "128" => Some(CipherSuite::AesGcm128Sha256),
-        "aes-gcm256-sha512" | "aesgcm256" | "256" => Some(CipherSuite::AesGcm256Sha512),
-        _ => None,
-    }
-}
-
-// ---- inspect helper (compatto) ----
-fn inspect_packet_compact(packet: &[u8]) {
-    if let Ok(h) = SframeHeader::deserialize(packet) {
-        let hdr = h.len();
-        let body = packet.len().saturating_sub(hdr);
-        let (ct, tag) = if body >= 16 { (body - 16, 16) } else { (body, 0) };
-        println!(
-            "[TX][SFRAME] kid={} ctr={} | aad={}B ct={}B tag={}B total={}B",
-            h.key_id(), h.counter(), hdr, ct, tag, packet.len()
-        );
-    }
-}
-
 // ---- scelta formato migliore (prefer MJPEG, poi YUYV) ----
 fn pick_best_format(
     formats: &[CameraFormat],
@@ -144,7 +122,7 @@ fn main() -> Result<()> {
     }
 
     // SFrame sender
-    let suite = parse_suite(suite).unwrap_or(CipherSuite::AesGcm256Sha512);
+    let suite = inspect::parse_suite_name(suite)?;
     let mut s = Sender::with_cipher_suite(key_id, suite);
     s.set_encryption_key(secret.as_bytes())?;
 
@@ -199,7 +177,7 @@ fn main() -> Result<()> {
         enc.encode(&img, use_w, use_h, ColorType::Rgb8)?;
 
         let pkt = s.encrypt_frame(&jpeg_buf)?;
-        if inspect && (n % 30 == 0) { inspect_packet_compact(pkt); }
+        if inspect && (n % 30 == 0) { inspect::print_compact("[TX][SFRAME]", pkt, suite); }
         write_u32_le(&mut stream, u32::try_from(pkt.len())?)?;
         stream.write_all(pkt)?;
 
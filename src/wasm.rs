@@ -0,0 +1,581 @@
+// src/wasm.rs
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen::prelude::*;
+use sframe::CipherSuite;
+use serde::Serialize;
+
+use crate::codec::Codec;
+use crate::inspect::{self, PacketInfo, UnknownSuite};
+use crate::receiver::{DEFAULT_REPLAY_WINDOW, Receiver, ReceiverError, ReceiverOptions};
+use crate::sender::{Sender, SenderOptions};
+
+// ------------------------------------------------------------
+// STRUTTURA DI DEBUG PER HEADER SFRAME (serializzabile verso JS)
+// ------------------------------------------------------------
+#[derive(Serialize, Clone)]
+pub struct SframeHeaderDebug {
+    pub suite: String,
+    pub key_len: usize,
+    pub nonce_len: usize,
+    pub kid: u64,
+    pub ctr: u64,
+    pub header_len: usize,
+    pub aad_len: usize,
+    pub ct_len: usize,
+    pub tag_len: usize,
+    pub total_len: usize,
+    pub header_hex: String,
+}
+
+static mut LAST_TX_HDR: Option<SframeHeaderDebug> = None;
+static mut LAST_RX_HDR: Option<SframeHeaderDebug> = None;
+
+// ------------------------------------------------------------
+// FUNZIONI DI SUPPORTO (helpers)
+// ------------------------------------------------------------
+
+// Senza nome si usa AES-GCM-256; un nome sconosciuto è un errore.
+pub(crate) fn parse_suite(s: Option<String>) -> Result<CipherSuite, UnknownSuite> {
+    match s.as_deref() {
+        Some(name) => inspect::parse_suite_name(name),
+        None => Ok(CipherSuite::AesGcm256Sha512),
+    }
+}
+
+/// Nomi brevi delle cipher suite SFrame accettate dai costruttori (`suite`).
+#[wasm_bindgen]
+pub fn sframe_supported_suites() -> Vec<String> {
+    inspect::suite_names().into_iter().map(String::from).collect()
+}
+
+// Errori di decifratura come `Error` con `code` stabile (vedi mls_error.rs):
+// la webapp scarta in silenzio i REPLAY senza fare parsing del testo.
+impl From<ReceiverError> for JsValue {
+    fn from(err: ReceiverError) -> Self {
+        let js_err = js_sys::Error::new(&err.to_string());
+        js_err.set_name("ReceiverError");
+        // Reflect::set su un Error appena creato non può fallire
+        let _ = js_sys::Reflect::set(&js_err, &"code".into(), &err.code().into());
+        js_err.into()
+    }
+}
+
+fn parse_codec(name: &str) -> Result<Codec, JsValue> {
+    Codec::from_name(name)
+        .ok_or_else(|| JsValue::from_str(&format!("Codec sconosciuto: {name} (vp8, h264, opus)")))
+}
+
+// Prefisso in chiaro di `frame` (o di un pacchetto cifrato parzialmente)
+fn clear_prefix_len(codec: Codec, frame: &[u8]) -> Result<usize, JsValue> {
+    codec
+        .clear_prefix_len(frame)
+        .ok_or_else(|| JsValue::from_str(&format!("Frame {codec:?} non valido ({}B)", frame.len())))
+}
+
+// Verifica che `dst` possa contenere `len` byte. I metodi `_into` la
+// chiamano prima di cifrare: un errore dopo avrebbe già consumato il
+// counter (TX) o aggiornato la finestra anti-replay (RX).
+fn ensure_capacity(dst: &js_sys::Uint8Array, len: usize) -> Result<(), JsValue> {
+    if (dst.length() as usize) < len {
+        return Err(JsValue::from_str(&format!(
+            "Buffer di destinazione troppo piccolo: {}B, servono {len}B",
+            dst.length()
+        )));
+    }
+    Ok(())
+}
+
+// Copia `src` in testa a `dst` (memoria JS) e restituisce i byte scritti
+fn write_into(dst: &js_sys::Uint8Array, src: &[u8]) -> Result<u32, JsValue> {
+    ensure_capacity(dst, src.len())?;
+    let len = src.len() as u32;
+    dst.subarray(0, len).copy_from(src);
+    Ok(len)
+}
+
+// Con `n_ratchet_bits` il KID viene letto come [generation || ratchet step]
+// (RFC 9605 5.1) e sender/receiver possono avanzare la chiave senza nuovo segreto.
+pub(crate) fn new_sender(kid: u64, suite: CipherSuite, n_ratchet_bits: Option<u8>) -> Sender {
+    SenderOptions {
+        key_id: kid,
+        cipher_suite: suite,
+        n_ratchet_bits,
+        ..Default::default()
+    }
+    .into()
+}
+
+// `replay_window` dai costruttori JS: assente = DEFAULT_REPLAY_WINDOW,
+// 0 = anti-replay disattivato; oltre MAX_REPLAY_WINDOW viene limitato.
+pub(crate) fn new_receiver(suite: CipherSuite, n_ratchet_bits: Option<u8>, replay_window: Option<u32>) -> Receiver {
+    ReceiverOptions {
+        cipher_suite: suite,
+        n_ratchet_bits,
+        replay_window: match replay_window {
+            None => Some(DEFAULT_REPLAY_WINDOW),
+            Some(0) => None,
+            Some(size) => Some(size.into()),
+        },
+    }
+    .into()
+}
+
+pub(crate) fn capture_header(dir_tx: bool, suite: CipherSuite, packet: &[u8]) {
+    let Ok(info) = PacketInfo::parse(packet, suite) else {
+        return;
+    };
+
+    let dbg = SframeHeaderDebug {
+        suite: info.suite.to_string(),
+        key_len: info.params.key_len,
+        nonce_len: info.params.nonce_len,
+        kid: info.kid,
+        ctr: info.ctr,
+        header_len: info.header_len,
+        aad_len: info.header_len,
+        ct_len: info.ct_len,
+        tag_len: info.tag_len(),
+        total_len: info.total_len(),
+        header_hex: hex::encode(info.header()),
+    };
+
+    unsafe {
+        if dir_tx {
+            LAST_TX_HDR = Some(dbg);
+        } else {
+            LAST_RX_HDR = Some(dbg);
+        }
+    }
+}
+
+// ------------------------------------------------------------
+// EXPORT WASM: recupero ultimo header TX/RX
+// ------------------------------------------------------------
+
+#[wasm_bindgen]
+pub fn sframe_last_tx_header() -> JsValue {
+    unsafe {
+        if let Some(ref h) = LAST_TX_HDR {
+            serde_wasm_bindgen::to_value(h).unwrap()
+        } else {
+            JsValue::UNDEFINED
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn sframe_last_rx_header() -> JsValue {
+    unsafe {
+        if let Some(ref h) = LAST_RX_HDR {
+            serde_wasm_bindgen::to_value(h).unwrap()
+        } else {
+            JsValue::UNDEFINED
+        }
+    }
+}
+
+// ------------------------------------------------------------
+// PEER WASM ESPORTATO VERSO JAVASCRIPT
+// ------------------------------------------------------------
+
+#[wasm_bindgen]
+pub struct WasmPeer {
+    s_audio: Sender,
+    s_video: Sender,
+    r_audio: Receiver,
+    r_video: Receiver,
+}
+
+#[wasm_bindgen]
+impl WasmPeer {
+    // --------------------------------------------------------
+    // COSTRUTTORE BASE
+    // --------------------------------------------------------
+    // FIX: Cambiato u32 in u64 per evitare overflow dei KID calcolati in JS
+    // `replay_window` vale per tutti i costruttori con un Receiver (anche
+    // WasmReceiver e WasmSession): vedi `new_receiver`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key_audio: u64,
+        key_video: u64,
+        suite: Option<String>,
+        secret: Vec<u8>,
+        n_ratchet_bits: Option<u8>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmPeer, JsValue> {
+        let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        // Sender (TX)
+        let mut s_audio = new_sender(key_audio, suite, n_ratchet_bits);
+        s_audio
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let mut s_video = new_sender(key_video, suite, n_ratchet_bits);
+        s_video
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        // Receiver (RX)
+        let mut r_audio = new_receiver(suite, n_ratchet_bits, replay_window);
+        r_audio
+            .set_encryption_key(key_audio, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let mut r_video = new_receiver(suite, n_ratchet_bits, replay_window);
+        r_video
+            .set_encryption_key(key_video, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        Ok(Self {
+            s_audio,
+            s_video,
+            r_audio,
+            r_video,
+        })
+    }
+
+    // --------------------------------------------------------
+    // COSTRUTTORE FULL-DUPLEX
+    // --------------------------------------------------------
+    // NOTA: per le chiamate di gruppo usare WasmSession (session.rs),
+    // che gestisce N mittenti remoti senza KID TX fittizi.
+    // FIX: Cambiato u32 in u64
+    // Argomenti posizionali come gli altri costruttori esposti a JS
+    #[allow(clippy::too_many_arguments)]
+    #[wasm_bindgen(js_name = "new_full_duplex")]
+    pub fn new_full_duplex(
+        tx_audio: u64,
+        tx_video: u64,
+        rx_audio: u64,
+        rx_video: u64,
+        suite: Option<String>,
+        secret: Vec<u8>,
+        n_ratchet_bits: Option<u8>,
+        replay_window: Option<u32>,
+    ) -> Result<WasmPeer, JsValue> {
+        let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        // Sender (TX) con KID specifici
+        let mut s_audio = new_sender(tx_audio, suite, n_ratchet_bits);
+        s_audio
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let mut s_video = new_sender(tx_video, suite, n_ratchet_bits);
+        s_video
+            .set_encryption_key(&secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        // Receiver (RX) con KID specifici
+        let mut r_audio = new_receiver(suite, n_ratchet_bits, replay_window);
+        r_audio
+            .set_encryption_key(rx_audio, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let mut r_video = new_receiver(suite, n_ratchet_bits, replay_window);
+        r_video
+            .set_encryption_key(rx_video, &secret)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        Ok(Self {
+            s_audio,
+            s_video,
+            r_audio,
+            r_video,
+        })
+    }
+
+    // --------------------------------------------------------
+    // RATCHET TX (richiede n_ratchet_bits nel costruttore)
+    // --------------------------------------------------------
+    // Restituiscono il nuovo KID da annunciare ai receiver.
+
+    #[wasm_bindgen]
+    pub fn ratchet_audio(&mut self) -> Result<u64, JsValue> {
+        self.s_audio
+            .ratchet()
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    #[wasm_bindgen]
+    pub fn ratchet_video(&mut self) -> Result<u64, JsValue> {
+        self.s_video
+            .ratchet()
+            .map_err(|e| JsValue::from_str(&format!("{e}")))
+    }
+
+    // --------------------------------------------------------
+    // CIFRATURA (ENCRYPT) - AUDIO / VIDEO
+    // --------------------------------------------------------
+
+    #[wasm_bindgen]
+    pub fn encrypt_audio(&mut self, input: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let out = self
+            .s_audio
+            .encrypt_frame(&input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let packet = out.to_vec();
+        capture_header(true, self.s_audio.cipher_suite(), &packet);
+
+        Ok(packet)
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_video(&mut self, input: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let out = self
+            .s_video
+            .encrypt_frame(&input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let packet = out.to_vec();
+        capture_header(true, self.s_video.cipher_suite(), &packet);
+
+        Ok(packet)
+    }
+
+    // --------------------------------------------------------
+    // DECIFRATURA (DECRYPT) - AUDIO / VIDEO
+    // --------------------------------------------------------
+
+    #[wasm_bindgen]
+    pub fn decrypt_audio(&mut self, packet: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.r_audio.cipher_suite(), &packet);
+
+        self.r_audio
+            .decrypt_frame(&packet)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_video(&mut self, packet: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.r_video.cipher_suite(), &packet);
+
+        self.r_video
+            .decrypt_frame(&packet)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+
+    // --------------------------------------------------------
+    // METADATI AUTENTICATI (AAD) - AUDIO / VIDEO
+    // --------------------------------------------------------
+    // `meta` (es. campi RTP o descrittore del codec) viaggia in chiaro
+    // fuori dal pacchetto ma è coperto dal tag: chi lo scambia tra due
+    // stream fa fallire la decifratura. Il receiver deve passare gli
+    // stessi byte usati dal sender.
+
+    #[wasm_bindgen]
+    pub fn encrypt_audio_with_meta(&mut self, input: Vec<u8>, meta: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let out = self
+            .s_audio
+            .encrypt_frame_with_meta(&input, &meta)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let packet = out.to_vec();
+        capture_header(true, self.s_audio.cipher_suite(), &packet);
+
+        Ok(packet)
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_video_with_meta(&mut self, input: Vec<u8>, meta: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        let out = self
+            .s_video
+            .encrypt_frame_with_meta(&input, &meta)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        let packet = out.to_vec();
+        capture_header(true, self.s_video.cipher_suite(), &packet);
+
+        Ok(packet)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_audio_with_meta(&mut self, packet: Vec<u8>, meta: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.r_audio.cipher_suite(), &packet);
+
+        self.r_audio
+            .decrypt_frame_with_meta(&packet, &meta)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_video_with_meta(&mut self, packet: Vec<u8>, meta: Vec<u8>) -> Result<Vec<u8>, JsValue> {
+        capture_header(false, self.r_video.cipher_suite(), &packet);
+
+        self.r_video
+            .decrypt_frame_with_meta(&packet, &meta)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+
+    // --------------------------------------------------------
+    // CIFRATURA SU BUFFER JS (SENZA ALLOCAZIONI PER FRAME)
+    // --------------------------------------------------------
+    // Il risultato viene scritto direttamente in `out` (Uint8Array o vista
+    // su un ArrayBuffer riutilizzato dal chiamante) invece di restituire un
+    // nuovo Vec: restituiscono i byte scritti. `out` deve avere almeno
+    // `input.length + sframe_max_overhead()` byte per la cifratura e
+    // `packet.length` per la decifratura: con meno spazio falliscono prima
+    // di toccare counter e finestra anti-replay.
+
+    #[wasm_bindgen]
+    pub fn encrypt_audio_into(&mut self, input: &[u8], out: &js_sys::Uint8Array) -> Result<u32, JsValue> {
+        ensure_capacity(out, input.len() + sframe_max_overhead() as usize)?;
+        let suite = self.s_audio.cipher_suite();
+        let packet = self
+            .s_audio
+            .encrypt_frame(input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        capture_header(true, suite, packet);
+        write_into(out, packet)
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_video_into(&mut self, input: &[u8], out: &js_sys::Uint8Array) -> Result<u32, JsValue> {
+        ensure_capacity(out, input.len() + sframe_max_overhead() as usize)?;
+        let suite = self.s_video.cipher_suite();
+        let packet = self
+            .s_video
+            .encrypt_frame(input)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        capture_header(true, suite, packet);
+        write_into(out, packet)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_audio_into(&mut self, packet: &[u8], out: &js_sys::Uint8Array) -> Result<u32, JsValue> {
+        ensure_capacity(out, packet.len())?;
+        capture_header(false, self.r_audio.cipher_suite(), packet);
+
+        let frame = self
+            .r_audio
+            .decrypt_frame(packet)
+            .map_err(JsValue::from)?;
+        write_into(out, frame)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_video_into(&mut self, packet: &[u8], out: &js_sys::Uint8Array) -> Result<u32, JsValue> {
+        ensure_capacity(out, packet.len())?;
+        capture_header(false, self.r_video.cipher_suite(), packet);
+
+        let frame = self
+            .r_video
+            .decrypt_frame(packet)
+            .map_err(JsValue::from)?;
+        write_into(out, frame)
+    }
+
+    // --------------------------------------------------------
+    // CIFRATURA PARZIALE PER CODEC (ENCODED TRANSFORM)
+    // --------------------------------------------------------
+    // `codec`: "vp8" | "h264" (video), "opus" (audio). Il prefisso del
+    // codec (descrittore VP8, header NAL H.264, TOC Opus) resta in chiaro
+    // e autenticato, così le SFU vedono keyframe e layer di simulcast.
+    // Per H.264 il resto del pacchetto è protetto da emulation prevention
+    // (vedi `Codec::escape`) e il frame Annex B resta divisibile in NAL.
+
+    #[wasm_bindgen]
+    pub fn encrypt_audio_partial(&mut self, input: Vec<u8>, codec: &str) -> Result<Vec<u8>, JsValue> {
+        let codec = parse_codec(codec)?;
+        let clear_len = clear_prefix_len(codec, &input)?;
+        let suite = self.s_audio.cipher_suite();
+        let packet = self
+            .s_audio
+            .encrypt_frame_partial(&input, clear_len)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        capture_header(true, suite, &packet[clear_len..]);
+
+        Ok(codec.escape(packet, clear_len))
+    }
+
+    #[wasm_bindgen]
+    pub fn encrypt_video_partial(&mut self, input: Vec<u8>, codec: &str) -> Result<Vec<u8>, JsValue> {
+        let codec = parse_codec(codec)?;
+        let clear_len = clear_prefix_len(codec, &input)?;
+        let suite = self.s_video.cipher_suite();
+        let packet = self
+            .s_video
+            .encrypt_frame_partial(&input, clear_len)
+            .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+        capture_header(true, suite, &packet[clear_len..]);
+
+        Ok(codec.escape(packet, clear_len))
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_audio_partial(&mut self, packet: Vec<u8>, codec: &str) -> Result<Vec<u8>, JsValue> {
+        let codec = parse_codec(codec)?;
+        let clear_len = clear_prefix_len(codec, &packet)?;
+        let packet = codec
+            .unescape(&packet, clear_len)
+            .ok_or_else(|| JsValue::from_str(&format!("Pacchetto {codec:?} non valido ({}B)", packet.len())))?;
+        capture_header(false, self.r_audio.cipher_suite(), &packet[clear_len..]);
+
+        self.r_audio
+            .decrypt_frame_partial(&packet, clear_len)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_video_partial(&mut self, packet: Vec<u8>, codec: &str) -> Result<Vec<u8>, JsValue> {
+        let codec = parse_codec(codec)?;
+        let clear_len = clear_prefix_len(codec, &packet)?;
+        let packet = codec
+            .unescape(&packet, clear_len)
+            .ok_or_else(|| JsValue::from_str(&format!("Pacchetto {codec:?} non valido ({}B)", packet.len())))?;
+        capture_header(false, self.r_video.cipher_suite(), &packet[clear_len..]);
+
+        self.r_video
+            .decrypt_frame_partial(&packet, clear_len)
+            .map(|b| b.to_vec())
+            .map_err(JsValue::from)
+    }
+}
+
+/// Byte massimi che la cifratura aggiunge al frame: header SFrame
+/// (config + KID e CTR da 8 byte) più il tag più lungo (16 byte).
+/// Serve a dimensionare i buffer passati a `encrypt_*_into`.
+#[wasm_bindgen]
+pub fn sframe_max_overhead() -> u32 {
+    1 + 8 + 8 + 16
+}
+
+// ------------------------------------------------------------
+// ISPEZIONE MANUALE DI UN PACCHETTO SFRAME (DEBUG)
+// ------------------------------------------------------------
+
+/// Descrive un pacchetto SFrame. `format`: "json", "hex" (dump completo)
+/// o assente per la riga compatta. `suite` è quella negoziata con il peer:
+/// determina la lunghezza del tag e quindi del ciphertext.
+#[wasm_bindgen]
+pub fn sframe_inspect(
+    packet: &[u8],
+    format: Option<String>,
+    suite: Option<String>,
+) -> Result<String, JsValue> {
+    let suite = parse_suite(suite).map_err(|e| JsValue::from_str(&format!("{e}")))?;
+    let info = PacketInfo::parse(packet, suite)
+        .map_err(|e| JsValue::from_str(&format!("{e}")))?;
+
+    Ok(match format.as_deref() {
+        Some("json") => info.to_json(),
+        Some("hex") => info.hex_dump(),
+        _ => format!(
+            "SFrame[{}, {info}, header_hex={}]",
+            info.suite,
+            hex::encode(info.header())
+        ),
+    })
+}
@@ -12,9 +12,13 @@ import {
   mlsJoin,
   mlsFetchRoster,
   mlsResync, // ← resync epoch / welcome
+  mlsLeave,
+  mlsCreateRoom,
+  mlsCloseRoom,
   deriveTxKey,
   deriveRxKey,
   computeKid,
+  mlsSframeGroup,
   attachIndexToIdentity,
   parseIdentityWithIndex,
 } from "./mls_sframe_session.js";
@@ -24,6 +28,8 @@ import {
   initSFrame,
   createTxPeer,
   createRxPeer,
+  createMlsTxPeer,
+  createMlsRxPeer,
 } from "./sframe_layer.js";
 
 // 🔹 FUNZIONI WASM PER GLI HEADER SFRAME
@@ -51,6 +57,10 @@ let mlsInfo = null;
 // Identity “base” (senza #index MLS)
 let myIdentity = null;
 
+// ID della stanza sul Delivery Service MLS (stringa, parametro "mls" dell'URL).
+// La stanza Janus resta numerica in els.roomId.
+let mlsRoomId = null;
+
 // Ref mutabile al TX peer SFrame: { peer: WasmPeer } oppure null
 let txPeerRef = null;
 
@@ -60,6 +70,9 @@ const subscribers = new Map();
 // throttling error per RX decrypt (per non spammare log)
 let lastRxDecryptErrorTs = 0;
 
+// `code` degli errori di decifratura (ReceiverError) da non loggare
+const RX_SILENT_ERRORS = new Set(["MISSING_KEY", "REPLAY", "STALE_RATCHET_STEP"]);
+
 // stato di "key sync in corso"
 let keySyncInProgress = false;
 
@@ -217,8 +230,8 @@ function startMlsHeartbeat() {
     if (mlsHeartbeatTimer) return;
     mlsHeartbeatTimer = setInterval(() => {
         if (!mlsInfo || !myIdentity) return;
-        const room = Number(els.roomId.value);
-        if (!Number.isFinite(room) || room <= 0) return;
+        const room = mlsRoomId;
+        if (!room) return;
 
         // 🔴 FIX LOOP: Se c'è già un sync in corso, salta il turno
         if (isResyncing) return;
@@ -270,8 +283,8 @@ function renderRemotePeers(roster, identityMe) {
 async function refreshRosterUI() {
   if (!myIdentity) return;
 
-  const room = Number(els.roomId.value);
-  if (!Number.isFinite(room) || room <= 0) return;
+  const room = mlsRoomId;
+  if (!room) return;
 
   try {
     const r = await mlsFetchRoster(room);
@@ -289,8 +302,8 @@ async function refreshRosterUI() {
 async function maybeResyncMls(reason) {
   if (!mlsInfo || !myIdentity) return;
 
-  const room = Number(els.roomId.value);
-  if (!Number.isFinite(room) || room <= 0) return;
+  const room = mlsRoomId;
+  if (!room) return;
 
   // 🔴 FIX LOOP: Evita richieste multiple simultanee
   if (isResyncing) return;
@@ -347,12 +360,7 @@ async function rekeyAllPeers() {
   // TX
   if (pcPub && localStream && txPeerRef) {
     try {
-      const selfIndex = mlsInfo.sender_index;
-      const txKey = await deriveTxKey(mlsInfo.master_secret, selfIndex);
-      const kidAudio = computeKid(mlsInfo.epoch, room, selfIndex);
-      const kidVideo = kidAudio + 1;
-
-      txPeerRef.peer = createTxPeer(kidAudio, kidVideo, txKey);
+      txPeerRef.peer = await buildTxPeer(room);
     } catch (e) {
       Output.error("TX rekey failed", e);
     }
@@ -364,6 +372,14 @@ async function rekeyAllPeers() {
       const { senderIndex: remoteIndex } = parseIdentityWithIndex(sub.display || "");
       if (remoteIndex == null) continue;
 
+      const mls = mlsSframeGroup();
+      if (mls) {
+        sub.rxPeerRef = sub.rxPeerRef || {};
+        sub.rxPeerRef.peer = createMlsRxPeer(mls.client, mls.groupId, remoteIndex);
+        askForKeyframes(feedId, sub);
+        continue;
+      }
+
       const rxKey = await deriveRxKey(mlsInfo.master_secret, remoteIndex);
       
       // 🔴=============================================================
@@ -384,36 +400,67 @@ async function rekeyAllPeers() {
 
       sub.rxPeerRef = sub.rxPeerRef || {};
       sub.rxPeerRef.peer = createRxPeer(99, 98, kidAudio, kidVideo, chiaveFinale);
-
-      // Fix per lo sblocco del video (richieste Keyframe multiple)
-      const askForKeyframeRekey = () => {
-        if (sub.handleId && sessionId) {
-          sendJanus({
-            janus: "message",
-            transaction: makeTxId(`force-kf-rekey-${feedId}`),
-            session_id: sessionId,
-            handle_id: sub.handleId,
-            body: { request: "configure", keyframe: true }
-          });
-        }
-      };
-
-      setTimeout(askForKeyframeRekey, 200);
-      setTimeout(askForKeyframeRekey, 1000);
-      setTimeout(askForKeyframeRekey, 2500);
+      askForKeyframes(feedId, sub);
 
     } catch (e) {
       Output.error("RX rekey failed", { feedId, error: e });
     }
   }
 }
+
+// Fix per lo sblocco del video dopo un rekey (richieste Keyframe multiple)
+function askForKeyframes(feedId, sub) {
+  const askForKeyframeRekey = () => {
+    if (sub.handleId && sessionId) {
+      sendJanus({
+        janus: "message",
+        transaction: makeTxId(`force-kf-rekey-${feedId}`),
+        session_id: sessionId,
+        handle_id: sub.handleId,
+        body: { request: "configure", keyframe: true }
+      });
+    }
+  };
+
+  setTimeout(askForKeyframeRekey, 200);
+  setTimeout(askForKeyframeRekey, 1000);
+  setTimeout(askForKeyframeRekey, 2500);
+}
+
+// Chiavi SFrame: dall'exporter del gruppo MLS quando il Welcome OpenMLS è
+// stato applicato, altrimenti dal segreto distribuito via ECDH
+async function buildTxPeer(room) {
+  const mls = mlsSframeGroup();
+  if (mls) return createMlsTxPeer(mls.client, mls.groupId);
+
+  const selfIndex = mlsInfo.sender_index;
+  const txKey = await deriveTxKey(mlsInfo.master_secret, selfIndex);
+  const kidAudio = computeKid(mlsInfo.epoch, room, selfIndex);
+  return createTxPeer(kidAudio, kidAudio + 1, txKey);
+}
+
+async function buildRxPeer(room, remoteIndex) {
+  const mls = mlsSframeGroup();
+  if (mls) return createMlsRxPeer(mls.client, mls.groupId, remoteIndex);
+
+  const rxKey = await deriveRxKey(mlsInfo.master_secret, remoteIndex);
+  const kidAudio = computeKid(mlsInfo.epoch, room, remoteIndex);
+  return createRxPeer(99, 98, kidAudio, kidAudio + 1, rxKey);
+}
+
+// Indice pubblicato nel display Janus: con MLS è la nostra foglia nel gruppo
+function publishedIndex() {
+  const mls = mlsSframeGroup();
+  return mls ? mls.leafIndex : mlsInfo.sender_index;
+}
 // ─────────────────────────────────────────────────────────────
 // UI: Room + invite link
 // ─────────────────────────────────────────────────────────────
 
-function updateRoomUI(roomId) {
+function updateRoomUI(roomId, mlsRoom) {
   const room = Number(roomId);
   if (!Number.isFinite(room) || room <= 0) return;
+  mlsRoomId = String(mlsRoom);
 
   if (els.roomId) {
     els.roomId.value = String(room);
@@ -426,6 +473,7 @@ function updateRoomUI(roomId) {
   if (inviteInput) {
     const url = new URL(window.location.href);
     url.searchParams.set("room", String(room));
+    url.searchParams.set("mls", mlsRoomId);
     inviteInput.value = url.toString();
   }
 
@@ -445,6 +493,7 @@ function updateRoomUI(roomId) {
 async function setupRoomOnLoad() {
   const url = new URL(window.location.href);
   let roomFromUrl = url.searchParams.get("room");
+  const mlsFromUrl = url.searchParams.get("mls");
 
   if (roomFromUrl) {
     const room = Number(roomFromUrl);
@@ -452,8 +501,12 @@ async function setupRoomOnLoad() {
       Output.error("Invalid room in URL, cannot join");
       return;
     }
-    Output.ui("Room from URL", { room });
-    updateRoomUI(room);
+    if (!mlsFromUrl) {
+      Output.error("No MLS room in URL, cannot join");
+      return;
+    }
+    Output.ui("Room from URL", { room, mls: mlsFromUrl });
+    updateRoomUI(room, mlsFromUrl);
     return;
   }
 
@@ -467,12 +520,14 @@ async function setupRoomOnLoad() {
     }
 
     const room = Number(json.roomId);
+    const mlsRoom = await mlsCreateRoom();
 
     url.searchParams.set("room", String(room));
+    url.searchParams.set("mls", mlsRoom);
     window.history.replaceState(null, "", url.toString());
 
-    Output.ui("New room created", { room });
-    updateRoomUI(room);
+    Output.ui("New room created", { room, mls: mlsRoom });
+    updateRoomUI(room, mlsRoom);
   } catch (e) {
     Output.error("Cannot create room", e);
   }
@@ -712,7 +767,7 @@ function attachPublisherHandle() {
 async function joinAsPublisher() {
   const room = Number(els.roomId.value);
 
-  if (!Number.isFinite(room) || room <= 0) {
+  if (!Number.isFinite(room) || room <= 0 || !mlsRoomId) {
     Output.error("No valid roomId, cannot join");
     return;
   }
@@ -724,7 +779,7 @@ async function joinAsPublisher() {
     await initSFrame(); 
 
     if (!mlsInfo) {
-      mlsInfo = await mlsJoin(myIdentity, room);
+      mlsInfo = await mlsJoin(myIdentity, mlsRoomId);
       Output.mls("MLS JOIN OK", Object.assign({}, mlsInfo, { master_secret: mlsInfo.master_secret ? "HIDDEN" : null }));
       renderRemotePeers(mlsInfo.roster, myIdentity);
     }
@@ -732,13 +787,13 @@ async function joinAsPublisher() {
     // 🔴 LA SALA D'ATTESA! Se non abbiamo il segreto, blocchiamo tutto finché non arriva!
     if (!mlsInfo.master_secret) {
         Output.ui("In attesa dell'invito crittografato dal creatore della stanza...");
-        await waitForWelcome(room);
+        await waitForWelcome(mlsRoomId);
     }
 
     // Avvia l'heartbeat per gestire futuri utenti o aggiornamenti
     startMlsHeartbeat();
 
-    const fullIdentity = attachIndexToIdentity(myIdentity, mlsInfo.sender_index);
+    const fullIdentity = attachIndexToIdentity(myIdentity, publishedIndex());
 
     Output.ui("Join as publisher", { room, identity: fullIdentity });
 
@@ -761,13 +816,7 @@ async function startPublishing() {
     const room = Number(els.roomId.value);
 
     // Se arriviamo qui, SIAMO SICURI di avere il master_secret
-    const selfIndex = mlsInfo.sender_index;
-    const txKey = await deriveTxKey(mlsInfo.master_secret, selfIndex);
-
-    const kidAudio = computeKid(mlsInfo.epoch, room, selfIndex);
-    const kidVideo = kidAudio + 1;
-
-    txPeerRef = { peer: createTxPeer(kidAudio, kidVideo, txKey) };
+    txPeerRef = { peer: await buildTxPeer(room) };
 
     pcPub = new RTCPeerConnection({ iceServers: [] });
 
@@ -1047,11 +1096,7 @@ async function handleSubscriberJsep(feedId, sub, jsep) {
       return;
     }
 
-    const rxKey = await deriveRxKey(mlsInfo.master_secret, remoteIndex);
-    const kidAudio = computeKid(mlsInfo.epoch, room, remoteIndex);
-    const kidVideo = kidAudio + 1;
-
-    sub.rxPeerRef = { peer: createRxPeer(99, 98, kidAudio, kidVideo, rxKey) };
+    sub.rxPeerRef = { peer: await buildRxPeer(room, remoteIndex) };
 
     const answer = await sub.pc.createAnswer();
     await sub.pc.setLocalDescription(answer);
@@ -1160,8 +1205,9 @@ function attachReceiverTransform(receiver, kind, sub) {
           }
 
         } catch (e) {
-          const errMsg = e.toString();
-          if (errMsg.includes("DecryptionKey") || (e.message && e.message.includes("DecryptionKey"))) {
+          // Chiave non ancora arrivata, frame ripetuti o di uno step già
+          // superato: si scartano senza log
+          if (RX_SILENT_ERRORS.has(e.code)) {
               return;
           }
           const now = Date.now();
@@ -1236,6 +1282,16 @@ function connectAndJoinRoom() {
 }
 
 function hangup() {
+  // Uscita firmata dal Delivery Service (prima che cleanup azzeri lo stato);
+  // se la stanza resta vuota e l'abbiamo creata noi, la chiudiamo
+  if (mlsInfo && myIdentity && mlsRoomId) {
+    const room = mlsRoomId;
+    mlsLeave(myIdentity, room)
+      .then(() => mlsFetchRoster(room))
+      .then(r => r.roster.some(m => m.status === "active") ? false : mlsCloseRoom(room))
+      .catch(e => Output.error("MLS leave failed", e));
+  }
+
   try {
     if (pluginHandlePub) {
       sendJanus({
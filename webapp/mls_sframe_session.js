@@ -5,11 +5,31 @@ import { Output } from "./output.js";
 const SERVER_JOIN_PATH = "/mls/join";
 const SERVER_ROSTER_PATH = "/mls/roster";
 const SERVER_WELCOME_PATH = "/mls/welcome";
+const SERVER_LEAVE_PATH = "/mls/leave";
+const SERVER_CREATE_PATH = "/mls/create";
+const SERVER_CLOSE_PATH = "/mls/close";
+const SERVER_COMMIT_PATH = "/mls/commit";
+const SERVER_MESSAGES_PATH = "/mls/messages";
+const SERVER_ACK_PATH = "/mls/messages/ack";
+
+// Token per chiudere le stanze MLS create da questa scheda
+const OWNER_TOKEN_PREFIX = "sframe-mls-owner:";
+
+// Stato del client MLS cifrato in localStorage, chiave in sessionStorage:
+// un reload della scheda ritrova la stessa chiave di firma e il server
+// accetta di nuovo il join invece di rispondere 403.
+const STATE_SLOT_PREFIX = "sframe-mls-state:";
+const STATE_KEY_PREFIX = "sframe-mls-state-key:";
+// GroupId MLS di ogni stanza, accanto allo stato: un client può avere più gruppi
+const GROUP_SLOT_PREFIX = "sframe-mls-group:";
 
 let mlsClient = null;
+let mlsIdentity = null; // identity con cui è stato creato (o ripristinato) mlsClient
+let mlsGroupId = null; // GroupId MLS della stanza corrente (Uint8Array)
 let ecdhKeyPair = null;
 let myPublicKeyBase64 = null;
 let myMasterSecret = null;
+let lastMessageSeq = 0; // ultimo messaggio della coda MLS già elaborato
 
 // Helper per Base64 sicuro per array binari
 function bytesToBase64(bytes) {
@@ -76,9 +96,53 @@ async function decryptWelcome(ivB64, ctB64, creatorPubKeyB64) {
 // -----------------------------------------------------------------------------
 // LOGICA IBRIDA
 // -----------------------------------------------------------------------------
+function stateKey(identity) {
+    const slot = STATE_KEY_PREFIX + identity;
+    const stored = sessionStorage.getItem(slot);
+    if (stored) return base64ToBytes(stored);
+
+    const key = crypto.getRandomValues(new Uint8Array(32));
+    sessionStorage.setItem(slot, bytesToBase64(key));
+    return key;
+}
+
+// Da chiamare dopo ogni operazione che cambia lo stato MLS
+function persistMlsState() {
+    if (!mlsClient) return;
+    try {
+        mlsClient.save_state(STATE_SLOT_PREFIX + mlsIdentity, stateKey(mlsIdentity));
+    } catch (e) { Output.error("Salvataggio stato MLS fallito", e); }
+}
+
+function groupSlot(roomId) {
+    return `${GROUP_SLOT_PREFIX}${mlsIdentity}:${roomId}`;
+}
+
+// Da chiamare ogni volta che mlsGroupId cambia
+function rememberGroup(roomId) {
+    if (mlsGroupId) localStorage.setItem(groupSlot(roomId), bytesToBase64(mlsGroupId));
+    else localStorage.removeItem(groupSlot(roomId));
+}
+
+// Gruppo salvato per la stanza, se lo stato ripristinato lo contiene ancora
+function restoreGroup(roomId) {
+    const saved = localStorage.getItem(groupSlot(roomId));
+    if (!saved) return null;
+    return mlsClient.group_ids().find(id => bytesToBase64(id) === saved) ?? null;
+}
+
 async function initMlsClient(identity) {
     if (!mlsClient) {
-        mlsClient = new window.SFRAME.WasmMlsClient(identity);
+        try {
+            mlsClient = window.SFRAME.WasmMlsClient.load_state(STATE_SLOT_PREFIX + identity, stateKey(identity)) ?? null;
+        } catch (e) { Output.error("Stato MLS salvato non leggibile, ne creo uno nuovo", e); }
+
+        if (mlsClient) {
+            Output.mls("Stato MLS ripristinato", { identity, groups: mlsClient.group_ids().length });
+        } else {
+            mlsClient = new window.SFRAME.WasmMlsClient(identity);
+        }
+        mlsIdentity = identity;
     }
     return mlsClient;
 }
@@ -88,11 +152,16 @@ export async function mlsJoin(identity, roomId) {
 
     // 1. Facciamo girare OpenMLS per la tesi
     const client = await initMlsClient(identity);
-    let mlsKpB64 = "";
+    mlsGroupId = restoreGroup(roomId);
+    let mlsKpB64;
     try {
-        const mlsKpBytes = client.generate_key_package();
-        mlsKpB64 = bytesToBase64(mlsKpBytes);
-    } catch(e) { Output.error("OpenMLS error", e); }
+        mlsKpB64 = bytesToBase64(client.generate_key_package());
+    } catch(e) {
+        // Senza KeyPackage il server rifiuterebbe comunque il join
+        Output.error("OpenMLS error", e);
+        throw new Error("KeyPackage MLS non generato");
+    }
+    persistMlsState();
 
     // 2. Chiave WebCrypto
     await initCrypto();
@@ -101,10 +170,14 @@ export async function mlsJoin(identity, roomId) {
     const combinedObj = { mls: mlsKpB64, ecdh: myPublicKeyBase64 };
     const combinedB64 = btoa(JSON.stringify(combinedObj));
 
+    // 4. Il server accetta il join solo se firmato con la chiave del KeyPackage
+    const timestamp = Math.floor(Date.now() / 1000);
+    const signature = bytesToBase64(client.sign_join(String(roomId), BigInt(timestamp)));
+
     const resp = await fetch(SERVER_JOIN_PATH, {
         method: "POST",
         headers: { "Content-Type": "application/json" },
-        body: JSON.stringify({ identity, room_id: roomId, key_package: combinedB64 }),
+        body: JSON.stringify({ identity, room_id: String(roomId), key_package: combinedB64, timestamp, signature }),
     });
 
     if (!resp.ok) throw new Error("Join MLS failed");
@@ -113,7 +186,11 @@ export async function mlsJoin(identity, roomId) {
 
     if (data.is_creator) {
         Output.mls("Siamo i creatori! Creazione gruppo MLS locale...");
-        try { client.create_group(); } catch(e) {}
+        try {
+            mlsGroupId = client.create_group();
+            persistMlsState();
+            rememberGroup(roomId);
+        } catch(e) { Output.error("Gruppo MLS non creato", e); }
         
         myMasterSecret = new Uint8Array(32);
         crypto.getRandomValues(myMasterSecret);
@@ -122,7 +199,7 @@ export async function mlsJoin(identity, roomId) {
         const me = data.roster.find(m => m.identity === identity);
         
         if (me && me.welcome_message) {
-            await processCombinedWelcome(me.welcome_message, data.roster);
+            await processCombinedWelcome(me.welcome_message, data.roster, roomId);
         } else {
             Output.mls("⚠️ Welcome non ancora presente.");
         }
@@ -138,16 +215,21 @@ export async function mlsJoin(identity, roomId) {
     };
 }
 
-async function processCombinedWelcome(welcomeB64, roster) {
+async function processCombinedWelcome(welcomeB64, roster, roomId) {
     const combinedWelcome = JSON.parse(atob(welcomeB64));
 
     Output.mls("Welcome trovato! Apertura pacchetto...");
     
     // 1. Diamo il Welcome a OpenMLS per far comparire il log
     try {
-        if (combinedWelcome.mls) mlsClient.process_welcome(base64ToBytes(combinedWelcome.mls));
+        if (combinedWelcome.mls) {
+            mlsGroupId = mlsClient.process_welcome(base64ToBytes(combinedWelcome.mls));
+            persistMlsState();
+            rememberGroup(roomId);
+        }
     } catch (e) {
-        // Ignoriamo l'errore noto in background
+        // Senza gruppo OpenMLS restano solo le chiavi derivate dal segreto ECDH
+        Output.error("Welcome MLS non applicato", e);
     }
 
     // 2. Estraiamo il vero segreto
@@ -178,23 +260,35 @@ export async function mlsResync(identity, roomId, currentInfo) {
             Output.mls(`Generazione Welcome per ${user.identity}...`);
             const userKpObj = JSON.parse(atob(user.key_package));
 
-            // 1. OpenMLS
+            // 1. OpenMLS: il Commit va agli altri membri, il Welcome al nuovo
             let mlsWelcomeB64 = "";
+            let commit = null;
             try {
-                const welcomeBytes = mlsClient.add_member(base64ToBytes(userKpObj.mls));
-                mlsWelcomeB64 = bytesToBase64(welcomeBytes);
-            } catch (e) { }
+                const commitEpoch = Number(mlsClient.epoch(mlsGroupId));
+                const added = mlsClient.add_member(mlsGroupId, base64ToBytes(userKpObj.mls));
+                mlsWelcomeB64 = bytesToBase64(added.welcome);
+                const signature = mlsClient.sign_commit(String(roomId), BigInt(commitEpoch), added.commit);
+                commit = { epoch: commitEpoch, message: bytesToBase64(added.commit), signature: bytesToBase64(signature) };
+                persistMlsState();
+            } catch (e) { Output.error(`add_member MLS fallito per ${user.identity}`, e); }
 
             // 2. WebCrypto
             const ecdhWelcome = await encryptForUser(userKpObj.ecdh, myMasterSecret);
             const combinedWelcome = JSON.stringify({ mls: mlsWelcomeB64, ecdh: ecdhWelcome });
             const combinedWelcomeB64 = btoa(combinedWelcome);
-
-            const resp = await fetch(SERVER_WELCOME_PATH, {
-                method: "POST",
-                headers: { "Content-Type": "application/json" },
-                body: JSON.stringify({ room_id: roomId, target_identity: user.identity, welcome_message: combinedWelcomeB64 }),
-            });
+            const welcome = { target_identity: user.identity, welcome_message: combinedWelcomeB64 };
+
+            const resp = commit
+                ? await fetch(SERVER_COMMIT_PATH, {
+                    method: "POST",
+                    headers: { "Content-Type": "application/json" },
+                    body: JSON.stringify({ room_id: String(roomId), sender_identity: identity, ...commit, welcomes: [welcome] }),
+                })
+                : await fetch(SERVER_WELCOME_PATH, {
+                    method: "POST",
+                    headers: { "Content-Type": "application/json" },
+                    body: JSON.stringify({ room_id: String(roomId), ...welcome }),
+                });
 
             if (resp.ok) {
                 Output.mls(`Welcome per ${user.identity} salvato sul server.`);
@@ -206,10 +300,15 @@ export async function mlsResync(identity, roomId, currentInfo) {
     else if (!currentInfo.master_secret) {
         const me = rosterData.roster.find(m => m.identity === identity);
         if (me && me.welcome_message) {
-            await processCombinedWelcome(me.welcome_message, rosterData.roster);
+            await processCombinedWelcome(me.welcome_message, rosterData.roster, roomId);
             changed = true;
         }
     }
+    // I Commit del creatore fanno avanzare il nostro gruppo MLS, e con esso le chiavi SFrame
+    if (!currentInfo.is_creator && await processPendingCommits(identity, roomId)) {
+        changed = true;
+    }
+
     // 🔴 FIX LOOP: Se l'epoca è cambiata, NON faccio un nuovo mlsJoin!
     // Aggiorno semplicemente il mio stato interno con il nuovo roster.
     else if (rosterData.epoch > currentInfo.epoch) {
@@ -233,6 +332,106 @@ export async function mlsResync(identity, roomId, currentInfo) {
     return { changed: false, info: currentInfo };
 }
 
+// Nuova stanza sul Delivery Service (ID stringa casuale, diverso dalla stanza Janus)
+export async function mlsCreateRoom() {
+    const resp = await fetch(SERVER_CREATE_PATH, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({}),
+    });
+    if (!resp.ok) throw new Error("Create MLS failed");
+
+    const data = await resp.json();
+    sessionStorage.setItem(OWNER_TOKEN_PREFIX + data.room_id, data.owner_token);
+    Output.mls("Stanza MLS creata", { room_id: data.room_id, ttl_secs: data.ttl_secs });
+    return data.room_id;
+}
+
+// Chiude la stanza MLS, solo se l'ha creata questa scheda
+export async function mlsCloseRoom(roomId) {
+    const slot = OWNER_TOKEN_PREFIX + roomId;
+    const ownerToken = sessionStorage.getItem(slot);
+    if (!ownerToken) return false;
+
+    const resp = await fetch(SERVER_CLOSE_PATH, {
+        method: "POST",
+        headers: { "Content-Type": "application/json", "X-Owner-Token": ownerToken },
+        body: JSON.stringify({ room_id: String(roomId) }),
+    });
+    if (!resp.ok) throw new Error("Close MLS failed");
+
+    sessionStorage.removeItem(slot);
+    Output.mls("Stanza MLS chiusa", { room_id: roomId });
+    return true;
+}
+
+// Uscita dalla stanza: come il join, firmata con la chiave del KeyPackage
+export async function mlsLeave(identity, roomId) {
+    if (!mlsClient) return;
+
+    const timestamp = Math.floor(Date.now() / 1000);
+    const signature = bytesToBase64(mlsClient.sign_leave(String(roomId), BigInt(timestamp)));
+
+    const resp = await fetch(SERVER_LEAVE_PATH, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ identity, room_id: String(roomId), timestamp, signature }),
+    });
+    if (!resp.ok) throw new Error("Leave MLS failed");
+
+    const data = await resp.json();
+    Output.mls("Uscita dalla stanza MLS", data);
+    mlsGroupId = null;
+    rememberGroup(roomId);
+    myMasterSecret = null;
+    lastMessageSeq = 0;
+    return data;
+}
+
+// Applica i Commit in coda sul Delivery Service; true se l'epoch MLS è cambiata
+async function processPendingCommits(identity, roomId) {
+    if (!mlsGroupId || !mlsClient.is_active(mlsGroupId)) return false;
+
+    const url = `${SERVER_MESSAGES_PATH}?room_id=${encodeURIComponent(roomId)}&identity=${encodeURIComponent(identity)}&after=${lastMessageSeq}`;
+    const resp = await fetch(url, { method: "GET" });
+    if (!resp.ok) return false;
+    const data = await resp.json();
+
+    const before = mlsClient.epoch(mlsGroupId);
+    for (const msg of data.messages) {
+        lastMessageSeq = Math.max(lastMessageSeq, msg.seq);
+        if (msg.kind !== "commit") continue;
+        try {
+            mlsClient.process_message(mlsGroupId, base64ToBytes(msg.message));
+        } catch (e) {
+            // Commit di epoch già superate (es. prima del nostro Welcome)
+            if (e.code !== "WRONG_EPOCH") Output.error("Commit MLS non applicato", e);
+        }
+    }
+
+    // La GET non consuma la coda: i messaggi elaborati si confermano firmando
+    if (data.messages.length > 0) {
+        const signature = bytesToBase64(mlsClient.sign_ack(String(roomId), BigInt(lastMessageSeq)));
+        await fetch(SERVER_ACK_PATH, {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ room_id: String(roomId), identity, after: lastMessageSeq, signature }),
+        }).catch(e => Output.error("Ack coda MLS fallito", e));
+    }
+
+    if (mlsClient.epoch(mlsGroupId) === before) return false;
+    persistMlsState();
+    Output.mls("Gruppo MLS aggiornato", { epoch: Number(mlsClient.epoch(mlsGroupId)) });
+    return true;
+}
+
+// Gruppo MLS attivo della stanza, per derivare le chiavi SFrame con
+// l'exporter (RFC 9605 5.2); null finché il Welcome OpenMLS non è applicato
+export function mlsSframeGroup() {
+    if (!mlsClient || !mlsGroupId || !mlsClient.is_active(mlsGroupId)) return null;
+    return { client: mlsClient, groupId: mlsGroupId, leafIndex: mlsClient.own_leaf_index(mlsGroupId) };
+}
+
 export async function mlsFetchRoster(roomId) {
     const url = `${SERVER_ROSTER_PATH}?room_id=${encodeURIComponent(roomId)}`;
     const resp = await fetch(url, { method: "GET" });
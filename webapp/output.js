@@ -91,6 +91,7 @@ export const Output = {
 
     // Dettagli extra opzionali
     const extras = [];
+    if (info.suite) extras.push(`suite=${info.suite} (key=${info.key_len}B nonce=${info.nonce_len}B)`);
     if (info.header_hex) extras.push(`header=${info.header_hex}`);
     if (info.nonce_hex) extras.push(`nonce=${info.nonce_hex}`);
     if (info.aad_hex) extras.push(`aad_hex=${info.aad_hex}`);
@@ -59,11 +59,14 @@ app.get("/", (req, res) => {
       <body style="background:#0f172a; color:#e5e7eb; font-family:system-ui;">
         <h1>HTTPS/WSS + MLS + Janus server attivo ✅</h1>
         <ul>
-          <li>Webapp: <code>GET /appRoom.html?room=1234</code> (esempio)</li>
+          <li>Webapp: <code>GET /appRoom.html?room=1234&amp;mls=ID</code> (esempio)</li>
           <li>API nuova stanza: <code>POST /api/new-room</code></li>
           <li>MLS join: <code>POST /mls/join</code> → http://${MLS_HOST}:${MLS_PORT}/mls/join</li>
           <li>MLS welcome: <code>POST /mls/welcome</code> → http://${MLS_HOST}:${MLS_PORT}/mls/welcome</li>
           <li>MLS roster: <code>GET /mls/roster?room_id=ID</code> → http://${MLS_HOST}:${MLS_PORT}/mls/roster</li>
+          <li>MLS leave: <code>POST /mls/leave</code> → http://${MLS_HOST}:${MLS_PORT}/mls/leave</li>
+          <li>MLS create: <code>POST /mls/create</code> → http://${MLS_HOST}:${MLS_PORT}/mls/create</li>
+          <li>MLS close: <code>POST /mls/close</code> → http://${MLS_HOST}:${MLS_PORT}/mls/close</li>
           <li>Janus WS proxy: <code>wss://sframe.local/janus</code> → ${JANUS_WS_URL}</li>
           <li>Janus HTTP backend: <code>${JANUS_HTTP_URL}</code></li>
         </ul>
@@ -190,6 +193,130 @@ app.get("/mls/roster", (req, res) => {
   proxyReq.end();
 });
 
+// ============================================================================
+//  PROXY MLS: POST /mls/leave (firmato dal client)
+// ============================================================================
+
+app.post("/mls/leave", (req, res) => {
+  const payload = JSON.stringify(req.body);
+
+  const options = {
+    hostname: MLS_HOST,
+    port: MLS_PORT,
+    path: "/mls/leave",
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+      "Content-Length": Buffer.byteLength(payload),
+    },
+  };
+
+  const proxyReq = http.request(options, (proxyRes) => {
+    let data = "";
+    proxyRes.on("data", (chunk) => (data += chunk));
+    proxyRes.on("end", () => {
+      try {
+        const json = JSON.parse(data);
+        res.status(proxyRes.statusCode || 200).json(json);
+      } catch (e) {
+        console.error("[MLS proxy] leave parse error:", e.message);
+        res.status(502).json({ error: "Invalid JSON from MLS server" });
+      }
+    });
+  });
+
+  proxyReq.on("error", (err) => {
+    console.error("[MLS proxy] leave error:", err.message);
+    res.status(502).json({ error: "MLS server unreachable" });
+  });
+
+  proxyReq.write(payload);
+  proxyReq.end();
+});
+
+// ============================================================================
+//  PROXY MLS: POST /mls/create (stanza con ID casuale)
+// ============================================================================
+
+app.post("/mls/create", (req, res) => {
+  const payload = JSON.stringify(req.body);
+
+  const options = {
+    hostname: MLS_HOST,
+    port: MLS_PORT,
+    path: "/mls/create",
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+      "Content-Length": Buffer.byteLength(payload),
+    },
+  };
+
+  const proxyReq = http.request(options, (proxyRes) => {
+    let data = "";
+    proxyRes.on("data", (chunk) => (data += chunk));
+    proxyRes.on("end", () => {
+      try {
+        const json = JSON.parse(data);
+        res.status(proxyRes.statusCode || 200).json(json);
+      } catch (e) {
+        console.error("[MLS proxy] create parse error:", e.message);
+        res.status(502).json({ error: "Invalid JSON from MLS server" });
+      }
+    });
+  });
+
+  proxyReq.on("error", (err) => {
+    console.error("[MLS proxy] create error:", err.message);
+    res.status(502).json({ error: "MLS server unreachable" });
+  });
+
+  proxyReq.write(payload);
+  proxyReq.end();
+});
+
+// ============================================================================
+//  PROXY MLS: POST /mls/close (header X-Owner-Token del creatore)
+// ============================================================================
+
+app.post("/mls/close", (req, res) => {
+  const payload = JSON.stringify(req.body);
+
+  const options = {
+    hostname: MLS_HOST,
+    port: MLS_PORT,
+    path: "/mls/close",
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+      "Content-Length": Buffer.byteLength(payload),
+      "X-Owner-Token": req.get("X-Owner-Token") || "",
+    },
+  };
+
+  const proxyReq = http.request(options, (proxyRes) => {
+    let data = "";
+    proxyRes.on("data", (chunk) => (data += chunk));
+    proxyRes.on("end", () => {
+      try {
+        const json = JSON.parse(data);
+        res.status(proxyRes.statusCode || 200).json(json);
+      } catch (e) {
+        console.error("[MLS proxy] close parse error:", e.message);
+        res.status(502).json({ error: "Invalid JSON from MLS server" });
+      }
+    });
+  });
+
+  proxyReq.on("error", (err) => {
+    console.error("[MLS proxy] close error:", err.message);
+    res.status(502).json({ error: "MLS server unreachable" });
+  });
+
+  proxyReq.write(payload);
+  proxyReq.end();
+});
+
 // ============================================================================
 //  HELPER: chiamata HTTP POST JSON a Janus (REST)
 // ============================================================================
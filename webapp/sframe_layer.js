@@ -98,6 +98,47 @@ export function createRxPeer(
   }
 }
 
+// Context SFrame per traccia nel KID MLS (RFC 9605 5.2)
+const MLS_CONTEXT_AUDIO = 0n;
+const MLS_CONTEXT_VIDEO = 1n;
+
+/**
+ * Peer TX con chiavi e KID esportati dal gruppo MLS (epoch corrente,
+ * nostra foglia). Stessa interfaccia di createTxPeer.
+ */
+export function createMlsTxPeer(mlsClient, groupId) {
+  try {
+    const audio = mlsClient.sframe_sender(groupId, "audio", MLS_CONTEXT_AUDIO);
+    const video = mlsClient.sframe_sender(groupId, "video", MLS_CONTEXT_VIDEO);
+    Output.sframe("TX Peer MLS created", { kidAudio: audio.kid, kidVideo: video.kid });
+    return {
+      encrypt_audio: (frame) => audio.encrypt(frame),
+      encrypt_video: (frame) => video.encrypt(frame),
+    };
+  } catch (e) {
+    Output.error("TX Peer MLS creation failed", e);
+    return null;
+  }
+}
+
+/**
+ * Peer RX per la foglia MLS `leafIndex`. Stessa interfaccia di createRxPeer.
+ */
+export function createMlsRxPeer(mlsClient, groupId, leafIndex) {
+  try {
+    const audio = mlsClient.sframe_receiver(groupId, "audio", MLS_CONTEXT_AUDIO, leafIndex);
+    const video = mlsClient.sframe_receiver(groupId, "video", MLS_CONTEXT_VIDEO, leafIndex);
+    Output.sframe("RX Peer MLS created", { leafIndex });
+    return {
+      decrypt_audio: (packet) => audio.decrypt(packet),
+      decrypt_video: (packet) => video.decrypt(packet),
+    };
+  } catch (e) {
+    Output.error("RX Peer MLS creation failed", e);
+    return null;
+  }
+}
+
 // ---------------------------------------------------------------------------
 // TODO (miglioramento API WASM):
 // Oggi WasmPeer è sempre full-duplex (Sender+Receiver per audio/video).